@@ -0,0 +1,167 @@
+//! `cargo udf`: print the SQL needed to install or remove the functions in a
+//! library built with the `udf` crate
+//!
+//! ```sh
+//! cargo build --release
+//! cargo udf create target/release/libmy_udf.so > install.sql
+//! cargo udf drop target/release/libmy_udf.so > uninstall.sql
+//! ```
+//!
+//! Function names, aliases, aggregate-ness and return types are read from
+//! metadata that `#[register]` adds to the library, so nothing is loaded or
+//! executed.
+
+mod meta;
+mod sql;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+Print SQL to install or remove the UDFs in a library built with `udf`
+
+Usage: cargo udf <COMMAND> [OPTIONS] <LIBRARY>
+
+Commands:
+  create    Print `CREATE FUNCTION` statements
+  drop      Print `DROP FUNCTION` statements
+
+Options:
+  --soname <NAME>  Library name to use in `SONAME` (default: LIBRARY's file name)
+  -h, --help       Print this message
+";
+
+/// What to print
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Create,
+    Drop,
+}
+
+/// Parsed command line arguments
+#[derive(Debug, PartialEq, Eq)]
+struct Args {
+    command: Command,
+    library: PathBuf,
+    soname: Option<String>,
+}
+
+impl Args {
+    /// Parse arguments, excluding the program name. `None` means help was
+    /// requested.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter().peekable();
+
+        // `cargo udf ...` runs `cargo-udf udf ...`
+        if args.peek().map(String::as_str) == Some("udf") {
+            args.next();
+        }
+
+        let mut command = None;
+        let mut library = None;
+        let mut soname = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--soname" => {
+                    soname = Some(args.next().ok_or("`--soname` requires a value")?);
+                }
+                "create" if command.is_none() => command = Some(Command::Create),
+                "drop" if command.is_none() => command = Some(Command::Drop),
+                _ if command.is_none() => return Err(format!("unknown command '{arg}'")),
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{arg}'")),
+                _ if library.is_none() => library = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument '{arg}'")),
+            }
+        }
+
+        Ok(Some(Self {
+            command: command.ok_or("a command is required")?,
+            library: library.ok_or("a library path is required")?,
+            soname,
+        }))
+    }
+}
+
+fn run(args: &Args) -> Result<String, String> {
+    let data = fs::read(&args.library)
+        .map_err(|e| format!("unable to read '{}': {e}", args.library.display()))?;
+    let funcs = meta::read_library(&data)?;
+
+    let sql = match args.command {
+        Command::Create => {
+            let soname = match &args.soname {
+                Some(name) => name.clone(),
+                None => file_name(&args.library)?,
+            };
+            sql::create_sql(&funcs, &soname)
+        }
+        Command::Drop => sql::drop_sql(&funcs),
+    };
+
+    Ok(sql)
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_owned)
+        .ok_or_else(|| format!("'{}' has no file name", path.display()))
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(sql) => {
+            print!("{sql}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        Args::parse(args.iter().map(|&s| s.to_owned()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&["udf", "create", "--soname", "x.so", "lib/libfoo.so"]),
+            Ok(Some(Args {
+                command: Command::Create,
+                library: PathBuf::from("lib/libfoo.so"),
+                soname: Some("x.so".to_owned()),
+            }))
+        );
+        assert_eq!(
+            parse(&["drop", "libfoo.so"]).unwrap().unwrap().command,
+            Command::Drop
+        );
+        assert_eq!(parse(&["create", "--help"]), Ok(None));
+        assert!(parse(&["create"]).is_err());
+        assert!(parse(&["install", "libfoo.so"]).is_err());
+        assert!(parse(&["create", "--soname"]).is_err());
+        assert!(parse(&["create", "a.so", "b.so"]).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! Reading the records that `#[register]` adds to a library
+//!
+//! The records are parsed by `udf::manifest`; this only locates the section.
+
+use object::{Object, ObjectSection};
+use udf::manifest::{self, UdfInfo};
+use udf::SqlType;
+
+/// Section names used by `#[register]` on ELF, Apple and Windows targets
+const SECTION_NAMES: [&str; 3] = ["udfmeta", "__udfmeta", ".udfmeta"];
+
+/// Read all functions from the contents of a dynamic library
+pub fn read_library(data: &[u8]) -> Result<Vec<UdfInfo<'_>>, String> {
+    let file = object::File::parse(data).map_err(|e| format!("unable to parse library: {e}"))?;
+    let section = SECTION_NAMES
+        .iter()
+        .find_map(|name| file.section_by_name(name))
+        .ok_or("no UDF metadata found; is this a library built with `udf` and `#[register]`?")?;
+    let bytes = section
+        .data()
+        .map_err(|e| format!("unable to read UDF metadata: {e}"))?;
+
+    manifest::parse_records(bytes).map_err(|e| match e {
+        manifest::ManifestError::UnsupportedVersion => {
+            format!("{e}; cargo-udf may need to be updated")
+        }
+        _ => e.to_string(),
+    })
+}
+
+/// The return type to use in `CREATE FUNCTION`
+pub fn sql_returns(ty: SqlType) -> &'static str {
+    match ty {
+        SqlType::Int => "integer",
+        other => other.display_name(),
+    }
+}
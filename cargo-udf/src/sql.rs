@@ -0,0 +1,78 @@
+//! Generating install and uninstall scripts
+
+use std::fmt::Write;
+
+use udf::manifest::UdfInfo;
+
+use crate::meta::sql_returns;
+
+/// `CREATE FUNCTION` statements for every function and alias
+pub fn create_sql(funcs: &[UdfInfo], soname: &str) -> String {
+    let soname = soname.replace('\'', "''");
+    let mut ret = String::new();
+
+    for func in funcs {
+        let create = if func.kind.is_aggregate() {
+            "CREATE AGGREGATE FUNCTION"
+        } else {
+            "CREATE FUNCTION"
+        };
+        for name in func.all_names() {
+            writeln!(
+                ret,
+                "{create} {name} RETURNS {} SONAME '{soname}';",
+                sql_returns(func.returns)
+            )
+            .unwrap();
+        }
+    }
+
+    ret
+}
+
+/// `DROP FUNCTION` statements for every function and alias
+pub fn drop_sql(funcs: &[UdfInfo]) -> String {
+    let mut ret = String::new();
+
+    for name in funcs.iter().flat_map(UdfInfo::all_names) {
+        writeln!(ret, "DROP FUNCTION IF EXISTS {name};").unwrap();
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use udf::manifest::parse_records;
+
+    const RECORDS: &[u8] = b"\
+        udf1 kind=basic name=udf_attribute returns=string maybe_null=false version=0.1.0 alias=attr\n\
+        udf1 kind=basic name=avg_cost returns=real maybe_null=true version=0.1.0\n\
+        udf1 kind=window name=avg_cost returns=real maybe_null=true version=0.1.0\n\
+        udf1 kind=basic name=sum_int returns=int maybe_null=false version=0.1.0\n";
+
+    #[test]
+    fn test_create_sql() {
+        let funcs = parse_records(RECORDS).unwrap();
+        assert_eq!(
+            create_sql(&funcs, "lib'udf.so"),
+            "CREATE AGGREGATE FUNCTION avg_cost RETURNS real SONAME 'lib''udf.so';\n\
+             CREATE FUNCTION sum_int RETURNS integer SONAME 'lib''udf.so';\n\
+             CREATE FUNCTION udf_attribute RETURNS string SONAME 'lib''udf.so';\n\
+             CREATE FUNCTION attr RETURNS string SONAME 'lib''udf.so';\n"
+        );
+    }
+
+    #[test]
+    fn test_drop_sql() {
+        let funcs = parse_records(RECORDS).unwrap();
+        assert_eq!(
+            drop_sql(&funcs),
+            "DROP FUNCTION IF EXISTS avg_cost;\n\
+             DROP FUNCTION IF EXISTS sum_int;\n\
+             DROP FUNCTION IF EXISTS udf_attribute;\n\
+             DROP FUNCTION IF EXISTS attr;\n"
+        );
+    }
+}
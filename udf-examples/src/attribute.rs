@@ -21,8 +21,11 @@ struct UdfAttribute;
 impl BasicUdf for UdfAttribute {
     type Returns<'a> = String;
 
-    /// Nothing to do here
-    fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+    /// Set the result length to that of all attributes joined
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        let attrs_len: usize = args.iter().map(|arg| arg.attribute().len()).sum();
+        let sep_len = args.len().saturating_sub(1) * 2;
+        cfg.set_max_len((attrs_len + sep_len) as u64);
         Ok(Self)
     }
 
@@ -94,6 +97,17 @@ mod tests {
             None,
         );
 
-        assert_eq!(res, Ok("attr1, attr2, attr3, attr4".to_owned()));
+        assert_eq!(res.unwrap(), "attr1, attr2, attr3, attr4");
+    }
+
+    #[test]
+    fn test_mock_server() {
+        let mut server = MockServer::<UdfAttribute>::new();
+        let args = &mut mock_args![(Int 1, "a", false), (Real 2.0, "b", false)];
+        server.init(args).unwrap();
+
+        let out = server.process(args);
+        assert_eq!(out.as_str(), Some("a, b"));
+        assert!(!out.is_null);
     }
 }
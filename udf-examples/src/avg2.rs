@@ -77,7 +77,7 @@ impl AggregateUdf for Avg2 {
         Ok(())
     }
 
-    /// For MariaDB only:
+    /// For `MariaDB` only:
     fn remove(
         &mut self,
         _cfg: &UdfCfg<Process>,
@@ -90,3 +90,74 @@ impl AggregateUdf for Avg2 {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use udf::mock::*;
+
+    use super::*;
+
+    #[test]
+    fn test_mock_server() {
+        let mut server = MockServer::<Avg2>::new();
+        server.init(&mut mock_args![(Int None, "x", true)]).unwrap();
+        assert_eq!(*server.cfg().max_len(), 20);
+
+        // Empty group
+        server.clear().unwrap();
+        assert!(server.process(&mock_args![(Int None, "x", true)]).is_null);
+
+        // Integers are coerced to reals
+        server.clear().unwrap();
+        server.add(&mock_args![(Int 1, "x", true)]).unwrap();
+        server.add(&mock_args![(Int 2, "x", true)]).unwrap();
+        server.add(&mock_args![(String "6", "x", true)]).unwrap();
+        let args = mock_args![(Int None, "x", true)];
+        assert_eq!(server.process(&args).as_real(), Some(3.0));
+
+        // Window functions remove rows
+        server.remove(&mock_args![(Int 6, "x", true)]).unwrap();
+        assert_eq!(server.process(&args).as_real(), Some(1.5));
+
+        // `add` panics on `NULL`, which sets the error flag for the group
+        assert!(server.add(&args).is_err());
+        assert!(server.process(&args).error.is_some());
+        server.clear().unwrap();
+        assert!(server.process(&args).error.is_none());
+    }
+
+    #[test]
+    fn test_window() {
+        // avg2(v) OVER (PARTITION BY k ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING)
+        let rows = [("a", 1), ("a", 2), ("a", 6), ("a", 10), ("b", 4)]
+            .map(|(key, v)| (key, mock_args![(Int v, "v", false)]));
+        let res = AggregateRunner::<Avg2>::new()
+            .window(1, 1)
+            .run(rows)
+            .unwrap();
+        let avgs: Vec<_> = res.iter().map(|(k, out)| (*k, out.as_real())).collect();
+
+        assert_eq!(
+            avgs,
+            [
+                ("a", Some(1.5)),
+                ("a", Some(3.0)),
+                ("a", Some(6.0)),
+                ("a", Some(8.0)),
+                ("b", Some(4.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_errors() {
+        // `add` panics on `NULL`, which sets the error for `process`. The next
+        // `clear` returns `Ok`, which resets it.
+        let rows = [("a", Some(1)), ("b", Some(3)), ("a", None)]
+            .map(|(key, v)| (key, mock_args![(v, "v", true)]));
+        let res = AggregateRunner::<Avg2>::new().run(rows).unwrap();
+
+        assert!(res[0].1.error.is_some());
+        assert_eq!(res[1].1.as_real(), Some(3.0));
+    }
+}
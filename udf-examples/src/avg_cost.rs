@@ -20,23 +20,14 @@ struct AvgCost {
     total_price: f64,
 }
 
-#[register]
+// Argument count and coercions are handled by `args(...)`
+#[register(args(qty: i64, price: f64))]
 impl BasicUdf for AvgCost {
     type Returns<'a> = Option<f64>
     where
         Self: 'a;
 
-    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
-        if args.len() != 2 {
-            return Err(format!("expected two arguments; got {}", args.len()));
-        }
-
-        let mut a0 = args.get(0).unwrap();
-        let mut a1 = args.get(1).unwrap();
-
-        a0.set_type_coercion(SqlType::Int);
-        a1.set_type_coercion(SqlType::Real);
-
+    fn init(cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
         cfg.set_maybe_null(true);
         cfg.set_decimals(10);
         cfg.set_max_len(20);
@@ -76,9 +67,10 @@ impl AggregateUdf for AvgCost {
         args: &ArgList<Process>,
         _error: Option<NonZeroU8>,
     ) -> Result<(), NonZeroU8> {
-        // We can unwrap because we are guaranteed to have 2 args (from checks in init)
-        let in_qty = args.get(0).unwrap().value().as_int().unwrap();
-        let mut price = args.get(1).unwrap().value().as_real().unwrap();
+        // Types are guaranteed by the signature; rows containing NULL are skipped
+        let Ok((in_qty, mut price)) = Self::typed_args(args) else {
+            return Ok(());
+        };
 
         dbg!(&in_qty, &price, &self);
 
@@ -21,11 +21,14 @@ struct IsConst {
 impl BasicUdf for IsConst {
     type Returns<'a> = &'static str;
 
-    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
         if args.len() != 1 {
             return Err("IS_CONST only accepts one argument".to_owned());
         }
 
+        // Long enough for "not const"
+        cfg.set_max_len(9);
+
         // Get the first argument, check if it is const, and store it in our
         // struct
         Ok(Self {
@@ -43,3 +46,72 @@ impl BasicUdf for IsConst {
         Ok(if self.is_const { "const" } else { "not const" })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use udf::mock::*;
+    use udf::Prepared;
+
+    use super::*;
+
+    thread_local! {
+        /// How many times `init` saw a const first argument, and a non-const
+        /// one that is not nullable (so it is not just a `NULL` constant)
+        static INITS: Cell<(u32, u32)> = const { Cell::new((0, 0)) };
+    }
+
+    /// Length of the first argument, prepared in `init` when it is const
+    struct PreparedLen {
+        len: Prepared<i64>,
+    }
+
+    fn arg_len(value: &SqlResult<'_>) -> Result<i64, ProcessError> {
+        Ok(value.as_bytes().map_or(0, <[u8]>::len).try_into()?)
+    }
+
+    #[register]
+    impl BasicUdf for PreparedLen {
+        type Returns<'a> = i64;
+
+        fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+            if args.is_empty() {
+                return Err("expected an argument".to_owned());
+            }
+            let len = Prepared::new(args, 0, arg_len)?;
+            let maybe_null = args.get(0).unwrap().maybe_null();
+            INITS.with(|c| {
+                let (consts, others) = c.get();
+                match (len.is_const(), maybe_null) {
+                    (true, _) => c.set((consts + 1, others)),
+                    (false, false) => c.set((consts, others + 1)),
+                    (false, true) => (),
+                }
+            });
+            Ok(Self { len })
+        }
+
+        fn process<'a>(
+            &'a mut self,
+            _cfg: &UdfCfg<Process>,
+            args: &ArgList<Process>,
+            _error: Option<NonZeroU8>,
+        ) -> Result<Self::Returns<'a>, ProcessError> {
+            self.len.get(args).copied()
+        }
+    }
+
+    #[test]
+    fn test_fuzz() {
+        check_basic::<IsConst>(200);
+    }
+
+    #[test]
+    fn test_fuzz_prepared() {
+        check_basic::<PreparedLen>(200);
+        let (consts, others) = INITS.with(Cell::get);
+        assert!(consts > 0, "no const arguments in init");
+        assert!(others > 0, "no non-const arguments in init");
+    }
+}
@@ -55,7 +55,7 @@ impl BasicUdf for Lipsum {
             if seed < 0 {
                 return Err(format!("Seed must be a positive integer, got {seed}"));
             }
-        };
+        }
 
         Ok(Self {
             res: String::default(),
@@ -75,13 +75,16 @@ impl BasicUdf for Lipsum {
             .unwrap()
             .value()
             .as_int()
-            .ok_or(ProcessError)?
+            .ok_or_else(|| ProcessError::msg("word count must not be NULL"))?
             .unsigned_abs() as usize;
 
         let res = match args.get(1) {
             Some(v) => {
                 // If we have a seed argument, use it.
-                let seed = v.value().as_int().ok_or(ProcessError)?;
+                let seed = v
+                    .value()
+                    .as_int()
+                    .ok_or_else(|| ProcessError::msg("seed must not be NULL"))?;
                 lipsum_from_seed(n, seed.unsigned_abs())
             }
             None => {
@@ -95,3 +98,15 @@ impl BasicUdf for Lipsum {
         Ok(&self.res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use udf::mock::*;
+
+    use super::*;
+
+    #[test]
+    fn test_fuzz() {
+        check_basic::<Lipsum>(200);
+    }
+}
@@ -1,6 +1,9 @@
 //! A pretty useless function that just writes to the server log whenever it's
 //! used.
 //!
+//! Messages are written both with `udf_log!` and with the `log` crate, which
+//! `udf::logger` sends to the same place.
+//!
 //! # Usage
 //!
 //! ```sql
@@ -17,7 +20,9 @@ impl BasicUdf for LogCalls {
     type Returns<'a> = Option<i64>;
 
     fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+        udf::logger::init();
         udf_log!(Note: "called init!");
+        log::info!("called init through `log`");
         Ok(Self {})
     }
 
@@ -28,6 +33,7 @@ impl BasicUdf for LogCalls {
         _error: Option<NonZeroU8>,
     ) -> Result<Self::Returns<'a>, ProcessError> {
         udf_log!(Note: "called process!");
+        log::info!("called process through `log`");
         Ok(None)
     }
 }
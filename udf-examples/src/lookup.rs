@@ -55,7 +55,8 @@ impl BasicUdf for Lookup6 {
         let arg = args.get(0).unwrap().value();
 
         let Some(hostname) = arg.as_string() else {
-            return Err(ProcessError);
+            return Err(ProcessError::msg("hostname must be a string")
+                .with_kind(ProcessErrorKind::InvalidArgument));
         };
 
         // `to_socket_addrs` checks the given hostname and port (0) and returns
@@ -20,7 +20,9 @@ struct UdfMedian {
 impl BasicUdf for UdfMedian {
     type Returns<'a> = Option<i64>;
 
-    fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+    fn init(cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+        // Groups with no non-null values have no median
+        cfg.set_maybe_null(true);
         Ok(Self { v: Vec::new() })
     }
 
@@ -70,3 +72,34 @@ impl AggregateUdf for UdfMedian {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use udf::mock::*;
+
+    use super::*;
+
+    #[test]
+    fn test_group_by() {
+        let rows = [(1, 5), (2, 7), (1, 3), (1, 9), (2, 1), (1, 4)]
+            .map(|(key, v)| (key, mock_args![(Int v, "v", false)]));
+        let res = AggregateRunner::<UdfMedian>::new().run(rows).unwrap();
+        let medians: Vec<_> = res.iter().map(|(k, out)| (*k, out.as_int())).collect();
+
+        assert_eq!(medians, [(1, Some(5)), (2, Some(7))]);
+    }
+
+    #[test]
+    fn test_null_rows() {
+        let rows = [(), ()].map(|key| (key, mock_args![(Int None, "v", true)]));
+        let res = AggregateRunner::<UdfMedian>::new().run(rows).unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert!(res[0].1.is_null);
+    }
+
+    #[test]
+    fn test_fuzz() {
+        check_aggregate::<UdfMedian>(500);
+    }
+}
@@ -15,26 +15,16 @@ struct UdfSequence {
     last_val: i64,
 }
 
-#[register]
+// The offset is optional; its coercion to an integer and the argument count
+// are handled by `args(...)` and `min_args`
+#[register(args(offset: Option<i64>), min_args = 0)]
 impl BasicUdf for UdfSequence {
     type Returns<'a> = i64
     where
         Self: 'a;
 
-    /// Init just validates the argument count and initializes our empty struct
-    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
-        if args.len() > 1 {
-            return Err(format!(
-                "This function takes 0 or 1 arguments; got {}",
-                args.len()
-            ));
-        }
-
-        // If we have an argument, set its type coercion to an integer
-        if let Some(mut a) = args.get(0) {
-            a.set_type_coercion(SqlType::Int);
-        }
-
+    /// Init just initializes our empty struct
+    fn init(cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
         // Result will differ for each call
         cfg.set_is_const(false);
         Ok(Self { last_val: 0 })
@@ -47,14 +37,11 @@ impl BasicUdf for UdfSequence {
         _error: Option<NonZeroU8>,
     ) -> Result<Self::Returns<'a>, ProcessError> {
         // If we have an argument, that will provide our offset value
-        let arg_val = match args.get(0) {
-            Some(v) => v.value().as_int().unwrap(),
-            None => 0,
-        };
+        let (offset,) = Self::typed_args(args)?;
 
         // Increment our last value, return the total
         self.last_val += 1;
-        Ok(self.last_val + arg_val)
+        Ok(self.last_val + offset.unwrap_or(0))
     }
 }
 
@@ -91,4 +78,25 @@ mod tests {
             assert_eq!(res, *expected);
         }
     }
+
+    #[test]
+    fn test_mock_server() {
+        // The string argument is coerced to an integer by the generated `init`
+        let mut server = MockServer::<UdfSequence>::new();
+        let args = &mut mock_args![("10", "", false)];
+        server.init(args).unwrap();
+
+        assert_eq!(server.process(args).as_int(), Some(11));
+        assert_eq!(server.process(args).as_int(), Some(12));
+
+        let mut server = MockServer::<UdfSequence>::new();
+        let err = server.init(&mut mock_args![(1, "", false), (2, "", false)]);
+        assert_eq!(err.unwrap_err(), "Expected at most 1 argument; got 2");
+
+        // No offset
+        let mut server = MockServer::<UdfSequence>::new();
+        let args = &mut MockArgList::new();
+        server.init(args).unwrap();
+        assert_eq!(server.process(args).as_int(), Some(1));
+    }
 }
@@ -37,8 +37,12 @@ impl BasicUdf for SumInt {
         _error: Option<NonZeroU8>,
     ) -> Result<Self::Returns<'a>, ProcessError> {
         // Iterate all arguments, sum all that are integers. This should
-        // be all of them, since we set coercion
-        Ok(args.iter().filter_map(|arg| arg.value().as_int()).sum())
+        // be all of them, since we set coercion. Like SQL, overflowing is an
+        // error.
+        args.iter()
+            .filter_map(|arg| arg.value().as_int())
+            .try_fold(0i64, i64::checked_add)
+            .ok_or_else(|| ProcessError::msg("BIGINT value is out of range"))
 
         // If you're not familiar with rust's combinators, here's the for loop
         // version:
@@ -52,3 +56,15 @@ impl BasicUdf for SumInt {
         // Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use udf::mock::*;
+
+    use super::*;
+
+    #[test]
+    fn test_fuzz() {
+        check_basic::<SumInt>(500);
+    }
+}
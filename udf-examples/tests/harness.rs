@@ -0,0 +1,142 @@
+//! Load the built example library and call it through its C symbols, without
+//! a database
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+
+use udf_harness::{Arg, Library, Returns};
+
+/// Cargo builds the library into the `deps` directory this test runs from.
+/// After `cargo build`, it is also in the directory above.
+fn open_library() -> Library {
+    let exe = std::env::current_exe().unwrap();
+    let name = format!("{DLL_PREFIX}udf_examples{DLL_SUFFIX}");
+    let path = exe
+        .ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| panic!("{name} not found; run `cargo build` first"));
+    Library::open(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+#[test]
+fn test_symbols() {
+    let lib = open_library();
+
+    assert_eq!(lib.check_symbols(), []);
+    for name in [
+        "avg2",
+        "avg2_add",
+        "avg2_clear",
+        "avg2_remove",
+        "udf_manifest",
+        "udf_stats",
+    ] {
+        assert!(lib.exports().iter().any(|e| e == name), "missing {name}");
+    }
+
+    // A basic function cannot be created as an aggregate
+    assert!(lib.aggregate_function("sum_int", Returns::Integer).is_err());
+    assert!(lib.function("not_a_function", Returns::Integer).is_err());
+}
+
+#[test]
+fn test_sequence() {
+    let lib = open_library();
+    let func = lib.function("udf_sequence", Returns::Integer).unwrap();
+
+    let mut inst = func.init(&[]).unwrap();
+    let res: Vec<_> = (0..3).map(|_| inst.process(&[]).as_int()).collect();
+    assert_eq!(res, [Some(1), Some(2), Some(3)]);
+
+    // Each statement starts again from `init`
+    let mut inst = func.init(&[]).unwrap();
+    assert_eq!(inst.process(&[]).as_int(), Some(1));
+}
+
+#[test]
+fn test_coercion() {
+    let lib = open_library();
+    let func = lib.function("sum_int", Returns::Integer).unwrap();
+
+    let args = [
+        Arg::new(1, "1").constant(),
+        Arg::new("20abc", "b"),
+        Arg::new(2.6, "c"),
+    ];
+    let mut inst = func.init(&args).unwrap();
+    assert!(inst.cfg().const_item);
+    assert_eq!(inst.process(&args).as_int(), Some(24));
+}
+
+#[test]
+fn test_const_args() {
+    let lib = open_library();
+    let func = lib.function("is_const", Returns::String).unwrap();
+
+    let mut inst = func.init(&[Arg::new(1, "1").constant()]).unwrap();
+    let out = inst.process(&[Arg::new(1, "1").constant()]);
+    assert_eq!(out.as_str(), Some("const"));
+
+    let mut inst = func.init(&[Arg::new(1, "val")]).unwrap();
+    let out = inst.process(&[Arg::new(1, "val")]);
+    assert_eq!(out.as_str(), Some("not const"));
+
+    let err = func
+        .init(&[Arg::new(1, "1"), Arg::new(2, "2")])
+        .unwrap_err();
+    assert_eq!(err, "IS_CONST only accepts one argument");
+}
+
+#[test]
+fn test_aggregate() {
+    let lib = open_library();
+    let func = lib.aggregate_function("avg2", Returns::Real).unwrap();
+    assert!(func.has_remove());
+
+    let rows: Vec<_> = [1, 2, 6].map(|v| [Arg::new(v, "v")]).into();
+    let mut inst = func.init(&rows[0]).unwrap();
+    assert!(inst.cfg().maybe_null);
+    assert_eq!(inst.cfg().decimals, 10);
+
+    // SELECT avg2(v) FROM t
+    inst.clear().unwrap();
+    for row in &rows {
+        inst.add(row).unwrap();
+    }
+    assert_eq!(inst.process(&rows[2]).as_real(), Some(3.0));
+
+    inst.remove(&rows[0]).unwrap();
+    assert_eq!(inst.process(&rows[2]).as_real(), Some(4.0));
+
+    // An empty group is `NULL`
+    inst.clear().unwrap();
+    assert!(inst.process(&rows[0]).is_sql_null());
+}
+
+#[test]
+fn test_stats() {
+    let lib = open_library();
+    let func = lib.function("lookup6", Returns::String).unwrap();
+
+    assert!(func.init(&[]).is_err());
+    let args = [Arg::new("0.0.0.0", "host")];
+    let mut inst = func.init(&args).unwrap();
+    assert_eq!(inst.process(&args).as_str(), Some("::ffff:0.0.0.0"));
+    drop(inst);
+
+    let stats = lib.function("udf_stats", Returns::String).unwrap();
+    assert!(stats.init(&[Arg::new(1, "1")]).is_err());
+    let mut inst = stats.init(&[]).unwrap();
+    let out = inst.process(&[]);
+    let json = out.as_str().unwrap();
+    assert!(json.starts_with(r#"{"functions":["#), "{json}");
+    assert!(
+        json.contains(concat!(
+            r#"{"name":"lookup6","init_ok":1,"init_failed":1,"calls":1,"#,
+            r#""nulls":0,"errors":0,"panics":0,"#
+        )),
+        "{json}"
+    );
+}
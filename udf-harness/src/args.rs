@@ -0,0 +1,167 @@
+//! Arguments and their C layout
+
+use std::ffi::{c_char, c_int, c_uint, c_ulong};
+use std::marker::PhantomData;
+use std::ptr;
+
+use udf_sys::server::Value;
+use udf_sys::{Item_result, UDF_ARGS};
+
+/// A single argument to a function, as it appears in a SQL expression
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arg {
+    /// The argument's value for the current row
+    pub value: Value,
+    /// The text of the argument expression, e.g. a column name or `'abc'`
+    pub attribute: String,
+    /// Whether the expression can be `NULL`
+    pub maybe_null: bool,
+    /// Whether the expression is a constant, in which case its value is
+    /// available in `init`
+    pub constant: bool,
+}
+
+impl Arg {
+    /// Create a non-constant argument. It may be `NULL` if `value` is `NULL`.
+    pub fn new(value: impl Into<Value>, attribute: &str) -> Self {
+        let value = value.into();
+        Self {
+            maybe_null: value.is_null(),
+            value,
+            attribute: attribute.to_owned(),
+            constant: false,
+        }
+    }
+
+    /// Mark this argument as a constant, such as a literal
+    #[must_use]
+    pub const fn constant(mut self) -> Self {
+        self.constant = true;
+        self
+    }
+
+    /// Mark this argument as possibly `NULL`, such as a nullable column
+    #[must_use]
+    pub const fn nullable(mut self) -> Self {
+        self.maybe_null = true;
+        self
+    }
+}
+
+/// `UDF_ARGS` and the buffers it points to, borrowing from a slice of [`Arg`]
+///
+/// Types are stored as `c_int` because the UDF may write any value to them
+/// during `init`.
+pub struct RawArgs<'a> {
+    types: Vec<c_int>,
+    ptrs: Vec<*const c_char>,
+    lengths: Vec<c_ulong>,
+    maybe_null: Vec<c_char>,
+    attrs: Vec<*const c_char>,
+    attr_lengths: Vec<c_ulong>,
+    udf_args: UDF_ARGS,
+    _marker: PhantomData<&'a [Arg]>,
+}
+
+impl<'a> RawArgs<'a> {
+    /// Lay out `args` like the server. If `init` is true, only constant
+    /// arguments have values.
+    pub fn new(args: &'a [Arg], init: bool) -> Self {
+        let mut types = Vec::with_capacity(args.len());
+        let mut ptrs = Vec::with_capacity(args.len());
+        let mut lengths = Vec::with_capacity(args.len());
+
+        for arg in args {
+            types.push(arg.value.item_result() as c_int);
+            let (ptr, len): (*const c_char, usize) = match arg.value {
+                _ if init && !arg.constant => (ptr::null(), 0),
+                Value::String(Some(ref v)) => (v.as_ptr().cast(), v.len()),
+                Value::Decimal(Some(ref v)) => (v.as_ptr().cast(), v.len()),
+                Value::Real(Some(ref v)) => (ptr::addr_of!(*v).cast(), 0),
+                Value::Int(Some(ref v)) => (ptr::addr_of!(*v).cast(), 0),
+                _ => (ptr::null(), 0),
+            };
+            ptrs.push(ptr);
+            lengths.push(len as c_ulong);
+        }
+
+        let maybe_null: Vec<c_char> = args.iter().map(|a| c_char::from(a.maybe_null)).collect();
+        let attrs: Vec<*const c_char> = args.iter().map(|a| a.attribute.as_ptr().cast()).collect();
+        let attr_lengths: Vec<c_ulong> =
+            args.iter().map(|a| a.attribute.len() as c_ulong).collect();
+
+        let mut ret = Self {
+            types,
+            ptrs,
+            lengths,
+            maybe_null,
+            attrs,
+            attr_lengths,
+            udf_args: UDF_ARGS {
+                arg_count: args.len() as c_uint,
+                arg_types: ptr::null_mut(),
+                args: ptr::null(),
+                lengths: ptr::null(),
+                maybe_null: ptr::null(),
+                attributes: ptr::null(),
+                attribute_lengths: ptr::null(),
+                extension: ptr::null(),
+            },
+            _marker: PhantomData,
+        };
+
+        // The vectors' buffers do not move when `ret` does
+        ret.udf_args.arg_types = ret.types.as_mut_ptr().cast();
+        ret.udf_args.args = ret.ptrs.as_ptr();
+        ret.udf_args.lengths = ret.lengths.as_ptr();
+        ret.udf_args.maybe_null = ret.maybe_null.as_ptr();
+        ret.udf_args.attributes = ret.attrs.as_ptr();
+        ret.udf_args.attribute_lengths = ret.attr_lengths.as_ptr();
+        ret
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut UDF_ARGS {
+        &mut self.udf_args
+    }
+
+    /// The argument types after `init`. Values that are not valid argument
+    /// types leave the original type in place.
+    pub fn types(&self, args: &[Arg]) -> Vec<Item_result> {
+        self.types
+            .iter()
+            .zip(args)
+            .map(|(&ty, arg)| match Item_result::try_from(ty) {
+                Ok(
+                    res @ (Item_result::STRING_RESULT
+                    | Item_result::REAL_RESULT
+                    | Item_result::INT_RESULT
+                    | Item_result::DECIMAL_RESULT),
+                ) => res,
+                _ => arg.value.item_result(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_args_init() {
+        let args = [Arg::new(1, "a").constant(), Arg::new("b", "col_b")];
+        let mut raw = RawArgs::new(&args, true);
+        let udf_args = unsafe { &*raw.as_mut_ptr() };
+
+        assert_eq!(udf_args.arg_count, 2);
+        unsafe {
+            assert_eq!(ptr::read_unaligned((*udf_args.args).cast::<i64>()), 1);
+            assert!((*udf_args.args.add(1)).is_null());
+            assert_eq!(*udf_args.attribute_lengths.add(1), 5);
+        }
+        assert_eq!(
+            raw.types(&args),
+            [Item_result::INT_RESULT, Item_result::STRING_RESULT]
+        );
+    }
+}
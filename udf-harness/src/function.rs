@@ -0,0 +1,444 @@
+//! Resolving a function's symbols and calling them
+
+use std::ffi::{c_char, c_uchar, c_ulong};
+use std::marker::PhantomData;
+use std::num::NonZeroU8;
+use std::{ptr, slice, str};
+
+use udf_sys::server::{CallFlags, Value};
+use udf_sys::{Item_result, UDF_ARGS, UDF_INIT};
+
+use crate::args::{Arg, RawArgs};
+use crate::library::LoadError;
+
+/// Size of the message buffer passed to `xxx_init`
+const ERRMSG_SIZE: usize = 512;
+
+/// Size of the result buffer passed to string functions
+const RESULT_BUFFER_SIZE: usize = 255;
+
+/// A symbol before its signature is known
+type RawFn = unsafe extern "C" fn();
+type InitFn = unsafe extern "C" fn(*mut UDF_INIT, *mut UDF_ARGS, *mut c_char) -> bool;
+type DeinitFn = unsafe extern "C" fn(*mut UDF_INIT);
+type IntFn =
+    unsafe extern "C" fn(*mut UDF_INIT, *const UDF_ARGS, *mut c_uchar, *mut c_uchar) -> i64;
+type RealFn =
+    unsafe extern "C" fn(*mut UDF_INIT, *const UDF_ARGS, *mut c_uchar, *mut c_uchar) -> f64;
+type BufFn = unsafe extern "C" fn(
+    *mut UDF_INIT,
+    *const UDF_ARGS,
+    *mut c_char,
+    *mut c_ulong,
+    *mut c_uchar,
+    *mut c_uchar,
+) -> *mut c_char;
+type AddFn = unsafe extern "C" fn(*mut UDF_INIT, *const UDF_ARGS, *mut c_uchar, *mut c_uchar);
+type ClearFn = unsafe extern "C" fn(*mut UDF_INIT, *mut c_uchar, *mut c_uchar);
+
+/// The return type given in `CREATE FUNCTION ... RETURNS`, which determines
+/// the signature used to call `xxx`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Returns {
+    /// `RETURNS STRING`
+    String,
+    /// `RETURNS INTEGER`
+    Integer,
+    /// `RETURNS REAL`
+    Real,
+    /// `RETURNS DECIMAL`
+    Decimal,
+}
+
+/// The process function, with the signature chosen by [`Returns`]
+#[derive(Clone, Copy, Debug)]
+enum ProcessSym {
+    Int(IntFn),
+    Real(RealFn),
+    Buf(BufFn),
+}
+
+/// A function resolved from a [`Library`](crate::Library)
+///
+/// Call [`init`](Self::init) to start a statement, which returns an
+/// [`Instance`] used for the remaining calls.
+#[derive(Clone, Copy, Debug)]
+pub struct Function<'lib> {
+    returns: Returns,
+    init: Option<InitFn>,
+    deinit: Option<DeinitFn>,
+    process: ProcessSym,
+    clear: Option<ClearFn>,
+    add: Option<AddFn>,
+    remove: Option<AddFn>,
+    _marker: PhantomData<&'lib libloading::Library>,
+}
+
+impl<'lib> Function<'lib> {
+    /// Look up the symbols for `name`, with the same requirements as the
+    /// server
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required symbol is not exported.
+    pub fn resolve(
+        lib: &'lib libloading::Library,
+        name: &str,
+        returns: Returns,
+        aggregate: bool,
+    ) -> Result<Self, LoadError> {
+        // SAFETY: the symbols are given the signatures the server uses. The
+        // copied pointers cannot outlive `lib` because of `'lib`.
+        let get = |suffix: &str| -> Option<RawFn> {
+            let symbol = format!("{name}{suffix}");
+            unsafe { lib.get::<RawFn>(symbol.as_bytes()) }
+                .ok()
+                .map(|sym| *sym)
+        };
+        let require = |suffix: &str| {
+            get(suffix).ok_or_else(|| LoadError::MissingSymbol(format!("{name}{suffix}")))
+        };
+
+        let main = require("")?;
+        let (clear, add) = if aggregate {
+            (Some(require("_clear")?), Some(require("_add")?))
+        } else {
+            (None, None)
+        };
+
+        // SAFETY: transmuting between function pointer types; each is only
+        // called with the arguments its signature describes
+        unsafe {
+            let process = match returns {
+                Returns::Integer => ProcessSym::Int(std::mem::transmute::<RawFn, IntFn>(main)),
+                Returns::Real => ProcessSym::Real(std::mem::transmute::<RawFn, RealFn>(main)),
+                Returns::String | Returns::Decimal => {
+                    ProcessSym::Buf(std::mem::transmute::<RawFn, BufFn>(main))
+                }
+            };
+
+            Ok(Self {
+                returns,
+                init: get("_init").map(|f| std::mem::transmute(f)),
+                deinit: get("_deinit").map(|f| std::mem::transmute(f)),
+                process,
+                clear: clear.map(|f| std::mem::transmute(f)),
+                add: add.map(|f| std::mem::transmute(f)),
+                remove: aggregate
+                    .then(|| get("_remove"))
+                    .flatten()
+                    .map(|f| std::mem::transmute(f)),
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    /// The return type this function was resolved with
+    pub fn returns(&self) -> Returns {
+        self.returns
+    }
+
+    /// True if this was resolved as an aggregate function
+    pub fn is_aggregate(&self) -> bool {
+        self.add.is_some()
+    }
+
+    /// True if this is an aggregate function that exports `xxx_remove`, so it
+    /// can be used as a window function
+    pub fn has_remove(&self) -> bool {
+        self.remove.is_some()
+    }
+
+    /// Start a statement by calling `xxx_init`, if it exists
+    ///
+    /// As in the server, only constant arguments have values during `init`.
+    /// `UDF_INIT` is set up from the arguments: `maybe_null` if any argument
+    /// may be `NULL`, `const_item` if all are constant, and `max_length` from
+    /// the return type or the longest string argument.
+    ///
+    /// # Errors
+    ///
+    /// Returns the message written by `init` if it fails. `xxx_deinit` is not
+    /// called in this case.
+    pub fn init(&self, args: &[Arg]) -> Result<Instance<'lib>, String> {
+        let max_length = match self.returns {
+            Returns::Integer => 21,
+            Returns::Real => 23,
+            Returns::String | Returns::Decimal => args
+                .iter()
+                .filter_map(|arg| match arg.value {
+                    Value::String(Some(ref v)) => Some(v.len()),
+                    Value::Decimal(Some(ref v)) => Some(v.len()),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0),
+        };
+
+        let mut initid = Box::new(UDF_INIT {
+            maybe_null: args.iter().any(|arg| arg.maybe_null),
+            decimals: 0,
+            max_length: max_length as c_ulong,
+            ptr: ptr::null_mut(),
+            const_item: args.iter().all(|arg| arg.constant),
+            extension: ptr::null_mut(),
+        });
+
+        let mut raw = RawArgs::new(args, true);
+        if let Some(init) = self.init {
+            let mut message = [0u8; ERRMSG_SIZE];
+            let failed = unsafe {
+                init(
+                    &mut *initid,
+                    raw.as_mut_ptr(),
+                    message.as_mut_ptr().cast::<c_char>(),
+                )
+            };
+            if failed {
+                let len = message
+                    .iter()
+                    .position(|&b| b == 0)
+                    .unwrap_or(message.len());
+                return Err(String::from_utf8_lossy(&message[..len]).into_owned());
+            }
+        }
+
+        Ok(Instance {
+            func: *self,
+            initid,
+            types: raw.types(args),
+            flags: CallFlags::new(),
+        })
+    }
+}
+
+/// A function during a statement, after a successful `xxx_init`
+///
+/// `xxx_deinit` is called when this is dropped.
+#[derive(Debug)]
+pub struct Instance<'lib> {
+    func: Function<'lib>,
+    /// Boxed so the UDF may keep pointers to it
+    initid: Box<UDF_INIT>,
+    /// Argument types after `init`, which may include coercions
+    types: Vec<Item_result>,
+    /// Flags passed to the function
+    flags: CallFlags,
+}
+
+impl Instance<'_> {
+    /// The `UDF_INIT` shared by all calls, as last updated by the function
+    pub fn cfg(&self) -> &UDF_INIT {
+        &self.initid
+    }
+
+    /// The argument types after `init`, including any coercions it set
+    pub fn arg_types(&self) -> &[Item_result] {
+        &self.types
+    }
+
+    /// Call `xxx` with a row of arguments, after applying coercions from
+    /// `init`
+    ///
+    /// Once a basic function sets `error`, later calls return `NULL` without
+    /// calling it. Aggregate functions receive the current `error` instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of arguments is different from that given to
+    /// `init`.
+    pub fn process(&mut self, args: &[Arg]) -> Output {
+        let args = self.coerce(args);
+
+        if !self.flags.start_process() {
+            return Output {
+                value: self.null_value(),
+                is_null: true,
+                error: self.flags.error(),
+            };
+        }
+
+        let mut raw = RawArgs::new(&args, false);
+        let initid: *mut UDF_INIT = &mut *self.initid;
+        let args_ptr = raw.as_mut_ptr();
+        let (is_null, error) = self.flags.as_mut_ptrs();
+
+        let value = match self.func.process {
+            ProcessSym::Int(f) => Value::Int(Some(unsafe { f(initid, args_ptr, is_null, error) })),
+            ProcessSym::Real(f) => {
+                Value::Real(Some(unsafe { f(initid, args_ptr, is_null, error) }))
+            }
+            ProcessSym::Buf(f) => {
+                let mut result = [0u8; RESULT_BUFFER_SIZE];
+                let mut length = RESULT_BUFFER_SIZE as c_ulong;
+                let res_ptr: *mut c_char = result.as_mut_ptr().cast();
+                let ret = unsafe { f(initid, args_ptr, res_ptr, &mut length, is_null, error) };
+
+                // The result may point into our buffer or to memory owned by
+                // the function, which stays valid until the next call
+                let bytes = (!ret.is_null()).then(|| {
+                    unsafe { slice::from_raw_parts(ret.cast::<u8>(), length as usize) }.to_vec()
+                });
+                match self.func.returns {
+                    Returns::Decimal => {
+                        Value::Decimal(bytes.map(|b| String::from_utf8_lossy(&b).into_owned()))
+                    }
+                    _ => Value::String(bytes),
+                }
+            }
+        };
+
+        Output {
+            value,
+            is_null: self.flags.is_null(),
+            error: self.flags.error(),
+        }
+    }
+
+    /// Call `xxx_clear` to start a new group. `is_null` is reset first, but
+    /// `error` is kept from the previous group.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value of `error` if it is set after the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function was not resolved as an aggregate.
+    pub fn clear(&mut self) -> Result<(), NonZeroU8> {
+        let clear = self.func.clear.expect("not an aggregate function");
+        self.flags.start_clear();
+
+        let (is_null, error) = self.flags.as_mut_ptrs();
+        unsafe { clear(&mut *self.initid, is_null, error) };
+        self.flags.error_result()
+    }
+
+    /// Call `xxx_add` with a row of arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns the value of `error` if it is set after the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function was not resolved as an aggregate, or if the
+    /// number of arguments is different from that given to `init`.
+    pub fn add(&mut self, args: &[Arg]) -> Result<(), NonZeroU8> {
+        let add = self.func.add.expect("not an aggregate function");
+        self.call_add_fn(add, args)
+    }
+
+    /// Call `xxx_remove` with a row of arguments, as the server does for
+    /// window functions
+    ///
+    /// # Errors
+    ///
+    /// Returns the value of `error` if it is set after the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function does not export `xxx_remove`, in addition to
+    /// the conditions for [`add`](Self::add).
+    pub fn remove(&mut self, args: &[Arg]) -> Result<(), NonZeroU8> {
+        let remove = self.func.remove.expect("function has no `remove`");
+        self.call_add_fn(remove, args)
+    }
+
+    fn call_add_fn(&mut self, f: AddFn, args: &[Arg]) -> Result<(), NonZeroU8> {
+        let args = self.coerce(args);
+        let mut raw = RawArgs::new(&args, false);
+        self.flags.start_add();
+
+        let (is_null, error) = self.flags.as_mut_ptrs();
+        unsafe { f(&mut *self.initid, raw.as_mut_ptr(), is_null, error) };
+        self.flags.error_result()
+    }
+
+    /// Copy the arguments with coercions from `init` applied
+    fn coerce(&self, args: &[Arg]) -> Vec<Arg> {
+        assert_eq!(
+            args.len(),
+            self.types.len(),
+            "argument count must be the same as for `init`"
+        );
+        args.iter()
+            .zip(&self.types)
+            .map(|(arg, &ty)| Arg {
+                value: arg.value.coerce(ty),
+                ..arg.clone()
+            })
+            .collect()
+    }
+
+    /// The value for a result that was not computed
+    fn null_value(&self) -> Value {
+        match self.func.returns {
+            Returns::String => Value::String(None),
+            Returns::Integer => Value::Int(None),
+            Returns::Real => Value::Real(None),
+            Returns::Decimal => Value::Decimal(None),
+        }
+    }
+}
+
+impl Drop for Instance<'_> {
+    fn drop(&mut self) {
+        if let Some(deinit) = self.func.deinit {
+            // SAFETY: `init` succeeded, so `deinit` must be called exactly once
+            unsafe { deinit(&mut *self.initid) };
+        }
+    }
+}
+
+/// The result of calling `xxx`
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Output {
+    /// The returned value. Strings and decimals are `NULL` if the returned
+    /// pointer was null.
+    pub value: Value,
+    /// Whether the function set `is_null`
+    pub is_null: bool,
+    /// The value of the `error` flag after the call, if set
+    pub error: Option<NonZeroU8>,
+}
+
+impl Output {
+    /// True if the server would see `NULL`: `is_null` or `error` is set, or
+    /// the value is `NULL`
+    pub fn is_sql_null(&self) -> bool {
+        self.is_null || self.error.is_some() || self.value.is_null()
+    }
+
+    /// The integer result, unless it is `NULL`
+    pub fn as_int(&self) -> Option<i64> {
+        match self.value {
+            Value::Int(v) if !self.is_sql_null() => v,
+            _ => None,
+        }
+    }
+
+    /// The real result, unless it is `NULL`
+    pub fn as_real(&self) -> Option<f64> {
+        match self.value {
+            Value::Real(v) if !self.is_sql_null() => v,
+            _ => None,
+        }
+    }
+
+    /// The string or decimal result, unless it is `NULL`
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.value {
+            Value::String(Some(ref v)) if !self.is_sql_null() => Some(v),
+            Value::Decimal(Some(ref v)) if !self.is_sql_null() => Some(v.as_bytes()),
+            _ => None,
+        }
+    }
+
+    /// The string or decimal result as UTF-8, unless it is `NULL` or invalid
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes().and_then(|b| str::from_utf8(b).ok())
+    }
+}
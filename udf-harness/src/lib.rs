@@ -0,0 +1,52 @@
+//! Load a compiled UDF library and call its functions the way `MariaDB` and
+//! `MySQL` do, without a running server
+//!
+//! [`Library::open`] loads a library with the system's dynamic loader, as
+//! `CREATE FUNCTION ... SONAME` does. Functions are resolved by name with
+//! [`Library::function`] or [`Library::aggregate_function`], which look up
+//! `xxx`, `xxx_init`, `xxx_deinit` (and `xxx_clear`, `xxx_add` and
+//! `xxx_remove`) with the same requirements as the server. They are then called
+//! with a `UDF_INIT` and `UDF_ARGS` laid out as the server would, including
+//! argument coercions requested in `init`.
+//!
+//! [`Library::check_symbols`] checks that every function in the library
+//! exports a complete set of symbols.
+//!
+//! This works with any UDF library, not just those built with the `udf` crate,
+//! and is intended for ABI tests in CI.
+//!
+//! ```no_run
+//! use udf_harness::{Arg, Library, Returns};
+//!
+//! let lib = Library::open("target/release/libmy_udf.so").unwrap();
+//! assert!(lib.check_symbols().is_empty());
+//!
+//! // CREATE AGGREGATE FUNCTION my_sum RETURNS integer SONAME 'libmy_udf.so';
+//! let func = lib.aggregate_function("my_sum", Returns::Integer).unwrap();
+//!
+//! // SELECT my_sum(x) FROM t
+//! let rows = [Arg::new(1, "x"), Arg::new(2, "x")];
+//! let mut inst = func.init(&rows[..1]).unwrap();
+//! inst.clear().unwrap();
+//! for row in &rows {
+//!     inst.add(std::slice::from_ref(row)).unwrap();
+//! }
+//! assert_eq!(inst.process(&rows[1..]).as_int(), Some(3));
+//! ```
+
+#![warn(clippy::pedantic, clippy::nursery, clippy::str_to_string)]
+#![allow(
+    clippy::missing_const_for_fn,
+    clippy::must_use_candidate,
+    clippy::cast_possible_truncation,
+    clippy::module_name_repetitions
+)]
+
+mod args;
+mod function;
+mod library;
+
+pub use args::Arg;
+pub use function::{Function, Instance, Output, Returns};
+pub use library::{Library, LoadError, SymbolIssue};
+pub use udf_sys::server::Value;
@@ -0,0 +1,273 @@
+//! Loading a library and checking its exported symbols
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use object::{BinaryFormat, Object};
+
+use crate::function::{Function, Returns};
+
+/// Suffixes of the functions that belong to a UDF
+const SUFFIXES: [&str; 5] = ["_init", "_deinit", "_add", "_clear", "_remove"];
+
+/// An error loading a library or resolving a function
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The file could not be read or parsed
+    Read(String),
+    /// The dynamic loader failed to open the library
+    Open(String),
+    /// A symbol required by `CREATE FUNCTION` is not exported
+    MissingSymbol(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Read(ref msg) => write!(f, "unable to read library: {msg}"),
+            Self::Open(ref msg) => write!(f, "unable to open library: {msg}"),
+            Self::MissingSymbol(ref sym) => write!(f, "can't find symbol '{sym}' in library"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// A problem with the set of symbols exported for a function, found by
+/// [`Library::check_symbols`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolIssue {
+    /// The function's name
+    pub function: String,
+    /// The symbol that should exist but does not
+    pub missing: String,
+}
+
+impl fmt::Display for SymbolIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "function `{}` is missing `{}`",
+            self.function, self.missing
+        )
+    }
+}
+
+/// A UDF library loaded with the system's dynamic loader, as the server
+/// loads it for `CREATE FUNCTION ... SONAME`
+///
+/// Loading a library runs its initializers, so only load trusted libraries.
+#[derive(Debug)]
+pub struct Library {
+    path: PathBuf,
+    exports: Vec<String>,
+    lib: libloading::Library,
+}
+
+impl Library {
+    /// Read the exported symbols from a library, then load it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be parsed as a dynamic library, or
+    /// if the dynamic loader fails.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| LoadError::Read(e.to_string()))?;
+        let exports = read_exports(&data)?;
+
+        // SAFETY: the caller is responsible for what the library runs when
+        // loaded, as with `CREATE FUNCTION`
+        let lib = unsafe { libloading::Library::new(path) }
+            .map_err(|e| LoadError::Open(e.to_string()))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            exports,
+            lib,
+        })
+    }
+
+    /// The path the library was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Names of all exported symbols, sorted
+    pub fn exports(&self) -> &[String] {
+        &self.exports
+    }
+
+    /// Check that the exported symbols form complete UDFs
+    ///
+    /// Any `xxx_init` or `xxx_deinit` is taken to belong to a function `xxx`,
+    /// unless it is a function with its own `_init` or `_deinit`.
+    /// Each such function must export `xxx`, `xxx_init` and `xxx_deinit`.
+    /// Functions that export any of `xxx_add`, `xxx_clear` or `xxx_remove` are
+    /// aggregates, and must export both `xxx_add` and `xxx_clear`.
+    pub fn check_symbols(&self) -> Vec<SymbolIssue> {
+        check_exports(&self.exports)
+    }
+
+    /// Resolve a function as `CREATE FUNCTION name RETURNS ...` does
+    ///
+    /// `xxx` must exist; `xxx_init` and `xxx_deinit` are used if they exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required symbol is not exported.
+    pub fn function(&self, name: &str, returns: Returns) -> Result<Function<'_>, LoadError> {
+        Function::resolve(&self.lib, name, returns, false)
+    }
+
+    /// Resolve a function as `CREATE AGGREGATE FUNCTION name RETURNS ...` does
+    ///
+    /// In addition to the symbols used by [`function`](Self::function),
+    /// `xxx_clear` and `xxx_add` must exist. `xxx_remove` is used for window
+    /// functions if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required symbol is not exported.
+    pub fn aggregate_function(
+        &self,
+        name: &str,
+        returns: Returns,
+    ) -> Result<Function<'_>, LoadError> {
+        Function::resolve(&self.lib, name, returns, true)
+    }
+}
+
+/// Names of the defined, exported symbols in a library
+fn read_exports(data: &[u8]) -> Result<Vec<String>, LoadError> {
+    let file = object::File::parse(data).map_err(|e| LoadError::Read(e.to_string()))?;
+    let exports = file.exports().map_err(|e| LoadError::Read(e.to_string()))?;
+
+    let mut names: Vec<String> = exports
+        .iter()
+        .map(|exp| {
+            let name = String::from_utf8_lossy(exp.name());
+            // Mach-O symbols have a leading underscore that `dlsym` adds
+            match file.format() {
+                BinaryFormat::MachO => name.strip_prefix('_').unwrap_or(&name).to_owned(),
+                _ => name.into_owned(),
+            }
+        })
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    Ok(names)
+}
+
+/// Find functions with incomplete symbol sets. `exports` must be sorted.
+fn check_exports(exports: &[String]) -> Vec<SymbolIssue> {
+    let has = |name: &str| exports.binary_search_by(|e| e.as_str().cmp(name)).is_ok();
+
+    // A symbol such as `foo_deinit` may be a function itself, if it has its
+    // own `foo_deinit_init` or `foo_deinit_deinit`. Then it says nothing
+    // about `foo`.
+    let candidates: Vec<(&str, &str)> = exports
+        .iter()
+        .filter_map(|e| {
+            let base = e
+                .strip_suffix(SUFFIXES[0])
+                .or_else(|| e.strip_suffix(SUFFIXES[1]))?;
+            Some((base, e.as_str()))
+        })
+        .collect();
+    let mut functions: Vec<&str> = candidates
+        .iter()
+        .filter(|cand| !candidates.iter().any(|other| other.0 == cand.1))
+        .map(|cand| cand.0)
+        .collect();
+    functions.sort_unstable();
+    functions.dedup();
+
+    let mut issues = Vec::new();
+    for func in functions {
+        let is_agg = SUFFIXES[2..]
+            .iter()
+            .any(|suffix| has(&format!("{func}{suffix}")));
+        let required: &[&str] = if is_agg {
+            &["", "_init", "_deinit", "_add", "_clear"]
+        } else {
+            &["", "_init", "_deinit"]
+        };
+
+        for suffix in required {
+            let symbol = format!("{func}{suffix}");
+            if !has(&symbol) {
+                issues.push(SymbolIssue {
+                    function: func.to_owned(),
+                    missing: symbol,
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(names: &[&str]) -> Vec<(String, String)> {
+        let mut exports: Vec<String> = names.iter().map(|&n| n.to_owned()).collect();
+        exports.sort_unstable();
+        check_exports(&exports)
+            .into_iter()
+            .map(|issue| (issue.function, issue.missing))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_complete() {
+        let names = [
+            "basic",
+            "basic_init",
+            "basic_deinit",
+            "agg",
+            "agg_init",
+            "agg_deinit",
+            "agg_add",
+            "agg_clear",
+            "my_add",
+            "my_add_init",
+            "my_add_deinit",
+            "unrelated",
+        ];
+        assert!(issues(&names).is_empty());
+    }
+
+    #[test]
+    fn test_check_missing() {
+        let names = [
+            "no_main_init",
+            "no_main_deinit",
+            "no_deinit",
+            "no_deinit_init",
+            "agg",
+            "agg_init",
+            "agg_deinit",
+            "agg_add",
+            "win",
+            "win_init",
+            "win_deinit",
+            "win_add",
+            "win_remove",
+        ];
+        let expected = [
+            ("agg", "agg_clear"),
+            ("no_deinit", "no_deinit_deinit"),
+            ("no_main", "no_main"),
+            ("win", "win_clear"),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(f, m)| (f.to_owned(), m.to_owned()))
+            .collect();
+        assert_eq!(issues(&names), expected);
+    }
+}
@@ -11,9 +11,12 @@
     clippy::must_use_candidate,
     clippy::cast_possible_truncation
 )]
+// Proc macro entrypoints are only considered public items in the test harness
+#![cfg_attr(test, allow(clippy::missing_inline_in_public_items))]
 
 mod register;
 mod types;
+mod udf_args;
 
 use proc_macro::TokenStream;
 
@@ -69,6 +72,36 @@ pub(crate) use match_variant;
 ///   by default).
 /// - `#[udf::register(alias = "alias")]` will specify an alias for this function.
 ///   More than one alias can be specified, and it can be combined with a `name` attribute.
+/// - `#[udf::register(args(name: &str, count: Option<i64>))]` declares a typed
+///   signature. The generated `init` checks the number of arguments and sets
+///   type coercions before calling `BasicUdf::init`, and `process` can use
+///   `Self::typed_args(args)?` to get a tuple of values. See `udf::FromSqlArg`
+///   for the accepted types; use `Option<T>` to accept `NULL`. Arguments take
+///   the same `#[udf(...)]` attributes as `#[derive(UdfArgs)]` fields, so
+///   `args(sep: &str, #[udf(variadic, coerce = "real")] values: Vec<f64>)`
+///   accepts any number of trailing arguments. This may only be specified on
+///   the `BasicUdf` implementation.
+/// - `#[udf::register(min_args = 1, max_args = 3)]` limits the number of
+///   arguments, which the generated `init` checks before calling
+///   `BasicUdf::init`. With `args(...)`, the count comes from the signature;
+///   `min_args` makes the arguments after it optional (they must be
+///   `Option<T>` and are `None` when left out), and `max_args` caps a
+///   variadic tail.
+/// - `#[udf::register(returns = "int")]` sets the SQL return type (`"int"`,
+///   `"real"`, `"string"`, or `"decimal"`). This is only needed if `Returns`
+///   is a type alias or custom `ToSqlReturn` type that is not a string, since
+///   primitive integers and floats are recognized. A mismatch with
+///   `ToSqlReturn::SQL_TYPE` is a compile error. This may only be specified
+///   on the `BasicUdf` implementation.
+/// - `#[udf::register(enforce_max_len)]` makes the `max_len` set in `init` a
+///   hard limit on string results. A longer result is logged as an error and
+///   that row returns `NULL`, rather than the result being handed to the
+///   server; later rows are not affected. `maybe_null` is set after `init`
+///   since any row may be `NULL`. This is meant for
+///   functions whose output can grow large, such as compressors, where
+///   `init` can set `max_len` from `ArgList::total_max_len` or
+///   `MaxLenOptions::blob_for`. This may only be specified on the `BasicUdf`
+///   implementation.
 ///
 /// **IMPORTANT**: if using aggregate UDFs, the exact same renaming must be applied to
 /// both the `impl BasicUdf` and the `impl AggregateUdf` blocks! If this is not followed,
@@ -78,3 +111,43 @@ pub fn register(args: TokenStream, item: TokenStream) -> TokenStream {
     // Keep this file clean by keeping the dirty work in entry
     register::register(&args, item)
 }
+
+/// # Derive `UdfArgs` for a struct of arguments
+///
+/// This maps each field of a struct onto a positional argument, in order.
+/// Field types must implement `udf::FromSqlArg` (e.g. `i64`, `f64`, `&str`,
+/// `String`); use `Option<T>` for arguments that may be `NULL`. The struct may
+/// have at most one lifetime, which is used for borrowed fields.
+///
+/// ```ignore
+/// #[derive(UdfArgs)]
+/// struct MyArgs<'a> {
+///     name: &'a str,
+///     #[udf(coerce = "real")]
+///     weight: Option<f64>,
+///     #[udf(variadic)]
+///     values: Vec<i64>,
+/// }
+///
+/// // In `init`
+/// MyArgs::validate(args)?;
+///
+/// // In `process`
+/// let MyArgs { name, weight, values } = MyArgs::extract(args)?;
+/// ```
+///
+/// # Field attributes
+///
+/// - `#[udf(coerce = "int")]`: coerce to a specific type (`int`, `real`,
+///   `string` or `decimal`) rather than the type's default. The field type
+///   must be able to read the coerced value, i.e. its `FromSqlArg::SQL_TYPE`
+///   must be the same type, `None`, or a string type with `decimal`; anything
+///   else is a compile error (except on structs with type parameters).
+/// - `#[udf(const_required)]`: fail `init` if the argument is not a constant
+/// - `#[udf(variadic)]`: the last field may be a `Vec<T>`, which collects all
+///   remaining arguments. The signature then accepts at least as many
+///   arguments as there are other fields.
+#[proc_macro_derive(UdfArgs, attributes(udf))]
+pub fn derive_udf_args(item: TokenStream) -> TokenStream {
+    udf_args::derive_udf_args(item)
+}
@@ -8,14 +8,16 @@ use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream, Parser};
 use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
 use syn::{
-    parse_macro_input, parse_quote, DeriveInput, Error, Expr, ExprLit, Ident, ImplItem,
-    ImplItemType, Item, ItemImpl, Lit, LitStr, Meta, Path, PathSegment, Token, Type, TypePath,
-    TypeReference,
+    parse_macro_input, parse_quote, Attribute, DeriveInput, Error, Expr, ExprLit, Ident, ImplItem,
+    ImplItemType, Item, ItemImpl, Lifetime, Lit, LitStr, Meta, MetaList, Path, PathSegment, Token,
+    Type, TypePath, TypeReference,
 };
 
 use crate::match_variant;
-use crate::types::{make_type_list, ImplType, RetType, TypeClass};
+use crate::types::{ImplType, ProcessSig};
+use crate::udf_args::{coercion_check, coercion_stmt, vec_inner, FieldOpts, StaticLifetime};
 
 /// Verify that an `ItemImpl` matches the end of any given path
 ///
@@ -50,7 +52,7 @@ fn impl_type(itemimpl: &ItemImpl) -> Option<ImplType> {
 /// # Arguments
 ///
 /// - args: a stream of everything inside `(...)` (e.g.
-/// `#[register(bin=false, a=2)]` will give the stream for `bin=false, a=2`
+///   `#[register(bin=false, a=2)]` will give the stream for `bin=false, a=2`
 /// - item: the item contained within the stream
 pub fn register(args: &TokenStream, input: TokenStream) -> TokenStream {
     let parsed = parse_macro_input!(input as ItemImpl);
@@ -73,6 +75,30 @@ pub fn register(args: &TokenStream, input: TokenStream) -> TokenStream {
         Err(e) => return e.into_compile_error().into(),
     };
 
+    if impl_ty == ImplType::Aggregate
+        && (parsed_meta.args.is_some()
+            || parsed_meta.min_args.is_some()
+            || parsed_meta.max_args.is_some()
+            || parsed_meta.enforce_max_len)
+    {
+        return Error::new(
+            Span::call_site(),
+            "`args(...)`, `min_args`, `max_args` and `enforce_max_len` should be specified on \
+             the `BasicUdf` implementation",
+        )
+        .into_compile_error()
+        .into();
+    }
+
+    if impl_ty == ImplType::Aggregate && parsed_meta.returns.is_some() {
+        return Error::new(
+            Span::call_site(),
+            "`returns` should be specified on the `BasicUdf` implementation",
+        )
+        .into_compile_error()
+        .into();
+    }
+
     let dstruct_path_as_ident: String = dstruct_path
         .path
         .segments
@@ -80,26 +106,32 @@ pub fn register(args: &TokenStream, input: TokenStream) -> TokenStream {
         .map(|seg| seg.ident.to_string())
         .collect();
     let wrapper_ident = Ident::new(
-        &format!("_{dstruct_path_as_ident}Wrapper",),
+        &format!("_{dstruct_path_as_ident}Wrapper"),
         Span::call_site(),
     );
 
-    let (ret_ty, wrapper_def) = match impl_ty {
-        ImplType::Basic => match get_ret_ty_and_wrapper(&parsed, dstruct_path, &wrapper_ident) {
-            Ok((r, w)) => (Some(r), w),
-            Err(e) => return e.into_compile_error().into(),
-        },
+    let (sig, wrapper_def) = match impl_ty {
+        ImplType::Basic => {
+            let (s, w) = get_sig_and_wrapper(&parsed, &parsed_meta, dstruct_path, &wrapper_ident);
+            (Some(s), w)
+        }
         ImplType::Aggregate => (None, TokenStream2::new()),
     };
 
-    let helper_traits = make_helper_trait_impls(dstruct_path, &parsed_meta, impl_ty);
+    let helper_traits = match make_helper_trait_impls(&parsed, dstruct_path, &parsed_meta, impl_ty)
+    {
+        Ok(v) => v,
+        Err(e) => return e.into_compile_error().into(),
+    };
+    let symbols_impl = make_symbols_impl(&parsed, dstruct_path, &parsed_meta.name, sig);
 
     let fn_items_iter = parsed_meta.all_names().map(|base_fn_name| match impl_ty {
         ImplType::Basic => make_basic_fns(
-            ret_ty.as_ref().unwrap(),
+            sig.unwrap(),
             base_fn_name,
             dstruct_path,
             &wrapper_ident,
+            parsed_meta.args.is_some(),
         ),
         ImplType::Aggregate => make_agg_fns(&parsed, base_fn_name, dstruct_path, &wrapper_ident),
     });
@@ -111,6 +143,8 @@ pub fn register(args: &TokenStream, input: TokenStream) -> TokenStream {
 
         #helper_traits
 
+        #symbols_impl
+
         #( #fn_items_iter )*
     }
     .into()
@@ -121,6 +155,100 @@ struct ParsedMeta {
     name: String,
     aliases: Vec<String>,
     default_name_used: bool,
+    /// Declarations from `args(a: T, b: U)`, if specified
+    args: Option<Vec<ArgDecl>>,
+    /// From `min_args = n`
+    min_args: Option<usize>,
+    /// From `max_args = n`
+    max_args: Option<usize>,
+    /// From `enforce_max_len`
+    enforce_max_len: bool,
+    /// Signature from `returns = "..."`, if specified
+    returns: Option<ProcessSig>,
+}
+
+/// A single `name: Type` item within `args(...)`, which may have `#[udf(...)]`
+/// attributes like a `#[derive(UdfArgs)]` field
+struct ArgDecl {
+    opts: FieldOpts,
+    name: Ident,
+    ty: Type,
+}
+
+impl Parse for ArgDecl {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let mut ty = input.parse()?;
+        LifetimeFiller.visit_type_mut(&mut ty);
+
+        Ok(Self {
+            opts: FieldOpts::parse(&attrs)?,
+            name,
+            ty,
+        })
+    }
+}
+
+/// Parse the integer value of `min_args` or `max_args`
+fn parse_count(value: &Expr) -> syn::Result<usize> {
+    match *value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(ref value),
+            ..
+        }) => value.base10_parse(),
+        _ => Err(Error::new_spanned(value, "expected an integer")),
+    }
+}
+
+/// Parse `enforce_max_len`, the only attribute without a value
+fn parse_flag(path: &Path, enforce_max_len: &mut bool) -> syn::Result<()> {
+    if !path.is_ident("enforce_max_len") {
+        return Err(Error::new_spanned(path, "unexpected key"));
+    }
+    if *enforce_max_len {
+        return Err(Error::new_spanned(
+            path,
+            "`enforce_max_len` can only be specified once",
+        ));
+    }
+    *enforce_max_len = true;
+    Ok(())
+}
+
+/// Parse the declarations in `args(...)`
+fn parse_arg_decls(list: &MetaList, arg_decls: &mut Option<Vec<ArgDecl>>) -> syn::Result<()> {
+    if !list.path.is_ident("args") {
+        return Err(Error::new_spanned(&list.path, "unexpected key"));
+    }
+    if arg_decls.is_some() {
+        return Err(Error::new_spanned(
+            list,
+            "`args` can only be specified once",
+        ));
+    }
+    let decls = list.parse_args_with(Punctuated::<ArgDecl, Token![,]>::parse_terminated)?;
+    *arg_decls = Some(decls.into_iter().collect());
+    Ok(())
+}
+
+/// Give elided lifetimes in argument types the `'a` lifetime of `TypedArgs::Args`
+struct LifetimeFiller;
+
+impl VisitMut for LifetimeFiller {
+    fn visit_type_reference_mut(&mut self, tref: &mut TypeReference) {
+        if tref.lifetime.is_none() {
+            tref.lifetime = Some(parse_quote!('a));
+        }
+        visit_mut::visit_type_reference_mut(self, tref);
+    }
+
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        if lt.ident == "_" {
+            *lt = parse_quote!('a);
+        }
+    }
 }
 
 impl ParsedMeta {
@@ -130,9 +258,28 @@ impl ParsedMeta {
         let mut name_from_attributes = None;
         let mut aliases = Vec::new();
 
+        let mut arg_decls = None;
+        let mut min_args = None;
+        let mut max_args = None;
+        let mut enforce_max_len = false;
+        let mut returns = None;
+
         for m in meta {
+            if let Meta::Path(path) = &m {
+                parse_flag(path, &mut enforce_max_len)?;
+                continue;
+            }
+
+            if let Meta::List(list) = &m {
+                parse_arg_decls(list, &mut arg_decls)?;
+                continue;
+            }
+
             let Meta::NameValue(mval) = m else {
-                return Err(Error::new_spanned(m, "expected `a = b atributes`"));
+                return Err(Error::new_spanned(
+                    m,
+                    "expected `a = b`, `args(...)` or `enforce_max_len` attributes",
+                ));
             };
 
             if !mval.path.segments.iter().count() == 1 {
@@ -141,6 +288,22 @@ impl ParsedMeta {
 
             let key = mval.path.segments.first().unwrap();
 
+            if key.ident == "min_args" || key.ident == "max_args" {
+                let target = if key.ident == "min_args" {
+                    &mut min_args
+                } else {
+                    &mut max_args
+                };
+                if target.is_some() {
+                    return Err(Error::new_spanned(
+                        key,
+                        format!("`{}` can only be specified once", key.ident),
+                    ));
+                }
+                *target = Some(parse_count(&mval.value)?);
+                continue;
+            }
+
             let Expr::Lit(ExprLit {
                 lit: Lit::Str(value),
                 ..
@@ -156,10 +319,19 @@ impl ParsedMeta {
                 name_from_attributes = Some(value.value());
             } else if key.ident == "alias" {
                 aliases.push(value.value());
+            } else if key.ident == "returns" {
+                if returns.is_some() {
+                    return Err(Error::new_spanned(
+                        key,
+                        "`returns` can only be specified once",
+                    ));
+                }
+                returns = Some(ProcessSig::from_attr(&value)?);
             } else {
                 return Err(Error::new_spanned(
                     key,
-                    "unexpected key (only `name` and `alias` are accepted)",
+                    "unexpected key (only `name`, `alias`, `returns`, `min_args`, `max_args`, \
+                     `args`, and `enforce_max_len` are accepted)",
                 ));
             }
         }
@@ -177,127 +349,370 @@ impl ParsedMeta {
             name,
             aliases,
             default_name_used,
+            args: arg_decls,
+            min_args,
+            max_args,
+            enforce_max_len,
+            returns,
         })
     }
 
+    /// The allowed argument count, from `min_args` and `max_args` and any
+    /// `args(...)` signature
+    fn arg_range(&self) -> syn::Result<(usize, Option<usize>)> {
+        let err = |msg: &str| Err(Error::new(Span::call_site(), msg));
+
+        let (min, max) = match self.args.as_deref() {
+            None => (self.min_args.unwrap_or(0), self.max_args),
+            Some(decls) => {
+                let variadic = matches!(decls.last(), Some(d) if d.opts.variadic);
+                let positional = decls.len() - usize::from(variadic);
+                let min = self.min_args.unwrap_or(positional);
+                if min > positional && !variadic {
+                    return err("`min_args` is more than the number of arguments in `args(...)`");
+                }
+                if self.max_args.is_some() && !variadic {
+                    return err("`max_args` can only be used with `args(...)` if the last \
+                         argument is `variadic`");
+                }
+                let max = if variadic {
+                    self.max_args
+                } else {
+                    Some(positional)
+                };
+                (min, max)
+            }
+        };
+
+        match max {
+            Some(max) if max < min => err("`max_args` must not be less than `min_args`"),
+            _ => Ok((min, max)),
+        }
+    }
+
     /// Iterate the basic name and all aliases
     fn all_names(&self) -> impl Iterator<Item = &String> {
         iter::once(&self.name).chain(self.aliases.iter())
     }
 }
 
-/// Get the return type to use and a wrapper. Once per impl setup.
-fn get_ret_ty_and_wrapper(
+/// Get the `process` signature and a wrapper. Once per impl setup.
+///
+/// The signature comes from `returns = "..."` or is guessed from `Returns`;
+/// either way, it is checked against `ToSqlReturn::SQL_TYPE` at compile time.
+fn get_sig_and_wrapper(
     parsed: &ItemImpl,
+    meta: &ParsedMeta,
     dstruct_path: &TypePath,
     wrapper_ident: &Ident,
-) -> syn::Result<(RetType, TokenStream2)> {
-    // Get the return type from the macro
+) -> (ProcessSig, TokenStream2) {
     // There is only one type for this trait, which is "Returns"
-    let trait_ret_ty = &parsed
-        .items
-        .iter()
-        .find_map(match_variant!(ImplItem::Type))
-        .expect("type expected")
-        .ty;
-    let ret_ty = make_type_list()
-        .into_iter()
-        .find(|x| x.type_ == *trait_ret_ty)
-        .ok_or_else(|| {
-            Error::new_spanned(
-                trait_ret_ty,
-                "expected `Returns` to be one of `i64`, `f64`, `&str`, `String`, \
-                 or their `Option<...>` types",
-            )
-        })?;
+    let sig = meta.returns.unwrap_or_else(|| {
+        let trait_ret_ty = &parsed
+            .items
+            .iter()
+            .find_map(match_variant!(ImplItem::Type))
+            .expect("type expected")
+            .ty;
+        ProcessSig::infer(trait_ret_ty)
+    });
 
-    let ret_ty_type = &ret_ty.type_;
-    let wrapper_struct = if ret_ty.type_cls == TypeClass::Bytes {
-        quote! {
-            type #wrapper_ident = udf::wrapper::BufConverter<#dstruct_path, #ret_ty_type>;
-        }
+    // Only buffer results may need to be kept alive between calls
+    let wrapper_struct = if sig == ProcessSig::Buf {
+        quote! { type #wrapper_ident = udf::wrapper::BufConverter<#dstruct_path>; }
     } else {
         quote! { type #wrapper_ident = #dstruct_path; }
     };
+    let sql_type = sig.sql_type();
 
-    Ok((ret_ty, wrapper_struct))
+    let wrapper_def = quote! {
+        #wrapper_struct
+
+        const _: () = ::udf::wrapper::verify_return_type::<#dstruct_path>(#sql_type);
+    };
+
+    (sig, wrapper_def)
 }
 
 /// Make implementations for our helper/metadata traits
 fn make_helper_trait_impls(
+    parsed: &ItemImpl,
     dstruct_path: &TypePath,
     meta: &ParsedMeta,
     impl_ty: ImplType,
-) -> TokenStream2 {
+) -> syn::Result<TokenStream2> {
     let name = LitStr::new(&meta.name, Span::call_site());
     let aliases = meta
         .aliases
         .iter()
         .map(|alias| LitStr::new(alias.as_ref(), Span::call_site()));
-    let (trait_name, check_expr) = match impl_ty {
-        ImplType::Basic => (
-            quote! { ::udf::wrapper::RegisteredBasicUdf },
-            TokenStream2::new(),
-        ),
+    let (trait_name, check_expr, arg_range) = match impl_ty {
+        ImplType::Basic => {
+            let (min, max) = meta.arg_range()?;
+            let max = max.map_or_else(
+                || quote! { ::std::option::Option::None },
+                |max| quote! { ::std::option::Option::Some(#max) },
+            );
+            let enforce_max_len = meta.enforce_max_len;
+            (
+                quote! { ::udf::wrapper::RegisteredBasicUdf },
+                TokenStream2::new(),
+                quote! {
+                    const ARG_RANGE: (usize, ::std::option::Option<usize>) = (#min, #max);
+                    const ENFORCE_MAX_LEN: bool = #enforce_max_len;
+
+                    fn counters() -> &'static ::udf::wrapper::Counters {
+                        static COUNTERS: ::udf::wrapper::Counters =
+                            ::udf::wrapper::Counters::new();
+                        &COUNTERS
+                    }
+                },
+            )
+        }
         ImplType::Aggregate => (
             quote! { ::udf::wrapper::RegisteredAggregateUdf },
             quote! { const _: () = ::udf::wrapper::verify_aggregate_attributes::<#dstruct_path>(); },
+            TokenStream2::new(),
         ),
     };
     let default_name_used = meta.default_name_used;
+    let typed_args_impl = match meta.args.as_deref() {
+        Some(decls) => Some(make_typed_args_impl(
+            dstruct_path,
+            decls,
+            meta.arg_range()?.0,
+        )?),
+        None => None,
+    };
 
-    quote! {
+    let meta_record = make_meta_record(parsed, dstruct_path, impl_ty);
+
+    Ok(quote! {
         impl #trait_name for #dstruct_path {
             const NAME: &'static str = #name;
             const ALIASES: &'static [&'static str] = &[#( #aliases ),*];
             const DEFAULT_NAME_USED: bool = #default_name_used;
+            #arg_range
         }
 
+        #typed_args_impl
+
         #check_expr
+
+        #meta_record
+    })
+}
+
+/// Check whether a type is written as `Option<T>`
+fn is_option(ty: &Type) -> bool {
+    let Type::Path(tpath) = ty else { return false };
+    matches!(tpath.path.segments.last(), Some(seg) if seg.ident == "Option")
+}
+
+/// Implement `TypedArgs` for a signature from `args(...)`. Arguments from
+/// `min_args` on may be left out and are read as `NULL`.
+fn make_typed_args_impl(
+    dstruct_path: &TypePath,
+    decls: &[ArgDecl],
+    min_args: usize,
+) -> syn::Result<TokenStream2> {
+    let mut validate_stmts = Vec::new();
+    let mut extracts = Vec::new();
+    let mut checks = Vec::new();
+
+    for (idx, decl) in decls.iter().enumerate() {
+        let ty = &decl.ty;
+        let name = decl.name.to_string();
+        let mut static_ty = ty.clone();
+        StaticLifetime.visit_type_mut(&mut static_ty);
+
+        if decl.opts.variadic {
+            if idx + 1 != decls.len() {
+                return Err(Error::new_spanned(
+                    &decl.name,
+                    "only the last argument can be `variadic`",
+                ));
+            }
+            if decl.opts.const_required {
+                return Err(Error::new_spanned(
+                    &decl.name,
+                    "`const_required` cannot be combined with `variadic`",
+                ));
+            }
+            let (Some(inner), Some(static_inner)) = (vec_inner(ty), vec_inner(&static_ty)) else {
+                return Err(Error::new_spanned(
+                    ty,
+                    "`variadic` arguments must be a `Vec<T>`",
+                ));
+            };
+            validate_stmts.push(coercion_stmt(&decl.opts, static_inner, idx));
+            checks.extend(coercion_check(&decl.opts, static_inner, &name));
+            extracts.push(quote! { ::udf::wrapper::extract_rest::<#inner>(args, #idx)? });
+            continue;
+        }
+
+        validate_stmts.push(coercion_stmt(&decl.opts, &static_ty, idx));
+        checks.extend(coercion_check(&decl.opts, &static_ty, &name));
+        if decl.opts.const_required {
+            validate_stmts.push(quote! { ::udf::wrapper::check_const(args, #idx)?; });
+        }
+
+        if idx < min_args {
+            extracts.push(quote! { ::udf::wrapper::extract_one::<#ty>(args, #idx)? });
+        } else if is_option(ty) {
+            extracts.push(quote! { ::udf::wrapper::extract_optional::<#ty>(args, #idx)? });
+        } else {
+            return Err(Error::new_spanned(
+                ty,
+                "arguments after `min_args` may be left out, so they must be `Option<T>`",
+            ));
+        }
+    }
+
+    let types = decls.iter().map(|decl| &decl.ty);
+
+    Ok(quote! {
+        impl ::udf::TypedArgs for #dstruct_path {
+            type Args<'a> = ( #( #types, )* );
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn validate_args(
+                args: &::udf::ArgList<::udf::Init>,
+            ) -> ::std::result::Result<(), ::std::string::String> {
+                #( #validate_stmts )*
+                Ok(())
+            }
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn typed_args<'a>(
+                args: &'a ::udf::ArgList<'a, ::udf::Process>,
+            ) -> ::std::result::Result<Self::Args<'a>, ::udf::ProcessError> {
+                Ok(( #( #extracts, )* ))
+            }
+        }
+
+        #( #checks )*
+    })
+}
+
+/// Add a record describing this UDF to the library's metadata section, which
+/// `udf::manifest` and `cargo udf` read
+fn make_meta_record(parsed: &ItemImpl, dstruct_path: &TypePath, impl_ty: ImplType) -> TokenStream2 {
+    let (len_call, meta_call) = match impl_ty {
+        ImplType::Basic => (
+            quote!(basic_meta_len::<#dstruct_path>(VERSION)),
+            quote!(basic_meta::<#dstruct_path, LEN>(VERSION)),
+        ),
+        ImplType::Aggregate => {
+            let window = impls_remove(parsed);
+            (
+                quote!(aggregate_meta_len::<#dstruct_path>(VERSION, #window)),
+                quote!(aggregate_meta::<#dstruct_path, LEN>(VERSION, #window)),
+            )
+        }
+    };
+
+    // Section names must match `udf::manifest`
+    quote! {
+        const _: () = {
+            const VERSION: &str = ::std::env!("CARGO_PKG_VERSION");
+            const LEN: usize = ::udf::wrapper::#len_call;
+
+            #[used]
+            #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__udfmeta")]
+            #[cfg_attr(windows, link_section = ".udfmeta$b")]
+            #[cfg_attr(
+                not(any(target_vendor = "apple", windows)),
+                link_section = "udfmeta"
+            )]
+            static UDF_META: [u8; LEN] = ::udf::wrapper::#meta_call;
+        };
     }
 }
 
+/// Point the `BasicSymbols` or `AggregateSymbols` trait at the functions
+/// generated for the main name, so `udf::mock::MockServer` can call them.
+/// `sig` is `None` for aggregate UDFs.
+fn make_symbols_impl(
+    parsed: &ItemImpl,
+    dstruct_path: &TypePath,
+    name: &str,
+    sig: Option<ProcessSig>,
+) -> TokenStream2 {
+    let Some(sig) = sig else {
+        let clear_fn = format_ident!("{}_clear", name);
+        let add_fn = format_ident!("{}_add", name);
+        let remove_fn = format_ident!("{}_remove", name);
+        let remove = if impls_remove(parsed) {
+            quote!(Some(#remove_fn))
+        } else {
+            quote!(None)
+        };
+
+        return quote! {
+            impl ::udf::wrapper::AggregateSymbols for #dstruct_path {
+                const CLEAR: ::udf::wrapper::ClearFn = #clear_fn;
+                const ADD: ::udf::wrapper::AddFn = #add_fn;
+                const REMOVE: Option<::udf::wrapper::AddFn> = #remove;
+            }
+        };
+    };
+
+    let init_fn = format_ident!("{}_init", name);
+    let deinit_fn = format_ident!("{}_deinit", name);
+    let process_fn = format_ident!("{}", name);
+    let variant = match sig {
+        ProcessSig::Buf => quote!(Buf),
+        ProcessSig::Int => quote!(Int),
+        ProcessSig::Real => quote!(Real),
+    };
+
+    quote! {
+        impl ::udf::wrapper::BasicSymbols for #dstruct_path {
+            const INIT: ::udf::wrapper::InitFn = #init_fn;
+            const PROCESS: ::udf::wrapper::ProcessFn = ::udf::wrapper::ProcessFn::#variant(#process_fn);
+            const DEINIT: ::udf::wrapper::DeinitFn = #deinit_fn;
+        }
+    }
+}
+
+/// Determine whether an `AggregateUdf` impl re-implements `remove`
+fn impls_remove(parsed: &ItemImpl) -> bool {
+    parsed
+        .items
+        .iter()
+        .filter_map(match_variant!(ImplItem::Fn))
+        .map(|m| &m.sig.ident)
+        .any(|id| *id == "remove")
+}
+
 /// Create the basic function signatures (`xxx_init`, `xxx_deinit`, `xxx`)
 fn make_basic_fns(
-    rt: &RetType,
+    sig: ProcessSig,
     base_fn_name: &str,
     dstruct_path: &TypePath,
     wrapper_ident: &Ident,
+    typed_args: bool,
 ) -> TokenStream2 {
     let init_fn_name = format_ident!("{}_init", base_fn_name);
     let deinit_fn_name = format_ident!("{}_deinit", base_fn_name);
     let process_fn_name = format_ident!("{}", base_fn_name);
 
-    let init_fn = make_init_fn(dstruct_path, wrapper_ident, &init_fn_name);
+    let init_fn = make_init_fn(dstruct_path, wrapper_ident, &init_fn_name, typed_args);
     let deinit_fn = make_deinit_fn(dstruct_path, wrapper_ident, &deinit_fn_name);
-    let process_fn = match rt.type_cls {
-        TypeClass::Bytes => make_proc_buf_fn(
-            dstruct_path,
-            wrapper_ident,
-            &process_fn_name,
-            rt.is_optional,
-            false,
-        ),
-        TypeClass::BytesRef => make_proc_buf_fn(
-            dstruct_path,
-            wrapper_ident,
-            &process_fn_name,
-            rt.is_optional,
-            true,
-        ),
-        TypeClass::Int => make_proc_fn(
-            dstruct_path,
-            wrapper_ident,
+    let process_fn = match sig {
+        ProcessSig::Buf => make_proc_buf_fn(dstruct_path, wrapper_ident, &process_fn_name),
+        ProcessSig::Int => make_proc_fn(
             &process_fn_name,
             &quote!(::std::ffi::c_longlong),
-            rt.is_optional,
+            &quote!(udf::wrapper::wrap_process_int::<#wrapper_ident, #dstruct_path>),
         ),
-        TypeClass::Float => make_proc_fn(
-            dstruct_path,
-            wrapper_ident,
+        ProcessSig::Real => make_proc_fn(
             &process_fn_name,
             &quote!(::std::ffi::c_double),
-            rt.is_optional,
+            &quote!(udf::wrapper::wrap_process_real::<#wrapper_ident, #dstruct_path>),
         ),
     };
 
@@ -321,21 +736,13 @@ fn make_agg_fns(
     let add_fn_name = format_ident!("{}_add", base_fn_name);
     let remove_fn_name = format_ident!("{}_remove", base_fn_name);
 
-    // Determine whether this re-implements `remove`
-    let impls_remove = &parsed
-        .items
-        .iter()
-        .filter_map(match_variant!(ImplItem::Fn))
-        .map(|m| &m.sig.ident)
-        .any(|id| *id == "remove");
-
     let clear_fn = make_clear_fn(dstruct_path, wrapper_ident, &clear_fn_name);
     let add_fn = make_add_fn(dstruct_path, wrapper_ident, &add_fn_name);
     let remove_fn_impl = make_remove_fn(dstruct_path, wrapper_ident, &remove_fn_name);
 
     // If we implement remove, add a remove function. Otherwise, we don't need
     // anything.
-    let remove_fn = if *impls_remove {
+    let remove_fn = if impls_remove(parsed) {
         remove_fn_impl
     } else {
         TokenStream2::default()
@@ -351,7 +758,19 @@ fn make_agg_fns(
 }
 
 /// Given the name of a type or struct, create a function that will be evaluated (`xxx`)
-fn make_init_fn(dstruct_path: &TypePath, wrapper_ident: &Ident, fn_name: &Ident) -> TokenStream2 {
+fn make_init_fn(
+    dstruct_path: &TypePath,
+    wrapper_ident: &Ident,
+    fn_name: &Ident,
+    typed_args: bool,
+) -> TokenStream2 {
+    // Typed signatures check the argument list before calling the user's `init`
+    let wrap_fn_name = if typed_args {
+        quote!(udf::wrapper::wrap_init_typed::<#wrapper_ident, #dstruct_path>)
+    } else {
+        quote!(udf::wrapper::wrap_init::<#wrapper_ident, #dstruct_path>)
+    };
+
     // SAFETY: we just minimally wrap the functions here, safety is handled
     // between our caller and callee
     quote! {
@@ -362,7 +781,7 @@ fn make_init_fn(dstruct_path: &TypePath, wrapper_ident: &Ident, fn_name: &Ident)
             message: *mut std::ffi::c_char,
         ) -> bool
         {
-            udf::wrapper::wrap_init::<#wrapper_ident, #dstruct_path>(initid, args, message)
+            #wrap_fn_name(initid, args, message)
         }
     }
 }
@@ -381,19 +800,12 @@ fn make_deinit_fn(dstruct_path: &TypePath, wrapper_ident: &Ident, fn_name: &Iden
     }
 }
 
+/// Make the `xxx` function for integer and real results
 fn make_proc_fn(
-    dstruct_path: &TypePath,
-    wrapper_ident: &Ident,
     fn_name: &Ident,
     ret_type: &TokenStream2,
-    is_optional: bool,
+    wrap_fn_name: &TokenStream2,
 ) -> TokenStream2 {
-    let wrap_fn_name = if is_optional {
-        quote!(udf::wrapper::wrap_process_basic_option::<#wrapper_ident, #dstruct_path, _>)
-    } else {
-        quote!(udf::wrapper::wrap_process_basic::<#wrapper_ident, #dstruct_path, _>)
-    };
-
     // SAFETY: we just minimally wrap the functions here, safety is handled
     // between our caller and callee
     quote! {
@@ -409,21 +821,12 @@ fn make_proc_fn(
     }
 }
 
+/// Make the `xxx` function for string and decimal results
 fn make_proc_buf_fn(
     dstruct_path: &TypePath,
     wrapper_ident: &Ident,
     fn_name: &Ident,
-    is_optional: bool,
-    is_ref: bool,
 ) -> TokenStream2 {
-    let wrap_fn_name = if is_optional && is_ref {
-        quote!(udf::wrapper::wrap_process_buf_option_ref::<#wrapper_ident, #dstruct_path, _>)
-    } else if is_optional {
-        quote!(udf::wrapper::wrap_process_buf_option::<#wrapper_ident, #dstruct_path, _>)
-    } else {
-        quote!(udf::wrapper::wrap_process_buf::<#wrapper_ident, #dstruct_path>)
-    };
-
     quote! {
         #[no_mangle]
         pub unsafe extern "C" fn #fn_name (
@@ -434,7 +837,7 @@ fn make_proc_buf_fn(
             is_null: *mut ::std::ffi::c_uchar,
             error: *mut ::std::ffi::c_uchar,
         ) -> *const ::std::ffi::c_char {
-            #wrap_fn_name(
+            udf::wrapper::wrap_process_buf::<#wrapper_ident, #dstruct_path>(
                 initid,
                 args,
                 result,
@@ -1,4 +1,6 @@
-use syn::{parse_quote, Type};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Error, GenericArgument, LitStr, PathArguments, PathSegment, Type};
 
 /// Allowable signatures
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -7,73 +9,79 @@ pub enum ImplType {
     Aggregate,
 }
 
-/// Possible return types in SQL
+/// The C signature of a `process` function
+///
+/// Conversion of the result is handled by `ToSqlReturn`, but the signature
+/// must be known when expanding the macro.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum TypeClass {
+pub enum ProcessSig {
     Int,
-    Float,
-    /// Bytes that can be properly returned
-    BytesRef,
-    /// Bytest that must be truncated
-    Bytes,
+    Real,
+    /// Strings and decimals
+    Buf,
 }
 
-/// Struct containing information about a return type
-#[derive(Clone, Debug)]
-pub struct RetType {
-    pub type_: Type,
-    pub is_optional: bool,
-    pub type_cls: TypeClass,
-}
+impl ProcessSig {
+    /// Parse `returns = "..."`
+    pub fn from_attr(value: &LitStr) -> syn::Result<Self> {
+        match value.value().as_str() {
+            "int" => Ok(Self::Int),
+            "real" => Ok(Self::Real),
+            "string" | "decimal" => Ok(Self::Buf),
+            _ => Err(Error::new_spanned(
+                value,
+                "expected one of \"int\", \"real\", \"string\", or \"decimal\"",
+            )),
+        }
+    }
 
-impl RetType {
-    fn new(type_: Type, is_optional: bool, fn_sig: TypeClass) -> Self {
-        Self {
-            type_,
-            is_optional,
-            type_cls: fn_sig,
+    /// Guess the signature from a `Returns` type. Primitive integers and
+    /// floats (optionally in an `Option`) are recognized, anything else is
+    /// assumed to be a string. This is checked against `ToSqlReturn::SQL_TYPE`
+    /// at compile time.
+    pub fn infer(ty: &Type) -> Self {
+        let ty = option_inner(ty).unwrap_or(ty);
+        let Some(seg) = last_segment(ty) else {
+            return Self::Buf;
+        };
+
+        let name = seg.ident.to_string();
+        match name.as_str() {
+            "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "bool" => Self::Int,
+            "f32" | "f64" => Self::Real,
+            _ => Self::Buf,
+        }
+    }
+
+    /// The `SqlType` this signature expects
+    pub fn sql_type(self) -> TokenStream2 {
+        match self {
+            Self::Int => quote!(::udf::SqlType::Int),
+            Self::Real => quote!(::udf::SqlType::Real),
+            Self::Buf => quote!(::udf::SqlType::String),
         }
     }
 }
 
-/// Brute force list of acceptable types
-///
-/// We cannot accept `String` directly because that would imply allocation that
-/// we can't allow (we would have to turn the `String` into a pointer to return
-/// it, and we would never get the pointer back to free it).
-pub fn make_type_list() -> Vec<RetType> {
-    vec![
-        // Only valid integer types
-        RetType::new(parse_quote! { i64 }, false, TypeClass::Int),
-        RetType::new(parse_quote! { Option<i64> }, true, TypeClass::Int),
-        // Only valid float types
-        RetType::new(parse_quote! { f64 }, false, TypeClass::Float),
-        RetType::new(parse_quote! { Option<f64> }, true, TypeClass::Float),
-        // Tons of possible byte slice references. We could probably make these
-        // generic somehow in the future, but it is proving tough.
-        RetType::new(parse_quote! { &'a [u8] }, false, TypeClass::BytesRef),
-        RetType::new(parse_quote! { Option<&'a [u8]> }, true, TypeClass::BytesRef),
-        RetType::new(parse_quote! { &str }, false, TypeClass::BytesRef),
-        RetType::new(parse_quote! { Option<&str> }, true, TypeClass::BytesRef),
-        RetType::new(parse_quote! { &'a str }, false, TypeClass::BytesRef),
-        RetType::new(parse_quote! { Option<&'a str> }, true, TypeClass::BytesRef),
-        RetType::new(parse_quote! { &'static str }, false, TypeClass::BytesRef),
-        RetType::new(
-            parse_quote! { Option<&'static str> },
-            true,
-            TypeClass::BytesRef,
-        ),
-        RetType::new(parse_quote! { &'a String }, false, TypeClass::BytesRef),
-        RetType::new(
-            parse_quote! { Option<&'a String> },
-            true,
-            TypeClass::BytesRef,
-        ),
-        // Bytes types that aren't in a reference. These will get copied if they fit,
-        // truncated with a stderr message if not
-        RetType::new(parse_quote! { Vec<u8> }, false, TypeClass::Bytes),
-        RetType::new(parse_quote! { Option<Vec<u8>>}, true, TypeClass::Bytes),
-        RetType::new(parse_quote! { String }, false, TypeClass::Bytes),
-        RetType::new(parse_quote! { Option<String>}, true, TypeClass::Bytes),
-    ]
+/// Get `T` from `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let seg = last_segment(ty).filter(|seg| seg.ident == "Option")?;
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+/// Get the last path segment of a type, e.g. `Bar<T>` in `foo::Bar<T>`
+fn last_segment(ty: &Type) -> Option<&PathSegment> {
+    match ty {
+        Type::Path(tpath) => tpath.path.segments.last(),
+        // Types from `macro_rules!` are wrapped in an invisible group
+        Type::Group(group) => last_segment(&group.elem),
+        Type::Paren(paren) => last_segment(&paren.elem),
+        _ => None,
+    }
 }
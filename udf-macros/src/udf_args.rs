@@ -0,0 +1,271 @@
+//! Implementation of `#[derive(UdfArgs)]`
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::visit_mut::VisitMut;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, GenericArgument,
+    GenericParam, Generics, Index, Lifetime, LitStr, Member, PathArguments, Type, WherePredicate,
+};
+
+/// Options set on a single field (or an argument in `#[register(args(...))]`)
+/// with `#[udf(...)]`
+#[derive(Default)]
+pub struct FieldOpts {
+    pub coerce: Option<TokenStream2>,
+    pub const_required: bool,
+    pub variadic: bool,
+}
+
+impl FieldOpts {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut opts = Self::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("udf")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("coerce") {
+                    let value: LitStr = meta.value()?.parse()?;
+                    let ty = match value.value().as_str() {
+                        "int" => quote!(Int),
+                        "real" => quote!(Real),
+                        "string" => quote!(String),
+                        "decimal" => quote!(Decimal),
+                        _ => {
+                            return Err(Error::new_spanned(
+                                value,
+                                "expected one of `int`, `real`, `string`, or `decimal`",
+                            ))
+                        }
+                    };
+                    opts.coerce = Some(quote!(::udf::SqlType::#ty));
+                } else if meta.path.is_ident("const_required") {
+                    opts.const_required = true;
+                } else if meta.path.is_ident("variadic") {
+                    opts.variadic = true;
+                } else {
+                    return Err(meta.error(
+                        "unexpected key (only `coerce`, `const_required`, and `variadic` \
+                         are accepted)",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Get `T` from `Vec<T>`
+pub fn vec_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(tpath) = ty else { return None };
+    let seg = tpath.path.segments.last()?;
+    if seg.ident != "Vec" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &seg.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+/// Check that a `variadic` field is valid and get its element type
+fn variadic_inner<'f>(
+    field: &'f syn::Field,
+    opts: &FieldOpts,
+    is_last: bool,
+) -> syn::Result<&'f Type> {
+    if !is_last {
+        return Err(Error::new_spanned(
+            field,
+            "only the last field can be `variadic`",
+        ));
+    }
+    if opts.const_required {
+        return Err(Error::new_spanned(
+            field,
+            "`const_required` cannot be combined with `variadic`",
+        ));
+    }
+    vec_inner(&field.ty)
+        .ok_or_else(|| Error::new_spanned(&field.ty, "`variadic` fields must be a `Vec<T>`"))
+}
+
+/// The statement in `validate` that sets the coercion for argument `idx`, or
+/// for all arguments from `idx` if variadic (`ty` is then the element type)
+pub fn coercion_stmt(opts: &FieldOpts, ty: &Type, idx: usize) -> TokenStream2 {
+    match (opts.variadic, opts.coerce.as_ref()) {
+        (false, None) => quote! { ::udf::wrapper::set_coercion::<#ty>(args, #idx); },
+        (false, Some(coerce)) => {
+            quote! { ::udf::wrapper::set_coercion_to(args, #idx, #coerce); }
+        }
+        (true, None) => quote! { ::udf::wrapper::set_rest_coercion::<#ty>(args, #idx); },
+        (true, Some(coerce)) => quote! {
+            for i in #idx..args.len() {
+                ::udf::wrapper::set_coercion_to(args, i, #coerce);
+            }
+        },
+    }
+}
+
+/// A check that the type of argument `name` can read the type it is coerced
+/// to, as an item. `ty` must not have lifetimes other than `'static`.
+pub fn coercion_check(opts: &FieldOpts, ty: &Type, name: &str) -> Option<TokenStream2> {
+    let coerce = opts.coerce.as_ref()?;
+    Some(quote! {
+        const _: () = ::udf::wrapper::verify_coercion::<#ty>(#coerce, #name);
+    })
+}
+
+/// Replace all lifetimes with `'static`, for checks outside of the impl
+pub struct StaticLifetime;
+
+impl VisitMut for StaticLifetime {
+    fn visit_lifetime_mut(&mut self, lt: &mut Lifetime) {
+        *lt = parse_quote!('static);
+    }
+}
+
+/// Top-level entrypoint
+pub fn derive_udf_args(input: TokenStream) -> TokenStream {
+    let parsed = parse_macro_input!(input as DeriveInput);
+
+    match make_impl(&parsed) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+/// Use the struct's lifetime if it has one, otherwise add one to the impl
+/// generics
+fn impl_lifetime(generics: &Generics) -> syn::Result<(Lifetime, Generics)> {
+    let mut lifetimes = generics.lifetimes();
+    let mut impl_generics = generics.clone();
+
+    match (lifetimes.next(), lifetimes.next()) {
+        (None, _) => {
+            let lt = Lifetime::new("'a", Span::call_site());
+            impl_generics
+                .params
+                .insert(0, GenericParam::Lifetime(parse_quote!(#lt)));
+            Ok((lt, impl_generics))
+        }
+        (Some(l), None) => Ok((l.lifetime.clone(), impl_generics)),
+        (Some(_), Some(l)) => Err(Error::new_spanned(
+            l,
+            "`UdfArgs` structs may have at most one lifetime",
+        )),
+    }
+}
+
+fn make_impl(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "`UdfArgs` can only be derived for structs",
+        ));
+    };
+
+    let (lt, mut impl_generics) = impl_lifetime(&input.generics)?;
+    let fields: Vec<_> = data.fields.iter().collect();
+    let mut validate_stmts = Vec::new();
+    let mut extract_fields = Vec::new();
+    let mut bounds: Vec<WherePredicate> = Vec::new();
+    let mut checks = Vec::new();
+    let mut variadic = false;
+    // Checks are items, which cannot use the struct's type parameters
+    let check_coercions = input.generics.type_params().next().is_none()
+        && input.generics.const_params().next().is_none();
+
+    for (idx, field) in fields.iter().enumerate() {
+        let opts = FieldOpts::parse(&field.attrs)?;
+        let ty = &field.ty;
+        let member = field
+            .ident
+            .clone()
+            .map_or_else(|| Member::Unnamed(Index::from(idx)), Member::Named);
+        let name = field
+            .ident
+            .as_ref()
+            .map_or_else(|| idx.to_string(), ToString::to_string);
+
+        let mut static_ty = ty.clone();
+        StaticLifetime.visit_type_mut(&mut static_ty);
+
+        if opts.variadic {
+            let inner = variadic_inner(field, &opts, idx + 1 == fields.len())?;
+            validate_stmts.push(coercion_stmt(&opts, inner, idx));
+            if check_coercions {
+                checks.extend(vec_inner(&static_ty).and_then(|t| coercion_check(&opts, t, &name)));
+            }
+            extract_fields
+                .push(quote! { #member: ::udf::wrapper::extract_rest::<#inner>(args, #idx)? });
+            bounds.push(parse_quote! { #inner: ::udf::FromSqlArg<#lt> });
+            variadic = true;
+            continue;
+        }
+
+        validate_stmts.push(coercion_stmt(&opts, ty, idx));
+        if check_coercions {
+            checks.extend(coercion_check(&opts, &static_ty, &name));
+        }
+        if opts.const_required {
+            validate_stmts.push(quote! { ::udf::wrapper::check_const(args, #idx)?; });
+        }
+        extract_fields.push(quote! { #member: ::udf::wrapper::extract_one::<#ty>(args, #idx)? });
+        bounds.push(parse_quote! { #ty: ::udf::FromSqlArg<#lt> });
+    }
+
+    let (count, count_check) = if variadic {
+        let count = fields.len() - 1;
+        let check = quote! { ::udf::wrapper::check_min_arg_count(#count, args.len())?; };
+        (count, check)
+    } else {
+        let count = fields.len();
+        let check = quote! { ::udf::wrapper::check_arg_count(#count, args.len())?; };
+        (count, check)
+    };
+    let construct = match &data.fields {
+        Fields::Named(_) | Fields::Unnamed(_) => quote! { Self { #( #extract_fields, )* } },
+        Fields::Unit => quote! { Self },
+    };
+
+    let where_clause = impl_generics.make_where_clause();
+    for bound in bounds {
+        where_clause.predicates.push(bound);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::udf::UdfArgs<#lt> for #ident #ty_generics #where_clause {
+            const COUNT: usize = #count;
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn validate(
+                args: &::udf::ArgList<::udf::Init>
+            ) -> ::std::result::Result<(), ::std::string::String> {
+                #count_check
+                #( #validate_stmts )*
+                Ok(())
+            }
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn extract(
+                args: &#lt ::udf::ArgList<#lt, ::udf::Process>
+            ) -> ::std::result::Result<Self, ::udf::ProcessError> {
+                Ok(#construct)
+            }
+        }
+
+        #( #checks )*
+    })
+}
@@ -0,0 +1,63 @@
+#![allow(unused)]
+
+use udf::prelude::*;
+
+struct MyUdf1;
+struct MyUdf2;
+struct MyUdf3;
+
+#[register(args(a: i64, b: i64), min_args = 1)]
+impl BasicUdf for MyUdf1 {
+    type Returns<'a> = i64;
+
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register(min_args = 3, max_args = 2)]
+impl BasicUdf for MyUdf2 {
+    type Returns<'a> = i64;
+
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register(args(#[udf(variadic)] a: Vec<i64>, b: i64))]
+impl BasicUdf for MyUdf3 {
+    type Returns<'a> = i64;
+
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+fn main() {}
@@ -0,0 +1,38 @@
+#![allow(unused)]
+
+use udf::prelude::*;
+
+#[derive(UdfArgs)]
+struct IntToString {
+    #[udf(coerce = "int")]
+    name: String,
+}
+
+#[derive(UdfArgs)]
+struct RealToInts<'a> {
+    label: &'a str,
+    #[udf(variadic, coerce = "real")]
+    rest: Vec<Option<i64>>,
+}
+
+struct MyUdf;
+
+#[register(args(#[udf(coerce = "string")] count: i64))]
+impl BasicUdf for MyUdf {
+    type Returns<'a> = i64;
+
+    fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<Process>,
+        _args: &ArgList<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+fn main() {}
@@ -0,0 +1,47 @@
+#![allow(unused)]
+
+use udf::prelude::*;
+
+type Count = i64;
+
+struct MyUdf;
+
+#[register]
+impl BasicUdf for MyUdf {
+    type Returns<'a> = Count;
+
+    fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<Process>,
+        _args: &ArgList<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+struct Unsupported;
+
+#[register]
+impl BasicUdf for Unsupported {
+    type Returns<'a> = u64;
+
+    fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<Process>,
+        _args: &ArgList<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+fn main() {}
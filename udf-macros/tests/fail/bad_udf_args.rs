@@ -0,0 +1,24 @@
+#![allow(unused)]
+
+use udf::prelude::*;
+
+#[derive(UdfArgs)]
+struct BadCoerce {
+    #[udf(coerce = "float")]
+    a: f64,
+}
+
+#[derive(UdfArgs)]
+struct NotLast {
+    #[udf(variadic)]
+    a: Vec<i64>,
+    b: i64,
+}
+
+#[derive(UdfArgs)]
+struct NotVec {
+    #[udf(variadic)]
+    a: i64,
+}
+
+fn main() {}
@@ -0,0 +1,49 @@
+#![allow(unused)]
+
+use udf::prelude::*;
+
+struct MyUdf;
+struct MyUdfOpt;
+
+#[register]
+impl BasicUdf for MyUdf {
+    type Returns<'a> = Decimal<10, 2>;
+
+    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register]
+impl BasicUdf for MyUdfOpt {
+    type Returns<'a> = Option<udf::Decimal<38, 0>>;
+
+    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+fn main() {
+    let _ = my_udf as *const ();
+    let _ = my_udf_init as *const ();
+    let _ = my_udf_opt as *const ();
+    let _ = my_udf_opt_init as *const ();
+}
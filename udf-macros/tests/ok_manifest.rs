@@ -0,0 +1,141 @@
+#![allow(unused)]
+
+use std::ffi::CStr;
+
+use udf::manifest::{functions, udf_manifest, UdfKind};
+use udf::prelude::*;
+
+struct MyUdf;
+struct MyAgg;
+struct MyWindow;
+
+#[register(alias = "my_alias")]
+impl BasicUdf for MyUdf {
+    type Returns<'a> = &'a str;
+
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register]
+impl BasicUdf for MyAgg {
+    type Returns<'a> = Option<i64>;
+
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register]
+impl AggregateUdf for MyAgg {
+    fn clear(&mut self, cfg: &UdfCfg<Process>, error: Option<NonZeroU8>) -> Result<(), NonZeroU8> {
+        todo!()
+    }
+    fn add(
+        &mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<'_, Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        todo!()
+    }
+}
+
+#[register]
+impl BasicUdf for MyWindow {
+    type Returns<'a> = f64;
+
+    fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register]
+impl AggregateUdf for MyWindow {
+    fn clear(&mut self, cfg: &UdfCfg<Process>, error: Option<NonZeroU8>) -> Result<(), NonZeroU8> {
+        todo!()
+    }
+    fn add(
+        &mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<'_, Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        todo!()
+    }
+    fn remove(
+        &mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<'_, Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        todo!()
+    }
+}
+
+#[test]
+fn test_functions() {
+    let funcs = functions();
+    // `udf_stats` is added by the `metrics` feature when testing the workspace
+    let summary: Vec<_> = funcs
+        .iter()
+        .filter(|f| f.name != "udf_stats")
+        .map(|f| (f.name, f.aliases.clone(), f.kind, f.returns, f.maybe_null))
+        .collect();
+
+    assert_eq!(
+        summary,
+        [
+            ("my_agg", vec![], UdfKind::Aggregate, SqlType::Int, true),
+            (
+                "my_udf",
+                vec!["my_alias"],
+                UdfKind::Basic,
+                SqlType::String,
+                false
+            ),
+            ("my_window", vec![], UdfKind::Window, SqlType::Real, false),
+        ]
+    );
+    assert!(funcs.iter().all(|f| f.version == env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn test_udf_manifest() {
+    let json = unsafe { CStr::from_ptr(udf_manifest()) }.to_str().unwrap();
+    assert!(json.contains(
+        r#"{"name":"my_udf","aliases":["my_alias"],"kind":"basic","returns":"string","maybe_null":false,"#
+    ));
+    assert!(json.contains(r#"{"name":"my_window","aliases":[],"kind":"window""#));
+
+    // The same pointer is returned each time
+    assert_eq!(udf_manifest(), udf_manifest());
+}
@@ -0,0 +1,70 @@
+#![allow(unused)]
+
+use std::borrow::Cow;
+
+use udf::prelude::*;
+use udf::{SqlReturn, ToSqlReturn};
+
+/// Implement a UDF that just returns `$val`
+macro_rules! make_udf {
+    ($name:ident, $ret:ty, $val:expr) => {
+        make_udf!($name, $ret, $val, register);
+    };
+    ($name:ident, $ret:ty, $val:expr, $($reg:tt)*) => {
+        struct $name;
+
+        #[$($reg)*]
+        impl BasicUdf for $name {
+            type Returns<'a> = $ret;
+
+            fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+                Ok(Self)
+            }
+
+            fn process<'a>(
+                &'a mut self,
+                _cfg: &UdfCfg<Process>,
+                _args: &ArgList<Process>,
+                _error: Option<NonZeroU8>,
+            ) -> Result<Self::Returns<'a>, ProcessError> {
+                Ok($val)
+            }
+        }
+    };
+}
+
+type Count = i64;
+
+struct Celsius(f64);
+
+impl<'a> ToSqlReturn<'a> for Celsius {
+    const SQL_TYPE: SqlType = SqlType::Real;
+
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        SqlReturn::Real(self.0)
+    }
+}
+
+make_udf!(SmallInt, i32, 1);
+make_udf!(Unsigned, Option<u32>, Some(1));
+make_udf!(Flag, bool, true);
+make_udf!(Single, f32, 1.0);
+make_udf!(BoxStr, Box<str>, "abc".into());
+make_udf!(CowStr, Cow<'a, str>, Cow::Borrowed("abc"));
+make_udf!(ArcBytes, std::sync::Arc<[u8]>, b"abc"[..].into());
+make_udf!(Date, udf::chrono::NaiveDate, udf::chrono::NaiveDate::MIN);
+make_udf!(Alias, Count, 1, register(returns = "int"));
+make_udf!(Temp, Celsius, Celsius(20.0), register(returns = "real"));
+
+fn main() {
+    let _: unsafe extern "C" fn(_, _, _, _) -> i64 = small_int;
+    let _: unsafe extern "C" fn(_, _, _, _) -> i64 = unsigned;
+    let _: unsafe extern "C" fn(_, _, _, _) -> i64 = flag;
+    let _: unsafe extern "C" fn(_, _, _, _) -> f64 = single;
+    let _: unsafe extern "C" fn(_, _, _, _, _, _) -> _ = box_str;
+    let _: unsafe extern "C" fn(_, _, _, _, _, _) -> _ = cow_str;
+    let _: unsafe extern "C" fn(_, _, _, _, _, _) -> _ = arc_bytes;
+    let _: unsafe extern "C" fn(_, _, _, _, _, _) -> _ = date;
+    let _: unsafe extern "C" fn(_, _, _, _) -> i64 = alias;
+    let _: unsafe extern "C" fn(_, _, _, _) -> f64 = temp;
+}
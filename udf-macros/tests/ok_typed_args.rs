@@ -0,0 +1,127 @@
+#![allow(unused)]
+
+use udf::prelude::*;
+
+struct MyUdf;
+struct MyAggUdf;
+
+#[register(args(name: &str, count: Option<i64>, raw: &'_ [u8]))]
+impl BasicUdf for MyUdf {
+    type Returns<'a> = Option<i64>;
+
+    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        let (name, count, raw): (&str, Option<i64>, &[u8]) = Self::typed_args(args)?;
+        Ok(count)
+    }
+}
+
+struct MyVariadicUdf;
+
+#[register(
+    args(sep: &str, limit: Option<i64>, #[udf(variadic, coerce = "string")] parts: Vec<&'_ str>),
+    min_args = 1,
+    max_args = 10
+)]
+impl BasicUdf for MyVariadicUdf {
+    type Returns<'a> = Option<i64>;
+
+    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        let (sep, limit, parts): (&str, Option<i64>, Vec<&str>) = Self::typed_args(args)?;
+        Ok(limit)
+    }
+}
+
+struct MyRangeUdf;
+
+#[register(min_args = 1, max_args = 2, enforce_max_len)]
+impl BasicUdf for MyRangeUdf {
+    type Returns<'a> = String;
+
+    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register(args(qty: i64, price: f64))]
+impl BasicUdf for MyAggUdf {
+    type Returns<'a> = f64;
+
+    fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+        todo!();
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        todo!();
+    }
+}
+
+#[register]
+impl AggregateUdf for MyAggUdf {
+    fn clear(&mut self, cfg: &UdfCfg<Process>, error: Option<NonZeroU8>) -> Result<(), NonZeroU8> {
+        todo!()
+    }
+
+    fn add(
+        &mut self,
+        cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        let Ok((qty, price)) = Self::typed_args(args) else {
+            return Ok(());
+        };
+        todo!()
+    }
+}
+
+const _: () = {
+    use udf::wrapper::RegisteredBasicUdf;
+
+    assert!(MyRangeUdf::ENFORCE_MAX_LEN);
+    assert!(!MyUdf::ENFORCE_MAX_LEN);
+};
+
+fn main() {
+    use udf::wrapper::RegisteredBasicUdf;
+
+    assert_eq!(MyUdf::ARG_RANGE, (3, Some(3)));
+    assert_eq!(MyVariadicUdf::ARG_RANGE, (1, Some(10)));
+    assert_eq!(MyRangeUdf::ARG_RANGE, (1, Some(2)));
+
+    let _ = my_udf as *const ();
+    let _ = my_udf_init as *const ();
+    let _ = my_udf_deinit as *const ();
+    let _ = my_agg_udf_add as *const ();
+}
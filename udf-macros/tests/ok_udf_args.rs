@@ -0,0 +1,81 @@
+#![allow(unused)]
+
+use udf::mock::*;
+use udf::prelude::*;
+
+#[derive(UdfArgs)]
+struct Simple<'a> {
+    name: &'a str,
+    count: Option<i64>,
+}
+
+#[derive(UdfArgs)]
+struct WithAttrs {
+    #[udf(coerce = "real")]
+    weight: Option<f64>,
+    #[udf(const_required)]
+    label: String,
+    #[udf(variadic)]
+    rest: Vec<i64>,
+}
+
+#[derive(UdfArgs)]
+struct Tuple(i64, f64);
+
+#[test]
+fn test_simple() {
+    let mut args = mock_args![(String "abc", "a", false), (Int None, "b", true)];
+    Simple::validate(args.as_init()).unwrap();
+    let Simple { name, count } = Simple::extract(args.as_process()).unwrap();
+    assert_eq!(name, "abc");
+    assert_eq!(count, None);
+
+    let mut args = mock_args![(String "abc", "a", false)];
+    assert_eq!(
+        Simple::validate(args.as_init()),
+        Err("Expected 2 arguments; got 1".to_owned())
+    );
+}
+
+#[test]
+fn test_attrs() {
+    let mut args = mock_args![
+        (Int 1, "a", false),
+        (String "label", "b", false),
+        (Int 2, "c", false),
+        (Int 3, "d", false),
+    ];
+    let init = args.as_init();
+    WithAttrs::validate(init).unwrap();
+    let types: Vec<_> = init.iter().map(|a| a.get_type_coercion()).collect();
+    assert_eq!(
+        types,
+        [SqlType::Real, SqlType::String, SqlType::Int, SqlType::Int]
+    );
+
+    let mut args = mock_args![(Real 1.5, "a", false), (String "label", "b", false)];
+    let res = WithAttrs::extract(args.as_process()).unwrap();
+    assert_eq!(res.weight, Some(1.5));
+    assert_eq!(res.label, "label");
+    assert!(res.rest.is_empty());
+
+    let mut args = mock_args![(Real 1.5, "a", false)];
+    assert_eq!(
+        WithAttrs::validate(args.as_init()),
+        Err("Expected at least 2 arguments; got 1".to_owned())
+    );
+
+    let mut args = mock_args![(Real 1.5, "a", false), (String None, "b", false)];
+    assert_eq!(
+        WithAttrs::validate(args.as_init()),
+        Err("Argument `b` must be a constant".to_owned())
+    );
+}
+
+#[test]
+fn test_tuple() {
+    let mut args = mock_args![(Int 1, "a", false), (Real 2.0, "b", false)];
+    Tuple::validate(args.as_init()).unwrap();
+    let Tuple(a, b) = Tuple::extract(args.as_process()).unwrap();
+    assert_eq!((a, b), (1, 2.0));
+}
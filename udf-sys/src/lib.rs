@@ -20,6 +20,8 @@
 #![allow(non_upper_case_globals)]
 #![allow(non_snake_case)]
 
+pub mod server;
+
 /// C builtin
 pub const true_: u32 = 1;
 
@@ -201,6 +203,92 @@ pub type Udf_func_string = Option<
 /// Function signature of a void functin (unused)
 pub type Udf_func_any = Option<unsafe extern "C" fn()>;
 
+/// Return value of component service methods. 0 indicates success.
+pub type mysql_service_status_t = ::std::ffi::c_int;
+
+/// Opaque handle to a component service
+pub type my_h_service = *mut ::std::ffi::c_void;
+
+/// The MySQL component `registry` service, used to acquire other services
+///
+/// MySQL 8 servers export `mysql_plugin_registry_acquire`, which returns this.
+/// It is not available on MariaDB.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct s_mysql_registry {
+    /// Acquire a service by name, e.g. `mysql_udf_metadata`
+    pub acquire: Option<
+        unsafe extern "C" fn(
+            service_name: *const ::std::ffi::c_char,
+            out_service: *mut my_h_service,
+        ) -> mysql_service_status_t,
+    >,
+
+    /// Acquire a service by name from the same component as another service
+    pub acquire_related: Option<
+        unsafe extern "C" fn(
+            service_name: *const ::std::ffi::c_char,
+            service: my_h_service,
+            out_service: *mut my_h_service,
+        ) -> mysql_service_status_t,
+    >,
+
+    /// Release a service acquired with `acquire` or `acquire_related`
+    pub release: Option<unsafe extern "C" fn(service: my_h_service) -> mysql_service_status_t>,
+}
+
+/// Signature of `mysql_plugin_registry_acquire`, exported by MySQL 8 servers
+pub type mysql_plugin_registry_acquire_t = unsafe extern "C" fn() -> *mut s_mysql_registry;
+
+/// The MySQL `mysql_udf_metadata` service (8.0.19 and later)
+///
+/// `extension_type` is `"charset"` or `"collation"`, and values are
+/// nul-terminated names such as `utf8mb4` or `latin1_swedish_ci`. Setting an
+/// argument's charset in `xxx_init` makes the server convert it before each
+/// call, and setting the result's charset tells the server how to interpret
+/// the returned bytes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct s_mysql_mysql_udf_metadata {
+    /// Get the charset or collation of an argument
+    pub argument_get: Option<
+        unsafe extern "C" fn(
+            udf_args: *mut UDF_ARGS,
+            extension_type: *const ::std::ffi::c_char,
+            index: ::std::ffi::c_uint,
+            out_value: *mut *mut ::std::ffi::c_void,
+        ) -> mysql_service_status_t,
+    >,
+
+    /// Get the charset or collation of the result
+    pub result_get: Option<
+        unsafe extern "C" fn(
+            udf_init: *mut UDF_INIT,
+            extension_type: *const ::std::ffi::c_char,
+            out_value: *mut *mut ::std::ffi::c_void,
+        ) -> mysql_service_status_t,
+    >,
+
+    /// Set the charset or collation an argument is converted to
+    pub argument_set: Option<
+        unsafe extern "C" fn(
+            udf_args: *mut UDF_ARGS,
+            extension_type: *const ::std::ffi::c_char,
+            index: ::std::ffi::c_uint,
+            in_value: *mut ::std::ffi::c_void,
+        ) -> mysql_service_status_t,
+    >,
+
+    /// Set the charset or collation of the result
+    pub result_set: Option<
+        unsafe extern "C" fn(
+            udf_init: *mut UDF_INIT,
+            extension_type: *const ::std::ffi::c_char,
+            in_value: *mut ::std::ffi::c_void,
+        ) -> mysql_service_status_t,
+    >,
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -0,0 +1,327 @@
+//! Rules the server follows when calling a UDF, shared by `udf`'s
+//! `MockServer` and `udf-harness`
+//!
+//! [`Value::coerce`] converts argument values to the types set in `init`, and
+//! [`CallFlags`] tracks the `is_null` and `error` flags passed to each call.
+
+use std::ffi::c_uchar;
+use std::num::NonZeroU8;
+
+use crate::Item_result;
+
+/// A SQL value passed as an argument or returned from a function
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    /// A string or binary value
+    String(Option<Vec<u8>>),
+    /// A floating point value
+    Real(Option<f64>),
+    /// An integer value
+    Int(Option<i64>),
+    /// A decimal value, in its string representation
+    Decimal(Option<String>),
+}
+
+impl Value {
+    /// True if this value is `NULL`
+    pub fn is_null(&self) -> bool {
+        match *self {
+            Self::String(ref v) => v.is_none(),
+            Self::Real(v) => v.is_none(),
+            Self::Int(v) => v.is_none(),
+            Self::Decimal(ref v) => v.is_none(),
+        }
+    }
+
+    /// The `Item_result` the server uses for this value
+    pub const fn item_result(&self) -> Item_result {
+        match *self {
+            Self::String(_) => Item_result::STRING_RESULT,
+            Self::Real(_) => Item_result::REAL_RESULT,
+            Self::Int(_) => Item_result::INT_RESULT,
+            Self::Decimal(_) => Item_result::DECIMAL_RESULT,
+        }
+    }
+
+    /// Convert to the type a UDF requested in `init`, as the server does
+    ///
+    /// Strings are parsed from their leading number (`0` if there is none),
+    /// reals and decimals are rounded to integers, and numbers are formatted
+    /// as text. `NULL` stays `NULL`.
+    #[must_use]
+    pub fn coerce(&self, to: Item_result) -> Self {
+        match to {
+            _ if self.item_result() == to => self.clone(),
+            Item_result::STRING_RESULT => Self::String(self.to_text().map(String::into_bytes)),
+            Item_result::REAL_RESULT => Self::Real(self.to_real()),
+            Item_result::INT_RESULT => Self::Int(self.to_int()),
+            Item_result::DECIMAL_RESULT => match *self {
+                Self::String(_) => Self::Decimal(self.to_real().map(|v| v.to_string())),
+                _ => Self::Decimal(self.to_text()),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    fn to_text(&self) -> Option<String> {
+        match *self {
+            Self::String(ref v) => v.as_ref().map(|b| String::from_utf8_lossy(b).into_owned()),
+            Self::Real(v) => v.map(|v| v.to_string()),
+            Self::Int(v) => v.map(|v| v.to_string()),
+            Self::Decimal(ref v) => v.clone(),
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_int(&self) -> Option<i64> {
+        match *self {
+            Self::Int(v) => v,
+            Self::Real(v) => v.map(|v| v.round() as i64),
+            Self::Decimal(ref v) => v.as_deref().map(|s| leading_int(s, true)),
+            Self::String(_) => self.to_text().map(|s| leading_int(&s, false)),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn to_real(&self) -> Option<f64> {
+        match *self {
+            Self::Int(v) => v.map(|v| v as f64),
+            Self::Real(v) => v,
+            Self::String(_) | Self::Decimal(_) => self.to_text().map(|s| leading_real(&s)),
+        }
+    }
+}
+
+/// Parse the integer at the start of `s`, saturating. If `round` is set, a
+/// fractional part of `.5` or more rounds away from zero.
+fn leading_int(s: &str, round: bool) -> i64 {
+    let s = s.trim_start();
+    let (neg, rest) = match s.as_bytes().first().copied() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let mut val = rest[..digits].bytes().fold(0i64, |acc, d| {
+        acc.saturating_mul(10).saturating_add(i64::from(d - b'0'))
+    });
+
+    let frac = &rest.as_bytes()[digits..];
+    if round && frac.first() == Some(&b'.') && matches!(frac.get(1), Some(b'5'..=b'9')) {
+        val = val.saturating_add(1);
+    }
+
+    if neg {
+        val.saturating_neg()
+    } else {
+        val
+    }
+}
+
+/// Parse the longest prefix of `s` that is a number, or `0.0`
+fn leading_real(s: &str) -> f64 {
+    let s = s.trim_start();
+    let bytes = s.as_bytes();
+    let digits = |from: usize| {
+        bytes.get(from..).map_or(0, |rest| {
+            rest.iter().take_while(|b| b.is_ascii_digit()).count()
+        })
+    };
+
+    // `[sign] digits [. digits] [e [sign] digits]`, found in one pass. Rust
+    // also accepts "inf" and "nan", which the server does not.
+    let mut end = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let mut mantissa = digits(end);
+    end += mantissa;
+    if bytes.get(end) == Some(&b'.') {
+        let frac = digits(end + 1);
+        mantissa += frac;
+        end += 1 + frac;
+    }
+    if mantissa == 0 {
+        return 0.0;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exp = digits(end + 1 + sign);
+        if exp > 0 {
+            end += 1 + sign + exp;
+        }
+    }
+    s[..end].parse().unwrap_or(0.0)
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(Some(value))
+    }
+}
+
+impl From<Option<i64>> for Value {
+    fn from(value: Option<i64>) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Real(Some(value))
+    }
+}
+
+impl From<Option<f64>> for Value {
+    fn from(value: Option<f64>) -> Self {
+        Self::Real(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(Some(value.as_bytes().to_vec()))
+    }
+}
+
+impl From<Option<&str>> for Value {
+    fn from(value: Option<&str>) -> Self {
+        Self::String(value.map(|s| s.as_bytes().to_vec()))
+    }
+}
+
+/// The `is_null` and `error` flags passed to a function, kept across the calls
+/// of one statement
+///
+/// `is_null` is reset before each call to `xxx` and `xxx_clear`. `error` is
+/// never reset by the server: once a basic function sets it, later rows are
+/// `NULL` without calling `xxx`. Aggregate functions instead receive it in
+/// every call, across groups, and may clear it themselves.
+#[derive(Clone, Debug, Default)]
+pub struct CallFlags {
+    is_null: c_uchar,
+    error: c_uchar,
+    /// Set once `clear`, `add` or `remove` is called, so `process` knows it
+    /// is finishing a group
+    in_group: bool,
+}
+
+impl CallFlags {
+    pub const fn new() -> Self {
+        Self {
+            is_null: 0,
+            error: 0,
+            in_group: false,
+        }
+    }
+
+    /// Prepare for a call to `xxx`. Returns `false` if the server would not
+    /// make it, because a basic function already set `error`.
+    pub fn start_process(&mut self) -> bool {
+        if !self.in_group && self.error != 0 {
+            return false;
+        }
+        self.is_null = 0;
+        true
+    }
+
+    /// Prepare for a call to `xxx_clear`
+    pub fn start_clear(&mut self) {
+        self.in_group = true;
+        self.is_null = 0;
+    }
+
+    /// Prepare for a call to `xxx_add` or `xxx_remove`
+    pub fn start_add(&mut self) {
+        self.in_group = true;
+    }
+
+    /// Pointers to pass as the `is_null` and `error` arguments
+    pub fn as_mut_ptrs(&mut self) -> (*mut c_uchar, *mut c_uchar) {
+        (&mut self.is_null, &mut self.error)
+    }
+
+    /// Whether `is_null` is set
+    pub const fn is_null(&self) -> bool {
+        self.is_null != 0
+    }
+
+    /// The value of `error`, if set
+    pub const fn error(&self) -> Option<NonZeroU8> {
+        NonZeroU8::new(self.error)
+    }
+
+    /// `Err` with the value of `error` if it is set, as the result of
+    /// `xxx_clear`, `xxx_add` or `xxx_remove`
+    pub fn error_result(&self) -> Result<(), NonZeroU8> {
+        self.error().map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coerce() {
+        let int = |v: Value| v.coerce(Item_result::INT_RESULT);
+        assert_eq!(int(" -12.7abc".into()), Value::Int(Some(-12)));
+        assert_eq!(int("abc".into()), Value::Int(Some(0)));
+        assert_eq!(
+            int(Value::Decimal(Some("-2.5".to_owned()))),
+            Value::Int(Some(-3))
+        );
+        assert_eq!(int(2.5.into()), Value::Int(Some(3)));
+        assert_eq!(int(Value::Real(None)), Value::Int(None));
+
+        let real = |v: Value| v.coerce(Item_result::REAL_RESULT);
+        assert_eq!(real("1.5e2xyz".into()), Value::Real(Some(150.0)));
+        assert_eq!(real("inf".into()), Value::Real(Some(0.0)));
+        assert_eq!(real("-.5e-1-".into()), Value::Real(Some(-0.05)));
+        assert_eq!(real("2.e".into()), Value::Real(Some(2.0)));
+        assert_eq!(real("+.".into()), Value::Real(Some(0.0)));
+        // These would take minutes if every prefix were tried
+        assert_eq!(
+            real("e".repeat(1 << 20).as_str().into()),
+            Value::Real(Some(0.0))
+        );
+        assert_eq!(
+            real("1-".repeat(1 << 19).as_str().into()),
+            Value::Real(Some(1.0))
+        );
+        assert_eq!(real(3.into()), Value::Real(Some(3.0)));
+
+        assert_eq!(
+            Value::from(7).coerce(Item_result::STRING_RESULT),
+            "7".into()
+        );
+        assert_eq!(
+            Value::from(0.5).coerce(Item_result::DECIMAL_RESULT),
+            Value::Decimal(Some("0.5".to_owned()))
+        );
+        assert_eq!(
+            Value::Int(None).coerce(Item_result::STRING_RESULT),
+            Value::String(None)
+        );
+    }
+
+    #[test]
+    fn test_call_flags() {
+        let mut flags = CallFlags::new();
+        let (is_null, error) = flags.as_mut_ptrs();
+        unsafe {
+            *is_null = 1;
+            *error = 2;
+        }
+        assert!(flags.is_null());
+
+        // A basic function is not called again once it sets `error`
+        assert!(!flags.start_process());
+        assert_eq!(flags.error_result(), Err(NonZeroU8::new(2).unwrap()));
+
+        // Aggregates keep `error` across groups but reset `is_null`
+        flags.start_clear();
+        assert!(!flags.is_null());
+        assert!(flags.start_process());
+        assert_eq!(flags.error(), NonZeroU8::new(2));
+    }
+}
@@ -0,0 +1,335 @@
+//! Character sets of string arguments and results
+//!
+//! String arguments are passed as bytes in the charset of their column or
+//! expression, which may be `latin1`, `utf16` or `binary` rather than UTF-8.
+//! `MySQL` 8.0.19 and later report these through the `mysql_udf_metadata`
+//! service:
+//!
+//! - [`SqlArg::charset`](crate::SqlArg::charset) and
+//!   [`SqlArg::collation`](crate::SqlArg::collation) give the names of an
+//!   argument's charset and collation.
+//! - [`SqlArg::set_charset`](crate::SqlArg::set_charset) in `init` asks the
+//!   server to convert an argument before each call. This is the simplest way
+//!   to always receive UTF-8:
+//!
+//!   ```
+//!   # use udf::prelude::*;
+//!   fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<(), String> {
+//!       for mut arg in args {
+//!           // Ignore the error on servers without charset support
+//!           let _ = arg.set_charset("utf8mb4");
+//!       }
+//!       let _ = cfg.set_result_charset("utf8mb4");
+//!       Ok(())
+//!   }
+//!   ```
+//!
+//! - [`UdfCfg::set_result_charset`](crate::UdfCfg::set_result_charset) in
+//!   `init` declares the charset of string results.
+//!
+//! `MariaDB` and older versions of `MySQL` do not provide this information, so
+//! `charset` returns `None` and setting a charset returns an error.
+//!
+//! With the `encoding_rs` feature, this module also has functions to decode
+//! and encode text in common `MySQL` charsets, and `SqlArg::decode` decodes an
+//! argument using its reported charset.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Once;
+use std::{ptr, str};
+
+use udf_sys::{
+    my_h_service, mysql_plugin_registry_acquire_t, s_mysql_mysql_udf_metadata, UDF_ARGS, UDF_INIT,
+};
+
+/// Set once we have looked for the metadata service
+static INIT: Once = Once::new();
+
+/// The metadata service, or null if the server does not provide it
+static SERVICE: AtomicPtr<s_mysql_mysql_udf_metadata> = AtomicPtr::new(ptr::null_mut());
+
+/// Find a symbol exported by the server process. `name` must be
+/// nul-terminated.
+#[cfg(any(target_os = "linux", target_vendor = "apple", target_os = "freebsd"))]
+unsafe fn server_symbol(name: &[u8]) -> *mut c_void {
+    extern "C" {
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+
+    #[cfg(target_os = "linux")]
+    let default: *mut c_void = ptr::null_mut();
+    #[cfg(not(target_os = "linux"))]
+    let default = -2isize as *mut c_void;
+
+    dlsym(default, name.as_ptr().cast())
+}
+
+/// Find a symbol exported by the server process. `name` must be
+/// nul-terminated.
+#[cfg(windows)]
+unsafe fn server_symbol(name: &[u8]) -> *mut c_void {
+    extern "system" {
+        fn GetModuleHandleW(name: *const u16) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const c_char) -> *mut c_void;
+    }
+
+    GetProcAddress(GetModuleHandleW(ptr::null()), name.as_ptr().cast())
+}
+
+/// Symbol lookup is not implemented for this platform
+#[cfg(not(any(
+    target_os = "linux",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    windows
+)))]
+unsafe fn server_symbol(_name: &[u8]) -> *mut c_void {
+    ptr::null_mut()
+}
+
+/// Acquire `mysql_udf_metadata` from the server's registry
+///
+/// The service is kept until the server exits; it belongs to the server
+/// itself, so holding it does not prevent anything from being unloaded.
+unsafe fn acquire_service() -> *mut s_mysql_mysql_udf_metadata {
+    let sym = server_symbol(b"mysql_plugin_registry_acquire\0");
+    if sym.is_null() {
+        return ptr::null_mut();
+    }
+
+    let registry_acquire = std::mem::transmute::<*mut c_void, mysql_plugin_registry_acquire_t>(sym);
+    let registry = registry_acquire();
+    let Some(acquire) = registry.as_ref().and_then(|r| r.acquire) else {
+        return ptr::null_mut();
+    };
+
+    let mut service: my_h_service = ptr::null_mut();
+    if acquire(b"mysql_udf_metadata\0".as_ptr().cast(), &mut service) != 0 {
+        return ptr::null_mut();
+    }
+    service.cast()
+}
+
+/// Get the metadata service, if the server provides it
+fn service() -> Option<&'static s_mysql_mysql_udf_metadata> {
+    INIT.call_once(|| {
+        // SAFETY: the symbol has this signature on servers that export it
+        let found = unsafe { acquire_service() };
+        if found.is_null() {
+            udf_log!(Debug: "charset information is not available from this server");
+        }
+        SERVICE.store(found, Ordering::Release);
+    });
+
+    // SAFETY: the service lives as long as the server
+    unsafe { SERVICE.load(Ordering::Acquire).as_ref() }
+}
+
+/// Get the `"charset"` or `"collation"` name of argument `index`
+pub(crate) fn argument_get<'a>(args: *mut UDF_ARGS, key: &[u8], index: usize) -> Option<&'a str> {
+    let get = service()?.argument_get?;
+    let mut out: *mut c_void = ptr::null_mut();
+    // SAFETY: `args` is valid and the service writes a nul-terminated name
+    unsafe {
+        if get(args, key.as_ptr().cast(), index.try_into().ok()?, &mut out) != 0 || out.is_null() {
+            return None;
+        }
+        CStr::from_ptr(out.cast()).to_str().ok()
+    }
+}
+
+/// Set the `"charset"` or `"collation"` of argument `index`
+pub(crate) fn argument_set(
+    args: *mut UDF_ARGS,
+    key: &[u8],
+    index: usize,
+    value: &str,
+) -> Result<(), String> {
+    let set = service()
+        .and_then(|s| s.argument_set)
+        .ok_or_else(unavailable)?;
+    let c_value = to_cstring(value)?;
+    let index = index
+        .try_into()
+        .map_err(|_| format!("invalid argument index {index}"))?;
+    // SAFETY: `args` is valid, and the server copies the name
+    let res = unsafe {
+        set(
+            args,
+            key.as_ptr().cast(),
+            index,
+            c_value.as_ptr().cast_mut().cast(),
+        )
+    };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "the server could not set {} `{value}`",
+            key_name(key)
+        ))
+    }
+}
+
+/// Set the `"charset"` or `"collation"` of the result
+pub(crate) fn result_set(initid: *mut UDF_INIT, key: &[u8], value: &str) -> Result<(), String> {
+    let set = service()
+        .and_then(|s| s.result_set)
+        .ok_or_else(unavailable)?;
+    let c_value = to_cstring(value)?;
+    // SAFETY: `initid` is valid, and the server copies the name
+    let res = unsafe {
+        set(
+            initid,
+            key.as_ptr().cast(),
+            c_value.as_ptr().cast_mut().cast(),
+        )
+    };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "the server could not set {} `{value}`",
+            key_name(key)
+        ))
+    }
+}
+
+/// Key for the charset of an argument or result
+pub(crate) const CHARSET: &[u8] = b"charset\0";
+/// Key for the collation of an argument or result
+pub(crate) const COLLATION: &[u8] = b"collation\0";
+
+/// `CHARSET` or `COLLATION` without the nul, for messages
+fn key_name(key: &[u8]) -> &str {
+    str::from_utf8(&key[..key.len() - 1]).unwrap_or_default()
+}
+
+fn unavailable() -> String {
+    "charset information is not available from this server".to_owned()
+}
+
+fn to_cstring(value: &str) -> Result<CString, String> {
+    CString::new(value).map_err(|_| format!("invalid name `{value}`"))
+}
+
+#[cfg(feature = "encoding_rs")]
+pub use self::encoding::*;
+
+#[cfg(feature = "encoding_rs")]
+mod encoding {
+    use std::borrow::Cow;
+
+    use encoding_rs::{
+        Encoding, BIG5, EUC_JP, EUC_KR, GB18030, GBK, ISO_8859_2, ISO_8859_7, ISO_8859_8, KOI8_R,
+        KOI8_U, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1250, WINDOWS_1251, WINDOWS_1256,
+        WINDOWS_1257,
+    };
+
+    /// The `encoding_rs` encoding for a `MySQL` charset name, if it is supported
+    ///
+    /// Note that `MySQL`'s `latin1` is Windows-1252, and `utf16` is big endian.
+    /// `binary` has no encoding, so it returns `None`.
+    pub fn encoding_for(charset: &str) -> Option<&'static Encoding> {
+        let enc = match charset {
+            "utf8mb4" | "utf8mb3" | "utf8" => UTF_8,
+            "latin1" | "ascii" => encoding_rs::WINDOWS_1252,
+            "latin2" => ISO_8859_2,
+            "greek" => ISO_8859_7,
+            "hebrew" => ISO_8859_8,
+            "cp1250" => WINDOWS_1250,
+            "cp1251" => WINDOWS_1251,
+            "cp1256" => WINDOWS_1256,
+            "cp1257" => WINDOWS_1257,
+            "koi8r" => KOI8_R,
+            "koi8u" => KOI8_U,
+            "sjis" | "cp932" => SHIFT_JIS,
+            "ujis" | "eucjpms" => EUC_JP,
+            "euckr" => EUC_KR,
+            "gbk" | "gb2312" => GBK,
+            "gb18030" => GB18030,
+            "big5" => BIG5,
+            "utf16" | "ucs2" => UTF_16BE,
+            "utf16le" => UTF_16LE,
+            _ => return None,
+        };
+        Some(enc)
+    }
+
+    /// Decode bytes in a `MySQL` charset, replacing malformed sequences with
+    /// `U+FFFD`
+    ///
+    /// This borrows the input when it is already valid UTF-8. Returns `None` if
+    /// the charset is not supported.
+    ///
+    /// ```
+    /// use udf::charset::decode;
+    ///
+    /// assert_eq!(decode(b"caf\xe9", "latin1").unwrap(), "café");
+    /// assert_eq!(decode(b"\x00h\x00i", "utf16").unwrap(), "hi");
+    /// assert!(decode(b"abc", "binary").is_none());
+    /// ```
+    pub fn decode<'a>(bytes: &'a [u8], charset: &str) -> Option<Cow<'a, str>> {
+        let enc = encoding_for(charset)?;
+        Some(enc.decode_without_bom_handling(bytes).0)
+    }
+
+    /// Encode a string in a `MySQL` charset, for use with
+    /// [`UdfCfg::set_result_charset`](crate::UdfCfg::set_result_charset)
+    ///
+    /// Characters that cannot be represented are written as HTML numeric
+    /// character references (e.g. `&#8364;`). Returns `None` if the charset is
+    /// not supported; this includes `utf16`, which `encoding_rs` can only
+    /// decode.
+    ///
+    /// ```
+    /// use udf::charset::encode;
+    ///
+    /// assert_eq!(&*encode("café", "latin1").unwrap(), b"caf\xe9");
+    /// assert!(encode("hi", "utf16").is_none());
+    /// ```
+    pub fn encode<'a>(s: &'a str, charset: &str) -> Option<Cow<'a, [u8]>> {
+        let enc = encoding_for(charset)?;
+        if enc.output_encoding() != enc {
+            return None;
+        }
+        Some(enc.encode(s).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_args;
+
+    #[test]
+    fn test_unavailable() {
+        // There is no server in tests, so there is no charset information
+        let mut args = mock_args![("abc", "a", false)];
+        let mut arg = args.as_init().get(0).unwrap();
+        assert_eq!(arg.charset(), None);
+        assert_eq!(arg.collation(), None);
+        assert_eq!(arg.set_charset("utf8mb4").unwrap_err(), unavailable());
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn test_encoding() {
+        assert_eq!(encoding_for("latin1"), Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(encoding_for("binary"), None);
+        assert_eq!(decode(b"\x80", "latin1").unwrap(), "€");
+        assert!(matches!(
+            decode(b"plain", "utf8mb4").unwrap(),
+            std::borrow::Cow::Borrowed("plain")
+        ));
+        assert_eq!(decode(b"h\x00i\x00", "utf16le").unwrap(), "hi");
+        assert_eq!(&*encode("€", "latin1").unwrap(), b"\x80");
+        assert_eq!(&*encode("€", "latin2").unwrap(), b"&#8364;");
+
+        let mut args = mock_args![(&b"caf\xe9"[..], "a", false)];
+        let arg = args.as_process().get(0).unwrap();
+        // Without a server the charset is unknown, so UTF-8 is assumed
+        assert_eq!(arg.decode().unwrap(), "caf\u{fffd}");
+    }
+}
@@ -68,6 +68,9 @@
 //! Replace `my_udf` with the function name, `integer` with the return type, and
 //! `libudf_test.so` with the correct file name.
 //!
+//! Every library also exports `udf_manifest`, which returns a JSON list of the
+//! functions it contains. See the [manifest] module for details.
+//!
 //! More details on building are discussed in [the project
 //! readme](https://github.com/pluots/sql-udf/blob/main/README.md). See [the
 //! `MariaDB` documentation](https://mariadb.com/kb/en/create-function-udf/) for
@@ -83,24 +86,25 @@
 //!   `mock` will be available by default. It is currently feature-gated because
 //!   it is considered unstable.)_
 //! - `logging-debug`: enable this feature to turn on debug level logging for
-//!   this crate. This uses the `udf_log!` macro and includes information about
+//!   this crate by default (see [Runtime Log Level](#runtime-log-level) to
+//!   enable it without a rebuild). This uses the `udf_log!` macro and includes information about
 //!   memory management and function calls. These will show up with your SQL
 //!   server logs, like:
 //!
 //!   ```text
-//!   2023-03-23 00:45:53+00:00 [Debug] UDF: ENTER init for 'udf_examples::lookup::Lookup6'
+//!   2023-03-23 00:45:53+00:00 [Debug] UDF: ENTER init for 'lookup6'
 //!   2023-03-23 00:45:53+00:00 [Debug] UDF: 0x7fdea4022220 24 bytes udf->server control transfer
-//!                                          (BufConverter<Lookup6, Option<String>>)
-//!   2023-03-23 00:45:53+00:00 [Debug] UDF: EXIT init for 'udf_examples::lookup::Lookup6'
-//!   2023-03-23 00:45:53+00:00 [Debug] UDF: ENTER process for 'udf_examples::lookup::Lookup6'
+//!                                          (BufConverter<Lookup6>)
+//!   2023-03-23 00:45:53+00:00 [Debug] UDF: EXIT init for 'lookup6'
+//!   2023-03-23 00:45:53+00:00 [Debug] UDF: ENTER process for 'lookup6'
 //!   2023-03-23 00:45:53+00:00 [Debug] UDF: 0x7fdea4022220 24 bytes server->udf control transfer
-//!                                          (BufConverter<Lookup6, Option<String>>)
+//!                                          (BufConverter<Lookup6>)
 //!   2023-03-23 00:45:53+00:00 [Debug] UDF: 0x7fdea4022220 24 bytes udf->server control transfer
-//!                                          (BufConverter<Lookup6, Option<String>>)
-//!   2023-03-23 00:45:53+00:00 [Debug] UDF: EXIT process for 'udf_examples::lookup::Lookup6'
-//!   2023-03-23 00:45:53+00:00 [Debug] UDF: ENTER deinit for 'udf_examples::lookup::Lookup6'
+//!                                          (BufConverter<Lookup6>)
+//!   2023-03-23 00:45:53+00:00 [Debug] UDF: EXIT process for 'lookup6'
+//!   2023-03-23 00:45:53+00:00 [Debug] UDF: ENTER deinit for 'lookup6'
 //!   2023-03-23 00:45:53+00:00 [Debug] UDF: 0x7fdea4022220 24 bytes server->udf control transfer
-//!                                          (BufConverter<Lookup6, Option<String>>)
+//!                                          (BufConverter<Lookup6>)
 //!   ```
 //!
 //!   This output can be helpful to understand the exact data flow between
@@ -108,10 +112,63 @@
 //!   library.
 //!
 //! - `logging-debug-calls` full debugging printing of the structs passed
-//!   between this library and the SQL server. Implies `logging-debug`. This
+//!   between this library and the SQL server by default (the `trace` level).
+//!   Implies `logging-debug`. This
 //!   output can be noisy, but can help to debug issues related to the lower
 //!   level interfaces (i.e. problems with this library or with the server
 //!   itself).
+//! - `rust_decimal` and `bigdecimal`: add conversions from [`SqlDecimal`] to
+//!   the decimal types of these crates.
+//! - `json`: add `SqlResult::as_json` and the `Json` wrapper, for
+//!   arguments and results that are (de)serialized with `serde_json`.
+//! - `arbitrary`: add randomized testing to the `mock` module. `check_basic`
+//!   and `check_aggregate` run a UDF on generated arguments (including `NULL`s,
+//!   invalid UTF-8, huge strings and extreme numbers) and fail if it panics or
+//!   breaks its `maybe_null` or `max_len` settings. `fuzz_basic` and
+//!   `fuzz_aggregate` do the same with input from `cargo fuzz` or `proptest`.
+//! - `log`: add the `logger` module. `logger::init()` installs a `log`
+//!   logger that writes in the server's log format, so messages from the `log`
+//!   macros (including those in dependencies) appear in the server's error
+//!   log, along with the UDF and phase that was running.
+//! - `tracing`: implies `log`. `tracing` events are forwarded to the `log`
+//!   logger when no subscriber is installed, and each call to a UDF function
+//!   runs in a `udf` span with `name` and `phase` fields.
+//! - `metrics`: count `init` and `process` calls, `NULL` results, errors,
+//!   panics and `process` latency for each registered function (see the
+//!   `metrics` module). The library also exports a `udf_stats()` function
+//!   that returns these counters as JSON.
+//! - `strict-contracts`: check every `process` result against the settings
+//!   from `init`. A `NULL` when `maybe_null` is false, a string longer than
+//!   `max_len`, or a decimal with more digits than `decimals` is logged and
+//!   returned as an error instead of being silently truncated by the server.
+//!   `mock::MockServer` panics on these violations, so tests fail. This
+//!   adds some overhead and is meant for debug and test builds.
+//! - `encoding_rs`: add `charset::decode`, `charset::encode` and
+//!   `SqlArg::decode`, to convert text in charsets such as `latin1` or `sjis`
+//!   using the charset names reported by the server (see the `charset`
+//!   module).
+//!
+//! # Runtime Log Level
+//!
+//! The debug output described above is always compiled in, and can be turned
+//! on without rebuilding with the `UDF_LOG` environment variable of the server
+//! process. It holds a comma-separated list of levels (`off`, `error`, `warn`,
+//! `note`, `debug` or `trace`), either alone to set the default or as
+//! `name=level` for a single function:
+//!
+//! ```text
+//! UDF_LOG=debug,lookup6=trace
+//! ```
+//!
+//! `debug` logs function entry and exit and memory transfers, and `trace` also
+//! logs the full state passed with each call. Names are matched against the
+//! whole SQL function name and its aliases, ignoring case and underscores, so
+//! `lookup6` matches a function registered as `lookup6` or `LOOKUP_6` but not
+//! `lookup` or `lookup60`. The variable is read the first
+//! time the library logs, so the server must be restarted for changes to take
+//! effect. Without `UDF_LOG`, the level is `debug` with `logging-debug`,
+//! `trace` with `logging-debug-calls`, and `note` otherwise. The `log`
+//! feature's logger uses the same levels.
 //!
 //! # Version Note
 //!
@@ -146,10 +203,11 @@ pub extern crate udf_sys;
 
 extern crate udf_macros;
 
-pub use udf_macros::register;
+pub use udf_macros::{register, UdfArgs};
 
 #[macro_use]
 mod macros;
+mod log_filter;
 pub mod prelude;
 pub mod traits;
 pub mod types;
@@ -163,4 +221,10 @@ pub use traits::*;
 #[doc(inline)]
 pub use types::{MYSQL_ERRMSG_SIZE, *};
 
+pub mod charset;
+#[cfg(feature = "log")]
+pub mod logger;
+pub mod manifest;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod mock;
@@ -0,0 +1,232 @@
+//! Runtime control of debug logging with the `UDF_LOG` environment variable.
+//! See the crate docs for the syntax.
+
+use std::env;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+use std::sync::Once;
+
+use crate::wrapper::RegisteredBasicUdf;
+
+/// The environment variable that is read for directives
+pub const ENV_VAR: &str = "UDF_LOG";
+
+/// Verbosity of a message or filter, from least to most verbose
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Off,
+    Error,
+    Warning,
+    Note,
+    /// Function entry and exit, and memory transfers
+    Debug,
+    /// Everything in `Debug`, plus the full state passed with each call
+    Trace,
+}
+
+impl Level {
+    fn parse(s: &str) -> Option<Self> {
+        let level = match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Self::Off,
+            "error" => Self::Error,
+            "warn" | "warning" => Self::Warning,
+            "info" | "note" => Self::Note,
+            "debug" => Self::Debug,
+            "trace" => Self::Trace,
+            _ => return None,
+        };
+        Some(level)
+    }
+
+    const fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Off,
+            1 => Self::Error,
+            2 => Self::Warning,
+            3 => Self::Note,
+            4 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+}
+
+/// Parsed directives from `UDF_LOG`
+#[derive(Debug, PartialEq, Eq)]
+struct Filter {
+    default: Level,
+    /// Normalized function names and their levels. Later entries take
+    /// priority.
+    functions: Vec<(String, Level)>,
+}
+
+impl Filter {
+    /// Parse directives, returning any that are invalid separately
+    fn parse(s: &str, default: Level) -> (Self, Vec<String>) {
+        let mut filter = Self {
+            default,
+            functions: Vec::new(),
+        };
+        let mut invalid = Vec::new();
+
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parsed = match directive.split_once('=') {
+                Some((name, level)) => Level::parse(level).map(|level| {
+                    filter.functions.push((normalize(name), level));
+                }),
+                None => Level::parse(directive).map(|level| filter.default = level),
+            };
+            if parsed.is_none() {
+                invalid.push(directive.to_owned());
+            }
+        }
+
+        (filter, invalid)
+    }
+
+    /// The most verbose level of any directive
+    fn max_level(&self) -> Level {
+        self.functions
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default, Ord::max)
+    }
+
+    /// The level for a function, given its registered name and aliases. The
+    /// last directive that names any of them applies.
+    fn level_for(&self, name: &str, aliases: &[&str]) -> Level {
+        self.functions
+            .iter()
+            .rev()
+            .find(|entry| {
+                names_match(&entry.0, name) || aliases.iter().any(|a| names_match(&entry.0, a))
+            })
+            .map_or(self.default, |&(_, level)| level)
+    }
+}
+
+/// Whether a normalized directive name matches a function name, without
+/// allocating
+fn names_match(normalized: &str, name: &str) -> bool {
+    let mut chars = name
+        .trim()
+        .chars()
+        .filter(|&c| c != '_')
+        .map(|c| c.to_ascii_lowercase());
+    normalized.chars().all(|c| chars.next() == Some(c)) && chars.next().is_none()
+}
+
+/// Lowercase and remove underscores
+fn normalize(name: &str) -> String {
+    name.trim()
+        .chars()
+        .filter(|&c| c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+static INIT: Once = Once::new();
+
+/// The most verbose level of any directive, so most checks only need to load
+/// this
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+
+/// The parsed filter, set once by `init_from_env` and never changed or freed
+/// after that, so it can be read without locking
+static FILTER: AtomicPtr<Filter> = AtomicPtr::new(ptr::null_mut());
+
+/// The level used when `UDF_LOG` is not set
+const fn default_level() -> Level {
+    if cfg!(feature = "logging-debug-calls") {
+        Level::Trace
+    } else if cfg!(feature = "logging-debug") {
+        Level::Debug
+    } else {
+        Level::Note
+    }
+}
+
+/// Read `UDF_LOG` and store the result
+fn init_from_env() {
+    let directives = env::var(ENV_VAR).unwrap_or_default();
+    let (filter, invalid) = Filter::parse(&directives, default_level());
+    MAX_LEVEL.store(filter.max_level() as u8, Ordering::Relaxed);
+    FILTER.store(Box::into_raw(Box::new(filter)), Ordering::Release);
+
+    for directive in invalid {
+        udf_log!(Warning: "ignoring invalid {ENV_VAR} directive '{directive}'");
+    }
+}
+
+/// The most verbose level enabled for any function
+pub fn max_level() -> Level {
+    INIT.call_once(init_from_env);
+    Level::from_u8(MAX_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Check whether messages at `level` should be logged for the UDF registered
+/// as `name` with `aliases`
+///
+/// This only needs an atomic load unless some directive enables `level`, and
+/// never locks.
+pub fn enabled(level: Level, name: &str, aliases: &[&str]) -> bool {
+    if level > max_level() {
+        return false;
+    }
+    // SAFETY: `max_level` has run `init_from_env`, which set the pointer to a
+    // filter that is never freed
+    let filter = unsafe { &*FILTER.load(Ordering::Acquire) };
+    level <= filter.level_for(name, aliases)
+}
+
+/// [`enabled`] for a registered UDF
+pub fn enabled_for<U: RegisteredBasicUdf + ?Sized>(level: Level) -> bool {
+    enabled(level, U::NAME, U::ALIASES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let (filter, invalid) = Filter::parse("debug, Lookup_6=trace,bad,x=loud", Level::Note);
+        assert_eq!(
+            filter,
+            Filter {
+                default: Level::Debug,
+                functions: vec![("lookup6".to_owned(), Level::Trace)],
+            }
+        );
+        assert_eq!(invalid, ["bad", "x=loud"]);
+        assert_eq!(filter.max_level(), Level::Trace);
+
+        let (filter, invalid) = Filter::parse("", Level::Note);
+        assert_eq!(filter.max_level(), Level::Note);
+        assert!(invalid.is_empty());
+    }
+
+    #[test]
+    fn test_level_for() {
+        let (filter, _) = Filter::parse("udf_sequence=trace,avg2=off,avg2=debug", Level::Note);
+        assert_eq!(filter.level_for("udf_sequence", &[]), Level::Trace);
+        assert_eq!(filter.level_for("UdfSequence", &[]), Level::Trace);
+        assert_eq!(filter.level_for("avg2", &[]), Level::Debug);
+        assert_eq!(filter.level_for("lookup6", &[]), Level::Note);
+        // Only whole names match
+        assert_eq!(filter.level_for("udf_sequence2", &[]), Level::Note);
+        assert_eq!(filter.level_for("avg", &[]), Level::Note);
+    }
+
+    #[test]
+    fn test_level_for_aliases() {
+        let (filter, _) = Filter::parse("udf=trace,lookup=debug,lookup_v2=off", Level::Note);
+        assert_eq!(filter.level_for("lookup6", &["lookup"]), Level::Debug);
+        assert_eq!(
+            filter.level_for("lookup6", &["lookup", "lookupv2"]),
+            Level::Off
+        );
+        // Crate and module names do not match
+        assert_eq!(filter.level_for("lookup6", &[]), Level::Note);
+    }
+}
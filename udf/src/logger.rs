@@ -0,0 +1,257 @@
+//! A [`log`] backend that writes to the server's error log
+//!
+//! Once [`init`] is called, messages from the `log` macros, in this crate or
+//! in any dependency, are written to `stderr` in the same format as
+//! [`udf_log!`](crate::udf_log). Messages logged while a UDF function is
+//! running name the function, as registered in SQL, and the phase (`init`,
+//! `process`, etc):
+//!
+//! ```text
+//! 2023-03-23 00:45:53+00:00 [Warning] UDF: my_dep::client: retrying (in process for 'lookup')
+//! ```
+//!
+//! With the `tracing` feature, `tracing` events are also forwarded here if no
+//! `tracing` subscriber is installed, and the wrappers enter a `udf` span
+//! with `name` (the SQL function name) and `phase` fields around each call.
+//!
+//! ```
+//! use udf::prelude::*;
+//!
+//! struct MyUdf;
+//!
+//! #[register]
+//! impl BasicUdf for MyUdf {
+//!     type Returns<'a> = i64;
+//!
+//!     fn init(_cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+//!         // Safe to call from every UDF; only the first call has an effect
+//!         udf::logger::init();
+//!         Ok(Self)
+//!     }
+//!
+//!     fn process<'a>(
+//!         &'a mut self,
+//!         _cfg: &UdfCfg<Process>,
+//!         _args: &ArgList<Process>,
+//!         _error: Option<NonZeroU8>,
+//!     ) -> Result<Self::Returns<'a>, ProcessError> {
+//!         log::info!("processing a row");
+//!         Ok(0)
+//!     }
+//! }
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::log_filter::{self, Level};
+
+thread_local! {
+    /// The UDF and phase currently running on this thread
+    static CURRENT_CALL: Cell<Option<Call>> = const { Cell::new(None) };
+}
+
+/// A running call: the UDF's registered name and aliases, and the phase
+#[derive(Clone, Copy, Debug)]
+struct Call {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    phase: &'static str,
+}
+
+/// The logger installed by [`init`]
+static LOGGER: ServerLogger = ServerLogger;
+
+/// Target of the wrapper's call entry and exit records. These are already
+/// written with `udf_log!` by the wrapper, so [`ServerLogger`] skips them.
+pub(crate) const CALLS_TARGET: &str = "udf::calls";
+
+/// A [`Log`] implementation that writes records to `stderr` in the server's
+/// log format
+///
+/// Usually this is installed with [`init`], but it may also be wrapped by
+/// another logger.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerLogger;
+
+impl Log for ServerLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level() && metadata.target() != CALLS_TARGET
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let call = CURRENT_CALL.with(Cell::get);
+        let (udf_name, aliases) = call.map_or(("", &[][..]), |c| (c.name, c.aliases));
+        if self.enabled(record.metadata())
+            && log_filter::enabled(filter_level(record.level()), udf_name, aliases)
+        {
+            let msg = format_record(
+                record.level(),
+                record.target(),
+                record.args(),
+                call.map(|c| (c.name, c.phase)),
+            );
+            udf_log!(msg);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install [`ServerLogger`] as the `log` logger, if no logger is installed
+///
+/// Levels are taken from the `UDF_LOG` environment variable, as for this
+/// crate's own debug messages (see the [crate docs](crate#runtime-log-level)).
+/// Calling this more than once has no effect, so it may be called from the
+/// `init` of every UDF in a library.
+#[inline]
+pub fn init() {
+    let _ = try_init();
+}
+
+/// Install [`ServerLogger`] as the `log` logger
+///
+/// # Errors
+///
+/// Returns an error if a logger has already been installed, by this function
+/// or any other.
+pub fn try_init() -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(match log_filter::max_level() {
+        Level::Off => LevelFilter::Off,
+        Level::Error => LevelFilter::Error,
+        Level::Warning => LevelFilter::Warn,
+        Level::Note => LevelFilter::Info,
+        Level::Debug => LevelFilter::Debug,
+        Level::Trace => LevelFilter::Trace,
+    });
+    Ok(())
+}
+
+/// The registered name of the UDF and the phase that are running on this
+/// thread, if any
+pub fn current_call() -> Option<(&'static str, &'static str)> {
+    CURRENT_CALL.with(Cell::get).map(|c| (c.name, c.phase))
+}
+
+/// Marks a UDF call as running on this thread until dropped. Used by the
+/// wrappers around every user function.
+pub(crate) struct CallScope {
+    prev: Option<Call>,
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl CallScope {
+    pub fn enter(
+        udf_name: &'static str,
+        aliases: &'static [&'static str],
+        phase: &'static str,
+    ) -> Self {
+        let call = Call {
+            name: udf_name,
+            aliases,
+            phase,
+        };
+        let prev = CURRENT_CALL.with(|cur| cur.replace(Some(call)));
+        Self {
+            prev,
+            #[cfg(feature = "tracing")]
+            _span: tracing::info_span!("udf", name = udf_name, phase = phase).entered(),
+        }
+    }
+}
+
+impl Drop for CallScope {
+    fn drop(&mut self) {
+        CURRENT_CALL.with(|cur| cur.set(self.prev));
+    }
+}
+
+/// The `UDF_LOG` level that corresponds to a `log` level
+const fn filter_level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn => Level::Warning,
+        log::Level::Info => Level::Note,
+        log::Level::Debug => Level::Debug,
+        log::Level::Trace => Level::Trace,
+    }
+}
+
+/// Format a record like `udf_log!`, with the running call if there is one
+fn format_record(
+    level: log::Level,
+    target: &str,
+    args: &fmt::Arguments<'_>,
+    call: Option<(&str, &str)>,
+) -> String {
+    let level = match level {
+        log::Level::Error => "Error",
+        log::Level::Warn => "Warning",
+        log::Level::Info => "Note",
+        log::Level::Debug | log::Level::Trace => "Debug",
+    };
+    match call {
+        Some((udf_name, phase)) => {
+            format!("[{level}] UDF: {target}: {args} (in {phase} for '{udf_name}')")
+        }
+        None => format!("[{level}] UDF: {target}: {args}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_record() {
+        assert_eq!(
+            format_record(
+                log::Level::Warn,
+                "dep::client",
+                &format_args!("retry {}", 2),
+                None
+            ),
+            "[Warning] UDF: dep::client: retry 2"
+        );
+        assert_eq!(
+            format_record(
+                log::Level::Info,
+                "dep",
+                &format_args!("hi"),
+                Some(("my_udf", "process"))
+            ),
+            "[Note] UDF: dep: hi (in process for 'my_udf')"
+        );
+    }
+
+    #[test]
+    fn test_skip_calls_target() {
+        log::set_max_level(LevelFilter::Trace);
+        let meta = |target| {
+            Metadata::builder()
+                .level(log::Level::Trace)
+                .target(target)
+                .build()
+        };
+        assert!(ServerLogger.enabled(&meta("dep")));
+        assert!(!ServerLogger.enabled(&meta(CALLS_TARGET)));
+    }
+
+    #[test]
+    fn test_call_scope() {
+        assert_eq!(current_call(), None);
+        {
+            let _outer = CallScope::enter("a_udf", &[], "init");
+            {
+                let _inner = CallScope::enter("b_udf", &[], "process");
+                assert_eq!(current_call(), Some(("b_udf", "process")));
+            }
+            assert_eq!(current_call(), Some(("a_udf", "init")));
+        }
+        assert_eq!(current_call(), None);
+    }
+}
@@ -63,8 +63,12 @@ macro_rules! udf_log {
 
 /// Log a call to a function, with optional printing of state
 ///
-/// Log calls are only printed with feature "logging-debug". Call state is only
-/// printed with feature "logging-debug-calls".
+/// Log calls are printed if the level for the UDF's registered name (or one of
+/// its aliases) is at least `debug`, and call state if it is `trace`. See
+/// [`log_filter`](crate::log_filter) for how this is configured at runtime.
+/// With feature "log", calls are also logged at `trace` level with target
+/// `udf::calls`, which [`ServerLogger`](crate::logger::ServerLogger) skips
+/// so they are not printed twice.
 macro_rules! log_call {
     // Log for entering a function
     (enter: $name:literal, $type:ty, $($state:expr),*) => {
@@ -84,23 +88,34 @@ macro_rules! log_call {
         $type:ty,
         $($state:expr),*
     ) => {{
-        #[cfg(feature = "logging-debug")]
-        $crate::udf_log!(
-            Debug: "{} {} for '{}'",
-            $enter_or_exit, $fn_name, std::any::type_name::<$type>()
+        use $crate::log_filter::{enabled, Level};
+
+        let udf_name = <$type as $crate::wrapper::RegisteredBasicUdf>::NAME;
+        let aliases = <$type as $crate::wrapper::RegisteredBasicUdf>::ALIASES;
+
+        #[cfg(feature = "log")]
+        log::trace!(
+            target: $crate::logger::CALLS_TARGET,
+            "{} {} for '{}'",
+            $enter_or_exit, $fn_name, udf_name
         );
 
-        cfg_if::cfg_if! {
-            if #[cfg(feature = "logging-debug-calls")] {
-                $crate::udf_log!(Debug: "data {} state at {}", $receive_or_return, $fn_name);
-                // For each specified item, print the expression and its value
-                $(
-                    $crate::udf_log!(
-                        Debug: "[{}]: {} = {:#?}",
-                        $receive_or_return, std::stringify!($state), $state
-                    );
-                )*
-            }
+        if enabled(Level::Debug, udf_name, aliases) {
+            $crate::udf_log!(
+                Debug: "{} {} for '{}'",
+                $enter_or_exit, $fn_name, udf_name
+            );
+        }
+
+        if enabled(Level::Trace, udf_name, aliases) {
+            $crate::udf_log!(Debug: "data {} state at {}", $receive_or_return, $fn_name);
+            // For each specified item, print the expression and its value
+            $(
+                $crate::udf_log!(
+                    Debug: "[{}]: {} = {:#?}",
+                    $receive_or_return, std::stringify!($state), $state
+                );
+            )*
         }
     }}
 }
@@ -0,0 +1,444 @@
+//! Discover the functions registered in a library
+//!
+//! `#[register]` adds a short text record for each UDF to a section of the
+//! compiled library. [`functions`] reads these records at runtime, and the
+//! exported C function `udf_manifest` returns them as JSON. Deployment tools
+//! and health checks can call it to find out what a library provides, rather
+//! than guessing from symbol names:
+//!
+//! ```text
+//! {"udf_version":"0.5.5","functions":[{"name":"avg_cost","aliases":[],
+//! "kind":"aggregate","returns":"real","maybe_null":true,"version":"0.1.0"}]}
+//! ```
+//!
+//! The section is named `udfmeta` on ELF targets, `__DATA,__udfmeta` on Apple
+//! targets, and `.udfmeta` on Windows. [`parse_records`] can be used to read
+//! it from a library without loading it, which is what `cargo udf` does.
+
+use std::ffi::{c_char, CString};
+use std::fmt::{self, Write};
+use std::sync::{Mutex, PoisonError};
+
+use crate::wrapper::META_TAG;
+use crate::SqlType;
+
+/// The kind of a registered UDF
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UdfKind {
+    /// A function that only implements `BasicUdf`
+    Basic,
+    /// A function that also implements `AggregateUdf`
+    Aggregate,
+    /// An aggregate function that also implements `AggregateUdf::remove`, so
+    /// it can be used as a window function
+    Window,
+}
+
+impl UdfKind {
+    /// The name of this kind as used in records and JSON
+    #[inline]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Basic => "basic",
+            Self::Aggregate => "aggregate",
+            Self::Window => "window",
+        }
+    }
+
+    /// True for `Aggregate` and `Window`, which need `CREATE AGGREGATE
+    /// FUNCTION`
+    #[inline]
+    pub const fn is_aggregate(self) -> bool {
+        matches!(self, Self::Aggregate | Self::Window)
+    }
+}
+
+/// A function registered with `#[register]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UdfInfo<'a> {
+    /// The SQL name of the function
+    pub name: &'a str,
+    /// Other names the function is registered under
+    pub aliases: Vec<&'a str>,
+    /// Whether this is a basic, aggregate, or window function
+    pub kind: UdfKind,
+    /// The type returned by `process`
+    pub returns: SqlType,
+    /// Whether `process` may return `NULL` other than on errors
+    pub maybe_null: bool,
+    /// Version of the crate that defines the function
+    pub version: &'a str,
+}
+
+impl<'a> UdfInfo<'a> {
+    /// Iterate the name and all aliases
+    pub fn all_names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        std::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+}
+
+/// An error reading function records
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ManifestError {
+    /// The section is not valid UTF-8
+    InvalidUtf8,
+    /// A record was written by a newer version of this crate
+    UnsupportedVersion,
+    /// A record is missing a required field or has an invalid value
+    InvalidRecord,
+}
+
+impl fmt::Display for ManifestError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match *self {
+            Self::InvalidUtf8 => "UDF metadata is not valid UTF-8",
+            Self::UnsupportedVersion => "UDF metadata was written by a newer version of `udf`",
+            Self::InvalidRecord => "invalid UDF metadata record",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Get all functions registered in the current library, sorted by name
+pub fn functions() -> Vec<UdfInfo<'static>> {
+    // Our own records are always valid, unless a newer `udf` is also linked
+    parse_records(section::get()).unwrap_or_default()
+}
+
+/// Parse the contents of the metadata section, sorted by name
+///
+/// A function that implements both `BasicUdf` and `AggregateUdf` has a record
+/// for each; these are combined into a single aggregate or window entry.
+///
+/// # Errors
+///
+/// Returns [`ManifestError::UnsupportedVersion`] if a record was written by a
+/// newer version of this crate, or another error if the data is malformed.
+pub fn parse_records(data: &[u8]) -> Result<Vec<UdfInfo<'_>>, ManifestError> {
+    let text = std::str::from_utf8(data).map_err(|_| ManifestError::InvalidUtf8)?;
+    let mut ret: Vec<UdfInfo> = Vec::new();
+
+    for line in text.split(['\0', '\n']).filter(|l| !l.is_empty()) {
+        let info = parse_record(line)?;
+        match ret.iter_mut().find(|existing| existing.name == info.name) {
+            Some(existing) if info.kind.is_aggregate() => *existing = info,
+            Some(_) => (),
+            None => ret.push(info),
+        }
+    }
+
+    ret.sort_by(|a, b| a.name.cmp(b.name));
+    Ok(ret)
+}
+
+/// Parse a single record. Unknown keys are ignored.
+fn parse_record(line: &str) -> Result<UdfInfo<'_>, ManifestError> {
+    let mut fields = line.split(' ');
+    match fields.next() {
+        Some(META_TAG) => (),
+        Some(tag) if tag.starts_with("udf") => return Err(ManifestError::UnsupportedVersion),
+        _ => return Err(ManifestError::InvalidRecord),
+    }
+
+    let mut name = None;
+    let mut aliases = Vec::new();
+    let mut kind = None;
+    let mut returns = None;
+    let mut maybe_null = false;
+    let mut version = "";
+
+    for field in fields {
+        let (key, value) = field.split_once('=').ok_or(ManifestError::InvalidRecord)?;
+        match key {
+            "name" => name = Some(value),
+            "alias" => aliases.push(value),
+            "kind" => kind = Some(parse_kind(value)?),
+            "returns" => returns = Some(parse_sql_type(value)?),
+            "maybe_null" => maybe_null = value == "true",
+            "version" => version = value,
+            _ => (),
+        }
+    }
+
+    Ok(UdfInfo {
+        name: name.ok_or(ManifestError::InvalidRecord)?,
+        aliases,
+        kind: kind.ok_or(ManifestError::InvalidRecord)?,
+        returns: returns.ok_or(ManifestError::InvalidRecord)?,
+        maybe_null,
+        version,
+    })
+}
+
+fn parse_kind(value: &str) -> Result<UdfKind, ManifestError> {
+    [UdfKind::Basic, UdfKind::Aggregate, UdfKind::Window]
+        .into_iter()
+        .find(|kind| kind.as_str() == value)
+        .ok_or(ManifestError::InvalidRecord)
+}
+
+fn parse_sql_type(value: &str) -> Result<SqlType, ManifestError> {
+    [
+        SqlType::Int,
+        SqlType::Real,
+        SqlType::String,
+        SqlType::Decimal,
+    ]
+    .into_iter()
+    .find(|ty| ty.display_name() == value)
+    .ok_or(ManifestError::InvalidRecord)
+}
+
+/// Serialize functions to the JSON returned by `udf_manifest`
+pub fn to_json(funcs: &[UdfInfo]) -> String {
+    let mut ret = String::new();
+    write!(
+        ret,
+        r#"{{"udf_version":{},"functions":["#,
+        JsonStr(env!("CARGO_PKG_VERSION"))
+    )
+    .unwrap();
+
+    for (i, func) in funcs.iter().enumerate() {
+        if i > 0 {
+            ret.push(',');
+        }
+        write!(ret, r#"{{"name":{},"aliases":["#, JsonStr(func.name)).unwrap();
+        for (j, alias) in func.aliases.iter().enumerate() {
+            if j > 0 {
+                ret.push(',');
+            }
+            write!(ret, "{}", JsonStr(alias)).unwrap();
+        }
+        write!(
+            ret,
+            r#"],"kind":"{}","returns":"{}","maybe_null":{},"version":{}}}"#,
+            func.kind.as_str(),
+            func.returns.display_name(),
+            func.maybe_null,
+            JsonStr(func.version)
+        )
+        .unwrap();
+    }
+
+    ret.push_str("]}");
+    ret
+}
+
+/// Format a string as a quoted JSON string
+pub(crate) struct JsonStr<'a>(pub &'a str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for ch in self.0.chars() {
+            match ch {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Return [`functions`] as NUL-terminated JSON (see the [module docs](self))
+///
+/// The string is created on the first call and remains valid for as long as
+/// the library is loaded.
+#[no_mangle]
+pub extern "C" fn udf_manifest() -> *const c_char {
+    static MANIFEST: Mutex<Option<CString>> = Mutex::new(None);
+
+    let mut manifest = MANIFEST.lock().unwrap_or_else(PoisonError::into_inner);
+    manifest
+        .get_or_insert_with(|| CString::new(to_json(&functions())).unwrap_or_default())
+        .as_ptr()
+}
+
+/// Locate our metadata section, using the start and end symbols that the
+/// linker provides. Each target also gets a one-byte anchor so the section
+/// always exists; NUL bytes are ignored when parsing.
+mod section {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
+    pub fn get() -> &'static [u8] {
+        #[used]
+        #[link_section = "udfmeta"]
+        static ANCHOR: [u8; 1] = [0];
+
+        extern "C" {
+            #[link_name = "__start_udfmeta"]
+            static START: u8;
+            #[link_name = "__stop_udfmeta"]
+            static STOP: u8;
+        }
+
+        // SAFETY: the linker defines these at the bounds of the section
+        unsafe { super::slice_between(std::ptr::addr_of!(START), std::ptr::addr_of!(STOP)) }
+    }
+
+    #[cfg(target_vendor = "apple")]
+    pub fn get() -> &'static [u8] {
+        #[used]
+        #[link_section = "__DATA,__udfmeta"]
+        static ANCHOR: [u8; 1] = [0];
+
+        extern "C" {
+            #[link_name = "\u{1}section$start$__DATA$__udfmeta"]
+            static START: u8;
+            #[link_name = "\u{1}section$end$__DATA$__udfmeta"]
+            static STOP: u8;
+        }
+
+        // SAFETY: the linker defines these at the bounds of the section
+        unsafe { super::slice_between(std::ptr::addr_of!(START), std::ptr::addr_of!(STOP)) }
+    }
+
+    #[cfg(windows)]
+    pub fn get() -> &'static [u8] {
+        // The linker sorts `.udfmeta$*` sections by suffix and merges them, so
+        // records (`$b`) end up between these
+        #[used]
+        #[link_section = ".udfmeta$a"]
+        static START: [u8; 1] = [0];
+        #[used]
+        #[link_section = ".udfmeta$c"]
+        static STOP: [u8; 1] = [0];
+
+        // SAFETY: both are in the same merged section
+        unsafe { super::slice_between(START.as_ptr(), STOP.as_ptr()) }
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_vendor = "apple",
+        windows
+    )))]
+    pub fn get() -> &'static [u8] {
+        &[]
+    }
+}
+
+/// Create a slice from two pointers into the same section
+///
+/// # Safety
+///
+/// Everything from `start` to `stop` must be readable for `'static`.
+#[allow(dead_code)]
+unsafe fn slice_between(start: *const u8, stop: *const u8) -> &'static [u8] {
+    let len = (stop as usize).saturating_sub(start as usize);
+    std::slice::from_raw_parts(start, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORDS: &[u8] = b"udf1 kind=basic name=avg_cost returns=real maybe_null=true \
+        version=0.1.0 alias=ac\n\0\0udf1 kind=basic name=sum_int returns=int \
+        maybe_null=false version=0.1.0 future=1\n\
+        udf1 kind=window name=avg_cost returns=real maybe_null=true version=0.1.0 alias=ac\n";
+
+    #[test]
+    fn test_parse_records() {
+        let funcs = parse_records(RECORDS).unwrap();
+        assert_eq!(
+            funcs,
+            [
+                UdfInfo {
+                    name: "avg_cost",
+                    aliases: vec!["ac"],
+                    kind: UdfKind::Window,
+                    returns: SqlType::Real,
+                    maybe_null: true,
+                    version: "0.1.0",
+                },
+                UdfInfo {
+                    name: "sum_int",
+                    aliases: vec![],
+                    kind: UdfKind::Basic,
+                    returns: SqlType::Int,
+                    maybe_null: false,
+                    version: "0.1.0",
+                },
+            ]
+        );
+        assert_eq!(funcs[0].all_names().collect::<Vec<_>>(), ["avg_cost", "ac"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_records(b"udf2 kind=basic name=a returns=int"),
+            Err(ManifestError::UnsupportedVersion)
+        );
+        assert_eq!(
+            parse_records(b"udf1 kind=basic returns=int"),
+            Err(ManifestError::InvalidRecord)
+        );
+        assert_eq!(
+            parse_records(b"udf1 kind=basic name=a returns=blob"),
+            Err(ManifestError::InvalidRecord)
+        );
+        assert_eq!(parse_records(b"\0\0"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let funcs = parse_records(RECORDS).unwrap();
+        assert_eq!(
+            to_json(&funcs),
+            format!(
+                "{{\"udf_version\":\"{}\",\"functions\":[\
+                 {{\"name\":\"avg_cost\",\"aliases\":[\"ac\"],\"kind\":\"window\",\
+                 \"returns\":\"real\",\"maybe_null\":true,\"version\":\"0.1.0\"}},\
+                 {{\"name\":\"sum_int\",\"aliases\":[],\"kind\":\"basic\",\
+                 \"returns\":\"int\",\"maybe_null\":false,\"version\":\"0.1.0\"}}]}}",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert_eq!(JsonStr("a\"\\\n").to_string(), r#""a\"\\\u000a""#);
+    }
+
+    #[test]
+    fn test_own_functions() {
+        // Nothing is registered in this crate except `udf_stats`, but the
+        // section must exist
+        let names: Vec<_> = functions().iter().map(|f| f.name).collect();
+        let expected: &[&str] = if cfg!(feature = "metrics") {
+            &["udf_stats"]
+        } else {
+            &[]
+        };
+        assert_eq!(names, expected);
+        let json = unsafe { std::ffi::CStr::from_ptr(udf_manifest()) };
+        if cfg!(feature = "metrics") {
+            assert!(json.to_str().unwrap().contains(r#""name":"udf_stats""#));
+        } else {
+            assert!(json.to_str().unwrap().ends_with(r#""functions":[]}"#));
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Per-function call counters, enabled with the `metrics` feature
+//!
+//! The wrappers generated by `#[register]` count calls for each function in a
+//! static that `#[register]` creates (aliases share the counters of the main
+//! name), so counting a call never locks. Counters are global to the library,
+//! so they cover all connections since it was loaded.
+//!
+//! With this feature, the library also exports a function `udf_stats` that
+//! returns the counters as JSON, so they can be checked from SQL:
+//!
+//! ```sql
+//! CREATE FUNCTION udf_stats RETURNS string SONAME 'libudf_examples.so';
+//! SELECT udf_stats();
+//! ```
+//!
+//! ```text
+//! {"functions":[{"name":"lookup6","init_ok":2,"init_failed":0,"calls":1500,
+//! "nulls":3,"errors":0,"panics":0,"total_us":5120,"max_us":85}]}
+//! ```
+//!
+//! `calls`, `nulls`, `errors` and the latencies are for `process`. `panics`
+//! counts panics caught in any function, and `init_failed` includes `init`
+//! returning an error.
+
+use std::fmt::Write;
+use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Once, PoisonError, RwLock};
+use std::time::Duration;
+
+use crate::manifest::JsonStr;
+use crate::prelude::*;
+use crate::wrapper::{BufConverter, RegisteredBasicUdf};
+
+/// A snapshot of the counters for one function
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FunctionStats {
+    /// The function's name, from `#[register]`
+    pub name: &'static str,
+    /// Successful calls to `init`
+    pub init_ok: u64,
+    /// Calls to `init` that returned an error or panicked
+    pub init_failed: u64,
+    /// Calls to `process`
+    pub calls: u64,
+    /// `process` calls that returned `NULL`
+    pub nulls: u64,
+    /// `process` calls that returned an error or panicked
+    pub errors: u64,
+    /// Panics caught in any function
+    pub panics: u64,
+    /// Total time spent in `process`
+    pub total_time: Duration,
+    /// Longest time spent in a single `process` call
+    pub max_time: Duration,
+}
+
+/// Live counters for one function, held in a static created by `#[register]`
+#[derive(Debug)]
+#[doc(hidden)]
+pub struct Counters {
+    /// Run once to add these counters to the registry for `udf_stats`
+    registered: Once,
+    init_ok: AtomicU64,
+    init_failed: AtomicU64,
+    calls: AtomicU64,
+    nulls: AtomicU64,
+    errors: AtomicU64,
+    panics: AtomicU64,
+    total_ns: AtomicU64,
+    max_ns: AtomicU64,
+}
+
+/// The result of one `process` call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Value,
+    Null,
+    Error,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Counters {
+    pub const fn new() -> Self {
+        Self {
+            registered: Once::new(),
+            init_ok: AtomicU64::new(0),
+            init_failed: AtomicU64::new(0),
+            calls: AtomicU64::new(0),
+            nulls: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+            total_ns: AtomicU64::new(0),
+            max_ns: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record_init(&self, ok: bool) {
+        let counter = if ok { &self.init_ok } else { &self.init_failed };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_process(&self, outcome: Outcome, elapsed: Duration) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match outcome {
+            Outcome::Value => (),
+            Outcome::Null => {
+                self.nulls.fetch_add(1, Ordering::Relaxed);
+            }
+            Outcome::Error => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.total_ns.fetch_add(ns, Ordering::Relaxed);
+        self.max_ns.fetch_max(ns, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_panic(&self) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, name: &'static str) -> FunctionStats {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        FunctionStats {
+            name,
+            init_ok: get(&self.init_ok),
+            init_failed: get(&self.init_failed),
+            calls: get(&self.calls),
+            nulls: get(&self.nulls),
+            errors: get(&self.errors),
+            panics: get(&self.panics),
+            total_time: Duration::from_nanos(get(&self.total_ns)),
+            max_time: Duration::from_nanos(get(&self.max_ns)),
+        }
+    }
+}
+
+/// Counters for every function that has been called, in order of first use.
+/// This is only locked to add a function and to take a snapshot.
+static REGISTRY: RwLock<Vec<(&'static str, &'static Counters)>> = RwLock::new(Vec::new());
+
+/// The counters for a registered UDF, added to the registry on first use
+pub(crate) fn counters<U: RegisteredBasicUdf>() -> &'static Counters {
+    let counters = U::counters();
+    counters.registered.call_once(|| {
+        REGISTRY
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((U::NAME, counters));
+    });
+    counters
+}
+
+/// Counters found or created by name, under the registry lock. This is the
+/// default for `RegisteredBasicUdf::counters`, for impls written without
+/// `#[register]`.
+pub(crate) fn counters_by_name(name: &'static str) -> &'static Counters {
+    let mut entries = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(&(_, found)) = entries.iter().find(|entry| entry.0 == name) {
+        return found;
+    }
+    let new: &'static Counters = Box::leak(Box::new(Counters::new()));
+    // Already in the registry, so `counters` does not add it again
+    new.registered.call_once(|| ());
+    entries.push((name, new));
+    new
+}
+
+/// Get the current counters for every function that has been called
+pub fn snapshot() -> Vec<FunctionStats> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|&(name, counters)| counters.snapshot(name))
+        .collect()
+}
+
+/// Serialize stats to the JSON returned by `udf_stats`
+pub fn to_json(stats: &[FunctionStats]) -> String {
+    let mut ret = String::from(r#"{"functions":["#);
+    for (i, fstats) in stats.iter().enumerate() {
+        if i > 0 {
+            ret.push(',');
+        }
+        write!(
+            ret,
+            concat!(
+                r#"{{"name":{},"init_ok":{},"init_failed":{},"calls":{},"nulls":{},"#,
+                r#""errors":{},"panics":{},"total_us":{},"max_us":{}}}"#
+            ),
+            JsonStr(fstats.name),
+            fstats.init_ok,
+            fstats.init_failed,
+            fstats.calls,
+            fstats.nulls,
+            fstats.errors,
+            fstats.panics,
+            fstats.total_time.as_micros(),
+            fstats.max_time.as_micros(),
+        )
+        .unwrap();
+    }
+    ret.push_str("]}");
+    ret
+}
+
+/// The `udf_stats()` function, which returns [`snapshot`] as JSON
+#[derive(Debug)]
+pub struct UdfStats;
+
+impl BasicUdf for UdfStats {
+    type Returns<'a> = String;
+
+    fn init(cfg: &UdfCfg<Init>, _args: &ArgList<Init>) -> Result<Self, String> {
+        // The result may be any length; we keep it in our own buffer
+        cfg.set_max_len(u32::MAX.into());
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<Process>,
+        _args: &ArgList<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Ok(to_json(&snapshot()))
+    }
+}
+
+impl RegisteredBasicUdf for UdfStats {
+    const NAME: &'static str = "udf_stats";
+    const ALIASES: &'static [&'static str] = &[];
+    const DEFAULT_NAME_USED: bool = false;
+    const ARG_RANGE: (usize, Option<usize>) = (0, Some(0));
+
+    fn counters() -> &'static Counters {
+        static COUNTERS: Counters = Counters::new();
+        &COUNTERS
+    }
+}
+
+/// The metadata record that `#[register]` would create, so `udf_stats`
+/// appears in `udf_manifest` and `cargo udf`
+const _: () = {
+    const VERSION: &str = env!("CARGO_PKG_VERSION");
+    const LEN: usize = crate::wrapper::basic_meta_len::<UdfStats>(VERSION);
+
+    #[used]
+    #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__udfmeta")]
+    #[cfg_attr(windows, link_section = ".udfmeta$b")]
+    #[cfg_attr(not(any(target_vendor = "apple", windows)), link_section = "udfmeta")]
+    static UDF_META: [u8; LEN] = crate::wrapper::basic_meta::<UdfStats, LEN>(VERSION);
+};
+
+/// `udf_stats_init`
+///
+/// # Safety
+///
+/// Must only be called by the server, with valid `UDF_INIT` and `UDF_ARGS`.
+#[no_mangle]
+pub unsafe extern "C" fn udf_stats_init(
+    initid: *mut udf_sys::UDF_INIT,
+    args: *mut udf_sys::UDF_ARGS,
+    message: *mut std::ffi::c_char,
+) -> bool {
+    crate::wrapper::wrap_init::<BufConverter<UdfStats>, UdfStats>(initid, args, message)
+}
+
+/// `udf_stats_deinit`
+///
+/// # Safety
+///
+/// Must only be called by the server, after `udf_stats_init` succeeded.
+#[no_mangle]
+pub unsafe extern "C" fn udf_stats_deinit(initid: *mut udf_sys::UDF_INIT) {
+    crate::wrapper::wrap_deinit::<BufConverter<UdfStats>, UdfStats>(initid);
+}
+
+/// `udf_stats`
+///
+/// # Safety
+///
+/// Must only be called by the server, after `udf_stats_init` succeeded.
+#[no_mangle]
+pub unsafe extern "C" fn udf_stats(
+    initid: *mut udf_sys::UDF_INIT,
+    args: *mut udf_sys::UDF_ARGS,
+    result: *mut std::ffi::c_char,
+    length: *mut std::ffi::c_ulong,
+    is_null: *mut std::ffi::c_uchar,
+    error: *mut std::ffi::c_uchar,
+) -> *const std::ffi::c_char {
+    crate::wrapper::wrap_process_buf::<BufConverter<UdfStats>, UdfStats>(
+        initid, args, result, length, is_null, error,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Registered;
+
+    impl RegisteredBasicUdf for Registered {
+        const NAME: &'static str = "metrics_test";
+        const ALIASES: &'static [&'static str] = &[];
+        const DEFAULT_NAME_USED: bool = false;
+
+        fn counters() -> &'static Counters {
+            static COUNTERS: Counters = Counters::new();
+            &COUNTERS
+        }
+    }
+
+    #[test]
+    fn test_counters() {
+        let counters = counters::<Registered>();
+        assert!(std::ptr::eq(counters, super::counters::<Registered>()));
+        let registered = snapshot()
+            .iter()
+            .filter(|s| s.name == "metrics_test")
+            .count();
+        assert_eq!(registered, 1);
+
+        counters.record_init(true);
+        counters.record_init(false);
+        counters.record_process(Outcome::Value, Duration::from_micros(30));
+        counters.record_process(Outcome::Null, Duration::from_micros(10));
+        counters.record_process(Outcome::Error, Duration::from_micros(20));
+        counters.record_panic();
+
+        let stats = snapshot()
+            .into_iter()
+            .find(|s| s.name == "metrics_test")
+            .unwrap();
+        let expected = FunctionStats {
+            name: "metrics_test",
+            init_ok: 1,
+            init_failed: 1,
+            calls: 3,
+            nulls: 1,
+            errors: 1,
+            panics: 1,
+            total_time: Duration::from_micros(60),
+            max_time: Duration::from_micros(30),
+        };
+        assert_eq!(stats, expected);
+        assert_eq!(
+            to_json(&[expected]),
+            concat!(
+                r#"{"functions":[{"name":"metrics_test","init_ok":1,"init_failed":1,"#,
+                r#""calls":3,"nulls":1,"errors":1,"panics":1,"total_us":60,"max_us":30}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_counters_by_name() {
+        struct Unregistered;
+
+        impl RegisteredBasicUdf for Unregistered {
+            const NAME: &'static str = "metrics_by_name";
+            const ALIASES: &'static [&'static str] = &[];
+            const DEFAULT_NAME_USED: bool = false;
+        }
+
+        let counters = counters::<Unregistered>();
+        assert!(std::ptr::eq(counters, super::counters::<Unregistered>()));
+        let registered = snapshot()
+            .iter()
+            .filter(|s| s.name == "metrics_by_name")
+            .count();
+        assert_eq!(registered, 1);
+    }
+}
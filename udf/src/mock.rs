@@ -90,14 +90,26 @@
 
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::new_without_default)]
+
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod runner;
+mod server;
+
 use std::cell::UnsafeCell;
 use std::ffi::{c_char, c_uint, c_ulong};
 use std::fmt::Debug;
 use std::marker::PhantomPinned;
 use std::ptr;
 
+use udf_sys::server::Value;
 use udf_sys::{Item_result, UDF_ARGS, UDF_INIT};
 
+#[cfg(feature = "arbitrary")]
+pub use fuzz::{check_aggregate, check_basic, fuzz_aggregate, fuzz_basic, FuzzCase};
+pub use runner::AggregateRunner;
+pub use server::{MockOutput, MockServer, MockValue};
+
 pub use crate::mock_args;
 use crate::traits::{Init, Process};
 use crate::types::{ArgList, UdfCfg};
@@ -190,6 +202,13 @@ impl MockUdfCfg {
         unsafe { UdfCfg::from_raw_ptr(self.inner.get()) }
     }
 
+    /// Flush `max_len` and get a pointer to the `UDF_INIT` for calling the
+    /// generated C functions
+    pub(crate) fn as_raw_ptr(&mut self) -> *mut UDF_INIT {
+        self.as_init();
+        self.inner.get()
+    }
+
     /// Get or set the `maybe_null` field
     pub fn maybe_null(&mut self) -> &mut bool {
         unsafe { &mut (*self.inner.get()).maybe_null }
@@ -241,7 +260,7 @@ impl BuiltArgs {
 }
 
 /// A single mock argument to be used when constructing a [`MockArgList`]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MockArg {
     value: MockArgData,
     attribute: String,
@@ -249,7 +268,7 @@ pub struct MockArg {
 }
 
 /// A representation of data within a mock argument
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum MockArgData {
     String(Option<String>),
@@ -268,6 +287,31 @@ impl MockArgData {
             Self::Decimal(_) => Item_result::DECIMAL_RESULT,
         }
     }
+
+    /// This value as the server's representation, which is the same for
+    /// strings and bytes
+    fn to_value(&self) -> Value {
+        match *self {
+            Self::String(ref v) => Value::String(v.clone().map(String::into_bytes)),
+            Self::Bytes(ref v) => Value::String(v.clone()),
+            Self::Real(v) => Value::Real(v),
+            Self::Int(v) => Value::Int(v),
+            Self::Decimal(ref v) => Value::Decimal(v.clone()),
+        }
+    }
+
+    /// Convert this value to another type the way the server does for
+    /// coercions. Bytes stay bytes if they are not converted.
+    fn coerce(&self, to: Item_result) -> Self {
+        match self.to_value().coerce(to) {
+            Value::String(v) if matches!(*self, Self::Bytes(_)) => Self::Bytes(v),
+            Value::String(v) => Self::String(v.map(|b| String::from_utf8_lossy(&b).into_owned())),
+            Value::Real(v) => Self::Real(v),
+            Value::Int(v) => Self::Int(v),
+            Value::Decimal(v) => Self::Decimal(v),
+            _ => self.clone(),
+        }
+    }
 }
 
 impl From<&str> for MockArgData {
@@ -382,8 +426,17 @@ impl MockArgList {
     /// Build the arguments
     ///
     /// This should always be safe to unwrap
+    fn build<S: UdfState>(&mut self) -> &ArgList<'_, S> {
+        let udf_args_ref = self.as_raw_ptr();
+
+        // SAFETY: we created this data and so it should be sound
+        unsafe { ArgList::from_raw_ptr(udf_args_ref) }
+    }
+
+    /// Build the arguments and get a pointer to the resulting `UDF_ARGS`, for
+    /// calling the generated C functions
     #[allow(clippy::pattern_type_mismatch)]
-    fn build<S: UdfState>(&mut self) -> &ArgList<S> {
+    pub(crate) fn as_raw_ptr(&mut self) -> *mut UDF_ARGS {
         let mut building = BuiltArgs::new();
 
         for arg in &self.unbuilt_args {
@@ -447,12 +500,33 @@ impl MockArgList {
 
         self.built_args = Some(building);
         self.set_udf_args();
-        let udf_args_ref = self.udf_args.as_mut().unwrap().get();
+        self.udf_args.as_mut().unwrap().get()
+    }
 
-        unsafe { ArgList::from_raw_ptr(udf_args_ref) }
+    /// The argument types after the last build, including any coercions set
+    /// by `init`
+    pub(crate) fn built_types(&self) -> &[Item_result] {
+        self.built_args
+            .as_ref()
+            .map_or(&[], |built| built.arg_types.as_slice())
+    }
 
-        // SAFETY: we created this data and so it should be sound
-        // unsafe { Some(ArgList::from_udf_args(udf_args)) }
+    /// The number of arguments
+    pub(crate) fn len(&self) -> usize {
+        self.unbuilt_args.len()
+    }
+
+    /// A copy of these arguments with each value converted to the given type,
+    /// as the server does after `init` sets coercions
+    pub(crate) fn coerced(&self, types: &[Item_result]) -> Self {
+        let mut ret = Self::new();
+        for (arg, ty) in self.unbuilt_args.iter().zip(types) {
+            ret.push_arg(MockArg {
+                value: arg.value.coerce(*ty),
+                ..arg.clone()
+            });
+        }
+        ret
     }
 
     /// Just update our `udf_args` from our `built_args`
@@ -477,12 +551,12 @@ impl MockArgList {
     }
 
     /// Create a `&ArgList<Init>` for testing with the `init()` function call
-    pub fn as_init(&mut self) -> &ArgList<Init> {
+    pub fn as_init(&mut self) -> &ArgList<'_, Init> {
         self.build()
     }
 
     /// Create a `&ArgList<Process>` for testing with the `process()` function call
-    pub fn as_process(&mut self) -> &ArgList<Process> {
+    pub fn as_process(&mut self) -> &ArgList<'_, Process> {
         self.build()
     }
 
@@ -576,8 +650,6 @@ macro_rules! mock_args {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn test_mock_args_macro() {
         let _args = mock_args![
@@ -592,4 +664,16 @@ mod tests {
             (Decimal None, "NULL", true),
         ];
     }
+
+    #[test]
+    fn test_coerce() {
+        use udf_sys::Item_result::{INT_RESULT, STRING_RESULT};
+
+        use super::MockArgData::{Bytes, Int, String};
+
+        let bytes = Bytes(Some(vec![b'1', b'2', 0xff]));
+        assert!(matches!(bytes.coerce(INT_RESULT), Int(Some(12))));
+        assert!(matches!(bytes.coerce(STRING_RESULT), Bytes(Some(ref b)) if b.len() == 3));
+        assert!(matches!(Int(Some(7)).coerce(STRING_RESULT), String(Some(ref s)) if s == "7"));
+    }
 }
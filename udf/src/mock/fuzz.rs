@@ -0,0 +1,509 @@
+//! Randomized testing of registered UDFs with the `arbitrary` crate
+
+use std::any::type_name;
+use std::fmt::Write;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use super::{MockArg, MockArgData, MockArgList, MockOutput, MockServer};
+use crate::wrapper::{caught_panics, AggregateSymbols, BasicSymbols};
+
+/// Most arguments in a generated statement
+const MAX_ARGS: usize = 4;
+
+/// Most rows in a generated statement
+const MAX_ROWS: usize = 4;
+
+/// Longest generated string. Huge strings are made by repeating a character,
+/// so they are cheap to generate from a small input.
+const MAX_HUGE_LEN: usize = 1 << 20;
+
+/// The server's `max_length` for numeric arguments
+const NUMERIC_MAX_LEN: u64 = 21;
+
+/// Integers that are likely to find bugs
+const EDGE_INTS: [i64; 8] = [0, 1, -1, i64::MIN, i64::MAX, i64::MIN + 1, 255, 256];
+
+/// Reals that are likely to find bugs
+const EDGE_REALS: [f64; 10] = [
+    0.0,
+    -0.0,
+    f64::NAN,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    f64::MIN,
+    f64::MAX,
+    f64::MIN_POSITIVE,
+    f64::EPSILON,
+    1e-300,
+];
+
+/// The type of a generated argument, which is the same in every row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArgKind {
+    String,
+    Bytes,
+    Real,
+    Int,
+    Decimal,
+}
+
+impl<'a> Arbitrary<'a> for ArgKind {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&[
+            Self::String,
+            Self::Bytes,
+            Self::Real,
+            Self::Int,
+            Self::Decimal,
+        ])
+        .copied()
+    }
+}
+
+/// Generate a value of the given type, which may be `NULL` if `nullable`
+fn arbitrary_value(
+    u: &mut Unstructured<'_>,
+    kind: ArgKind,
+    nullable: bool,
+) -> arbitrary::Result<MockArgData> {
+    if nullable && u.ratio(1, 4)? {
+        return Ok(match kind {
+            ArgKind::String => MockArgData::String(None),
+            ArgKind::Bytes => MockArgData::Bytes(None),
+            ArgKind::Real => MockArgData::Real(None),
+            ArgKind::Int => MockArgData::Int(None),
+            ArgKind::Decimal => MockArgData::Decimal(None),
+        });
+    }
+
+    let value = match kind {
+        ArgKind::String if u.ratio(1, 8)? => {
+            let c = char::arbitrary(u)?;
+            let len = u.int_in_range(256..=MAX_HUGE_LEN)?;
+            MockArgData::String(Some(std::iter::repeat(c).take(len).collect()))
+        }
+        ArgKind::String => MockArgData::String(Some(String::arbitrary(u)?)),
+        ArgKind::Bytes => MockArgData::Bytes(Some(Vec::arbitrary(u)?)),
+        ArgKind::Real if u.ratio(1, 2)? => MockArgData::Real(Some(*u.choose(&EDGE_REALS)?)),
+        ArgKind::Real => MockArgData::Real(Some(f64::arbitrary(u)?)),
+        ArgKind::Int if u.ratio(1, 2)? => MockArgData::Int(Some(*u.choose(&EDGE_INTS)?)),
+        ArgKind::Int => MockArgData::Int(Some(i64::arbitrary(u)?)),
+        ArgKind::Decimal => {
+            let digits = i64::arbitrary(u)?;
+            let scale = u.int_in_range(0..=30)?;
+            MockArgData::Decimal(Some(format_decimal(digits, scale)))
+        }
+    };
+    Ok(value)
+}
+
+/// A `NULL` of the same type as `value`
+fn null_like(value: &MockArgData) -> MockArgData {
+    match *value {
+        MockArgData::String(_) => MockArgData::String(None),
+        MockArgData::Bytes(_) => MockArgData::Bytes(None),
+        MockArgData::Real(_) => MockArgData::Real(None),
+        MockArgData::Int(_) => MockArgData::Int(None),
+        MockArgData::Decimal(_) => MockArgData::Decimal(None),
+    }
+}
+
+/// Format `digits * 10^-scale` the way the server formats decimals
+fn format_decimal(digits: i64, scale: usize) -> String {
+    let abs = format!("{:0>width$}", digits.unsigned_abs(), width = scale + 1);
+    let (int_part, frac_part) = abs.split_at(abs.len() - scale);
+    let mut ret = String::new();
+    if digits < 0 {
+        ret.push('-');
+    }
+    ret.push_str(int_part);
+    if scale > 0 {
+        let _ = write!(ret, ".{frac_part}");
+    }
+    ret
+}
+
+impl<'a> Arbitrary<'a> for MockArgData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let kind = ArgKind::arbitrary(u)?;
+        arbitrary_value(u, kind, true)
+    }
+}
+
+impl<'a> Arbitrary<'a> for MockArg {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let maybe_null = bool::arbitrary(u)?;
+        let kind = ArgKind::arbitrary(u)?;
+        let value = arbitrary_value(u, kind, maybe_null)?;
+        Ok(Self::new(value, &String::arbitrary(u)?, maybe_null))
+    }
+}
+
+/// One generated argument: a column or constant in a SQL expression
+#[derive(Clone, Debug)]
+struct FuzzColumn {
+    attribute: String,
+    maybe_null: bool,
+    /// Whether this is a constant, which is the only kind of argument that
+    /// has a value during `init`
+    constant: bool,
+    /// One value per row. Constants have the same value in each row.
+    values: Vec<MockArgData>,
+}
+
+/// A randomly generated statement for a UDF: some arguments, with consistent
+/// types and nullability across a few rows
+///
+/// As in the server, `UDF_INIT` is set up from the arguments before `init`:
+/// `maybe_null` is set if any argument may be `NULL`, `const_item` if all are
+/// constants, and `max_len` is the longest any argument can be. Only constants
+/// have a value during `init`; other arguments are `NULL`. Run it with
+/// [`run_basic`](Self::run_basic) or [`run_aggregate`](Self::run_aggregate).
+#[derive(Clone, Debug)]
+pub struct FuzzCase {
+    columns: Vec<FuzzColumn>,
+    rows: usize,
+    decimals: u32,
+}
+
+impl<'a> Arbitrary<'a> for FuzzCase {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let rows = u.int_in_range(1..=MAX_ROWS)?;
+        let arg_count = u.int_in_range(0..=MAX_ARGS)?;
+        let mut columns = Vec::with_capacity(arg_count);
+
+        for _ in 0..arg_count {
+            let kind = ArgKind::arbitrary(u)?;
+            let maybe_null = bool::arbitrary(u)?;
+            let constant = bool::arbitrary(u)?;
+
+            let values = if constant {
+                vec![arbitrary_value(u, kind, maybe_null)?; rows]
+            } else {
+                (0..rows)
+                    .map(|_| arbitrary_value(u, kind, maybe_null))
+                    .collect::<arbitrary::Result<_>>()?
+            };
+
+            columns.push(FuzzColumn {
+                attribute: String::arbitrary(u)?,
+                maybe_null,
+                constant,
+                values,
+            });
+        }
+
+        Ok(Self {
+            columns,
+            rows,
+            decimals: u.int_in_range(0..=31)?,
+        })
+    }
+}
+
+impl FuzzCase {
+    /// Call `init`, then `process` for each row, then `deinit` on a basic UDF
+    ///
+    /// # Panics
+    ///
+    /// Panics with a description of the problem and the case if the UDF
+    /// panics, or if a result does not respect `maybe_null` or `max_len` as
+    /// set after `init`. A failed `init` is not a problem.
+    pub fn run_basic<U: BasicSymbols>(&self) {
+        let mut server = MockServer::<U>::new();
+        let rows = self.arg_lists();
+        let Some((max_len, maybe_null)) = self.init(&mut server) else {
+            return;
+        };
+
+        for row in &rows {
+            let out = self.checked::<U, _>("process", || server.process(row));
+            self.check_output::<U>(&out, max_len, maybe_null);
+        }
+
+        self.checked::<U, _>("deinit", || drop(server));
+    }
+
+    /// Call `init`, then `clear`, `add` for each row and `process` for the
+    /// group, then `deinit` on an aggregate UDF. An empty group is also
+    /// processed.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`run_basic`](Self::run_basic).
+    pub fn run_aggregate<U: AggregateSymbols>(&self) {
+        let mut server = MockServer::<U>::new();
+        let rows = self.arg_lists();
+        let Some((max_len, maybe_null)) = self.init(&mut server) else {
+            return;
+        };
+
+        // Errors are part of the output, so results are not needed
+        let _ = self.checked::<U, _>("clear", || server.clear());
+        for row in &rows {
+            let _ = self.checked::<U, _>("add", || server.add(row));
+        }
+        let out = self.checked::<U, _>("process", || server.process(&rows[rows.len() - 1]));
+        self.check_output::<U>(&out, max_len, maybe_null);
+
+        let _ = self.checked::<U, _>("clear", || server.clear());
+        let out = self.checked::<U, _>("process", || server.process(&rows[0]));
+        self.check_output::<U>(&out, max_len, maybe_null);
+
+        self.checked::<U, _>("deinit", || drop(server));
+    }
+
+    /// One argument list per row
+    fn arg_lists(&self) -> Vec<MockArgList> {
+        (0..self.rows)
+            .map(|row| self.arg_list(|col| col.values[row].clone()))
+            .collect()
+    }
+
+    /// The argument list for `init`, where only constants have a value
+    fn init_args(&self) -> MockArgList {
+        self.arg_list(|col| {
+            if col.constant {
+                col.values[0].clone()
+            } else {
+                null_like(&col.values[0])
+            }
+        })
+    }
+
+    fn arg_list(&self, mut value: impl FnMut(&FuzzColumn) -> MockArgData) -> MockArgList {
+        let mut list = MockArgList::new();
+        for col in &self.columns {
+            list.push_arg(MockArg::new(value(col), &col.attribute, col.maybe_null));
+        }
+        list
+    }
+
+    /// Set up the server's `UDF_INIT` and call `init`. Returns `max_len` and
+    /// `maybe_null` after `init`, or `None` if it failed.
+    fn init<U: BasicSymbols>(&self, server: &mut MockServer<U>) -> Option<(u64, bool)> {
+        let max_len = self
+            .columns
+            .iter()
+            .flat_map(|col| &col.values)
+            .map(|value| match *value {
+                MockArgData::String(ref v) | MockArgData::Decimal(ref v) => {
+                    v.as_ref().map_or(0, |s| s.len() as u64)
+                }
+                MockArgData::Bytes(ref v) => v.as_ref().map_or(0, |b| b.len() as u64),
+                MockArgData::Real(_) | MockArgData::Int(_) => NUMERIC_MAX_LEN,
+            })
+            .max()
+            .unwrap_or(0);
+
+        let cfg = server.cfg();
+        *cfg.max_len() = max_len;
+        *cfg.maybe_null() = self.columns.iter().any(|col| col.maybe_null);
+        *cfg.is_const() = self.columns.iter().all(|col| col.constant);
+        *cfg.decimals() = self.decimals;
+
+        let mut args = self.init_args();
+        self.checked::<U, _>("init", || server.init(&mut args))
+            .ok()?;
+        let cfg = server.cfg();
+        Some((*cfg.max_len(), *cfg.maybe_null()))
+    }
+
+    /// Run `f`, and fail if the wrapper caught a panic during it
+    fn checked<U, R>(&self, phase: &str, f: impl FnOnce() -> R) -> R {
+        let before = caught_panics();
+        let ret = f();
+        assert!(
+            caught_panics() == before,
+            "`{}` panicked in `{phase}` with {self:#?}",
+            type_name::<U>()
+        );
+        ret
+    }
+
+    fn check_output<U>(&self, out: &MockOutput, max_len: u64, maybe_null: bool) {
+        // An error always makes the result `NULL`, regardless of `maybe_null`
+        assert!(
+            maybe_null || out.error.is_some() || !out.is_sql_null(),
+            "`{}` returned NULL but did not set `maybe_null`, with {self:#?}",
+            type_name::<U>()
+        );
+        if let Some(bytes) = out.as_bytes() {
+            assert!(
+                bytes.len() as u64 <= max_len,
+                "`{}` returned {} bytes but `max_len` is {max_len}, with {self:#?}",
+                type_name::<U>(),
+                bytes.len()
+            );
+        }
+    }
+}
+
+/// Build a [`FuzzCase`] from fuzzer input and run it on a basic UDF, for use
+/// in a `cargo fuzz` target or a `proptest` test
+///
+/// Every input produces a case; `arbitrary` fills in defaults once the input
+/// runs out. With `proptest`, generate the input as bytes:
+///
+/// ```ignore
+/// proptest! {
+///     #[test]
+///     fn fuzz_my_udf(data: Vec<u8>) {
+///         udf::mock::fuzz_basic::<MyUdf>(&data);
+///     }
+/// }
+/// ```
+///
+/// For `cargo fuzz`, UDF types need to be reachable from outside the crate,
+/// e.g. by adding `"rlib"` to `crate-type` and making them public. Then
+/// `cargo fuzz init` and a target like the following is all that is needed:
+///
+/// ```toml
+/// # fuzz/Cargo.toml
+/// [dependencies]
+/// libfuzzer-sys = "0.4"
+/// my-udf = { path = ".." }
+/// udf = { version = "0.5", features = ["mock", "arbitrary"] }
+/// ```
+///
+/// ```ignore
+/// // fuzz/fuzz_targets/my_udf.rs
+/// #![no_main]
+///
+/// libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+///     udf::mock::fuzz_basic::<my_udf::MyUdf>(data);
+/// });
+/// ```
+///
+/// # Panics
+///
+/// Panics if the UDF fails the checks in [`FuzzCase::run_basic`].
+pub fn fuzz_basic<U: BasicSymbols>(data: &[u8]) {
+    if let Ok(case) = FuzzCase::arbitrary_take_rest(Unstructured::new(data)) {
+        case.run_basic::<U>();
+    }
+}
+
+/// Build a [`FuzzCase`] from fuzzer input and run it on an aggregate UDF
+///
+/// # Panics
+///
+/// Panics if the UDF fails the checks in [`FuzzCase::run_aggregate`].
+pub fn fuzz_aggregate<U: AggregateSymbols>(data: &[u8]) {
+    if let Ok(case) = FuzzCase::arbitrary_take_rest(Unstructured::new(data)) {
+        case.run_aggregate::<U>();
+    }
+}
+
+/// Run a basic UDF on `iterations` cases generated from a fixed seed, for use
+/// in ordinary unit tests
+///
+/// ```should_panic
+/// use udf::mock::*;
+/// use udf::prelude::*;
+///
+/// struct FirstArg;
+///
+/// #[register]
+/// impl BasicUdf for FirstArg {
+///     type Returns<'a> = i64;
+///
+///     fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+///         Ok(Self)
+///     }
+///
+///     fn process<'a>(
+///         &'a mut self,
+///         cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         error: Option<NonZeroU8>,
+///     ) -> Result<Self::Returns<'a>, ProcessError> {
+///         // Panics if there are no arguments or the first is not an integer
+///         Ok(args.get(0).unwrap().value().as_int().unwrap())
+///     }
+/// }
+///
+/// check_basic::<FirstArg>(100);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the UDF fails the checks in [`FuzzCase::run_basic`].
+pub fn check_basic<U: BasicSymbols>(iterations: usize) {
+    for data in SeededInputs::new().take(iterations) {
+        fuzz_basic::<U>(&data);
+    }
+}
+
+/// Run an aggregate UDF on `iterations` cases generated from a fixed seed
+///
+/// # Panics
+///
+/// Panics if the UDF fails the checks in [`FuzzCase::run_aggregate`].
+pub fn check_aggregate<U: AggregateSymbols>(iterations: usize) {
+    for data in SeededInputs::new().take(iterations) {
+        fuzz_aggregate::<U>(&data);
+    }
+}
+
+/// Pseudorandom fuzzer inputs from a xorshift generator, so results are
+/// repeatable without depending on a random number crate
+struct SeededInputs(u64);
+
+impl SeededInputs {
+    fn new() -> Self {
+        Self(0x9E37_79B9_7F4A_7C15)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+impl Iterator for SeededInputs {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = (self.next_u64() % 512) as usize;
+        Some((0..len).map(|_| self.next_u64().to_le_bytes()[0]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(format_decimal(12345, 2), "123.45");
+        assert_eq!(format_decimal(-5, 3), "-0.005");
+        assert_eq!(format_decimal(7, 0), "7");
+        assert_eq!(format_decimal(i64::MIN, 1), "-922337203685477580.8");
+    }
+
+    #[test]
+    fn test_case_shape() {
+        for data in SeededInputs::new().take(200) {
+            let case = FuzzCase::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+            let rows = case.arg_lists();
+            assert_eq!(rows.len(), case.rows);
+            let init_args = case.init_args();
+
+            for (i, col) in case.columns.iter().enumerate() {
+                assert_eq!(col.values.len(), case.rows);
+                if !col.constant {
+                    assert!(init_args.unbuilt_args[i].value.to_value().is_null());
+                }
+                let kinds: Vec<_> = col.values.iter().map(MockArgData::as_item_result).collect();
+                assert!(kinds.iter().all(|k| *k == kinds[0]));
+                if !col.maybe_null {
+                    assert!(col.values.iter().all(|v| !v.to_value().is_null()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Running an aggregate UDF over groups of rows
+
+use super::{MockArgList, MockOutput, MockServer, MockUdfCfg};
+use crate::wrapper::AggregateSymbols;
+
+/// Run a registered aggregate UDF over rows the way the server does for
+/// `GROUP BY`, or for a sliding window with `OVER (...)`
+///
+/// Each row is a grouping key and its arguments. Rows with equal keys form a
+/// group, and groups are returned in order of their first row. The server's
+/// calling sequence is used: `init` (with the first row's arguments), then
+/// `clear`, `add` for each row and `process` for every group, then `deinit`.
+/// Like [`MockServer`], this calls the functions generated by `#[register]`.
+///
+/// The `error` flag is carried across calls and groups as described in
+/// [`AggregateUdf`](crate::AggregateUdf), and is part of each [`MockOutput`].
+///
+/// ```
+/// use udf::mock::*;
+/// use udf::prelude::*;
+///
+/// #[derive(Default)]
+/// struct MySum(i64);
+///
+/// #[register]
+/// impl BasicUdf for MySum {
+///     type Returns<'a> = i64;
+///
+///     fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+///         Ok(Self::default())
+///     }
+///
+///     fn process<'a>(
+///         &'a mut self,
+///         cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         error: Option<NonZeroU8>,
+///     ) -> Result<Self::Returns<'a>, ProcessError> {
+///         Ok(self.0)
+///     }
+/// }
+///
+/// #[register]
+/// impl AggregateUdf for MySum {
+///     fn clear(&mut self, cfg: &UdfCfg<Process>, error: Option<NonZeroU8>) -> Result<(), NonZeroU8> {
+///         self.0 = 0;
+///         Ok(())
+///     }
+///
+///     fn add(
+///         &mut self,
+///         cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         error: Option<NonZeroU8>,
+///     ) -> Result<(), NonZeroU8> {
+///         self.0 += args.get(0).unwrap().value().as_int().unwrap_or(0);
+///         Ok(())
+///     }
+///
+///     fn remove(
+///         &mut self,
+///         cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         error: Option<NonZeroU8>,
+///     ) -> Result<(), NonZeroU8> {
+///         self.0 -= args.get(0).unwrap().value().as_int().unwrap_or(0);
+///         Ok(())
+///     }
+/// }
+///
+/// let rows = || {
+///     [("a", 1), ("b", 10), ("a", 2), ("a", 3)]
+///         .map(|(key, v)| (key, mock_args![(Int v, "v", false)]))
+/// };
+///
+/// // SELECT my_sum(v) FROM t GROUP BY key
+/// let res = AggregateRunner::<MySum>::new().run(rows()).unwrap();
+/// let sums: Vec<_> = res.iter().map(|(k, out)| (*k, out.as_int())).collect();
+/// assert_eq!(sums, [("a", Some(6)), ("b", Some(10))]);
+///
+/// // SELECT my_sum(v) OVER (PARTITION BY key ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)
+/// let res = AggregateRunner::<MySum>::new()
+///     .window(1, 0)
+///     .run(rows())
+///     .unwrap();
+/// let sums: Vec<_> = res.iter().map(|(k, out)| (*k, out.as_int())).collect();
+/// assert_eq!(sums, [("a", Some(1)), ("a", Some(3)), ("a", Some(5)), ("b", Some(10))]);
+/// ```
+#[derive(Debug)]
+pub struct AggregateRunner<U> {
+    server: MockServer<U>,
+    /// Rows before and after the current row, if running as a window function
+    window: Option<(usize, usize)>,
+}
+
+impl<U: AggregateSymbols> AggregateRunner<U> {
+    /// Create a runner that simulates `GROUP BY`
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            server: MockServer::new(),
+            window: None,
+        }
+    }
+
+    /// Simulate a window function with the frame `ROWS BETWEEN preceding
+    /// PRECEDING AND following FOLLOWING`, partitioned by the grouping key
+    ///
+    /// There is one result per row rather than per group. Rows are added with
+    /// `add` as they enter the frame, and removed with `remove` as they leave.
+    /// This requires the UDF to implement `remove`.
+    #[inline]
+    #[must_use]
+    pub fn window(mut self, preceding: usize, following: usize) -> Self {
+        self.window = Some((preceding, following));
+        self
+    }
+
+    /// The `UDF_INIT` that will be passed to `init`, which can be changed
+    /// before calling [`run`](Self::run)
+    #[inline]
+    pub fn cfg(&mut self) -> &mut MockUdfCfg {
+        self.server.cfg()
+    }
+
+    /// Run the UDF, returning each key with its result. `deinit` is called
+    /// before returning.
+    ///
+    /// If there are no rows, nothing is called and the result is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the message from `init` if it fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if rows have different numbers of arguments, or if running as a
+    /// window function and the UDF does not implement `remove`.
+    pub fn run<K: Clone + PartialEq>(
+        mut self,
+        rows: impl IntoIterator<Item = (K, MockArgList)>,
+    ) -> Result<Vec<(K, MockOutput)>, String> {
+        let mut groups: Vec<(K, Vec<MockArgList>)> = Vec::new();
+        for (key, args) in rows {
+            match groups.iter().position(|group| group.0 == key) {
+                Some(idx) => groups[idx].1.push(args),
+                None => groups.push((key, vec![args])),
+            }
+        }
+
+        let Some(first) = groups.first_mut() else {
+            return Ok(Vec::new());
+        };
+        self.server.init(&mut first.1[0])?;
+
+        let mut ret = Vec::new();
+        for group in &groups {
+            let key = &group.0;
+            match self.window {
+                Some((preceding, following)) => {
+                    for output in self.run_window(&group.1, preceding, following) {
+                        ret.push((key.clone(), output));
+                    }
+                }
+                None => ret.push((key.clone(), self.run_group(&group.1))),
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Aggregate a group to a single result. The last row is passed to
+    /// `process`, as it is the current row when the group ends.
+    fn run_group(&mut self, group: &[MockArgList]) -> MockOutput {
+        // Errors are visible in the output, so their results are not needed
+        let _ = self.server.clear();
+        for args in group {
+            let _ = self.server.add(args);
+        }
+        self.server.process(&group[group.len() - 1])
+    }
+
+    /// Produce a result for each row in a partition
+    fn run_window(
+        &mut self,
+        group: &[MockArgList],
+        preceding: usize,
+        following: usize,
+    ) -> Vec<MockOutput> {
+        let _ = self.server.clear();
+
+        // The rows currently in the frame are `start..end`
+        let (mut start, mut end) = (0, 0);
+        let mut ret = Vec::with_capacity(group.len());
+
+        for (current, args) in group.iter().enumerate() {
+            let new_start = current.saturating_sub(preceding);
+            let new_end = current
+                .saturating_add(following)
+                .saturating_add(1)
+                .min(group.len());
+
+            for row in &group[start..new_start] {
+                let _ = self.server.remove(row);
+            }
+            for row in &group[end.max(new_start)..new_end] {
+                let _ = self.server.add(row);
+            }
+            (start, end) = (new_start, new_end);
+
+            ret.push(self.server.process(args));
+        }
+
+        ret
+    }
+}
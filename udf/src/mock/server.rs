@@ -0,0 +1,362 @@
+//! Calling a UDF through the C functions that `#[register]` generates
+
+use std::ffi::{c_char, c_ulong};
+use std::marker::PhantomData;
+use std::num::NonZeroU8;
+use std::{slice, str};
+
+use udf_sys::server::CallFlags;
+use udf_sys::Item_result;
+
+use super::{MockArgList, MockUdfCfg};
+use crate::wrapper::{AddFn, AggregateSymbols, BasicSymbols, DeinitFn, ProcessFn};
+use crate::{MYSQL_ERRMSG_SIZE, MYSQL_RESULT_BUFFER_SIZE};
+
+/// Drive a registered UDF through its exported `extern "C"` functions, the way
+/// the server does
+///
+/// Calling `MyUdf::init` and `process` directly skips the code that
+/// `#[register]` generates. `MockServer` instead calls `my_udf_init`, `my_udf`,
+/// `my_udf_deinit` (and `my_udf_clear`, `_add` and `_remove` for aggregates)
+/// with a mocked `UDF_INIT` and `UDF_ARGS`. This covers argument coercions,
+/// result conversion and buffer handling, and cleanup in `deinit`.
+///
+/// Coercions set in `init` are applied to the argument values of later calls,
+/// as the server would: strings are parsed from their leading number, reals
+/// and decimals are rounded to integers, and numbers are formatted as strings.
+///
+/// `deinit` is called when the `MockServer` is dropped, if `init` succeeded.
+///
+/// ```
+/// use udf::mock::*;
+/// use udf::prelude::*;
+///
+/// struct AddOne;
+///
+/// #[register]
+/// impl BasicUdf for AddOne {
+///     type Returns<'a> = i64;
+///
+///     fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+///         args.get(0).ok_or("one argument required")?.set_type_coercion(SqlType::Int);
+///         Ok(Self)
+///     }
+///
+///     fn process<'a>(
+///         &'a mut self,
+///         cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         error: Option<NonZeroU8>,
+///     ) -> Result<Self::Returns<'a>, ProcessError> {
+///         Ok(args.get(0).unwrap().value().as_int().unwrap_or(0) + 1)
+///     }
+/// }
+///
+/// let mut server = MockServer::<AddOne>::new();
+/// assert!(server.init(&mut mock_args![]).is_err());
+///
+/// let mut server = MockServer::<AddOne>::new();
+/// let mut args = mock_args![("41abc", "x", false)];
+/// server.init(&mut args).unwrap();
+///
+/// // The string is coerced to an integer before `process` is called
+/// let out = server.process(&args);
+/// assert_eq!(out.as_int(), Some(42));
+/// ```
+#[derive(Debug)]
+pub struct MockServer<U> {
+    cfg: MockUdfCfg,
+    /// Argument types after `init`, which may include coercions
+    coercions: Vec<Item_result>,
+    /// Set once `init` succeeds
+    deinit: Option<DeinitFn>,
+    /// Flags passed to the UDF
+    flags: CallFlags,
+    _marker: PhantomData<U>,
+}
+
+/// The result of calling `process` through [`MockServer`]
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct MockOutput {
+    /// The returned value, decoded according to the function's signature
+    pub value: MockValue,
+    /// Whether the function set `is_null`
+    pub is_null: bool,
+    /// The value of the `error` flag after the call, if set
+    pub error: Option<NonZeroU8>,
+}
+
+/// A value returned by a C `process` function
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum MockValue {
+    /// Returned by functions with an integer signature
+    Int(i64),
+    /// Returned by functions with a real signature
+    Real(f64),
+    /// Returned by functions with a string or decimal signature. `None` if the
+    /// returned pointer was null.
+    Bytes(Option<Vec<u8>>),
+}
+
+impl MockOutput {
+    /// True if the server would see `NULL`: `is_null` or `error` is set, or a
+    /// string function returned a null pointer
+    pub fn is_sql_null(&self) -> bool {
+        self.is_null || self.error.is_some() || self.value == MockValue::Bytes(None)
+    }
+
+    /// The integer result, unless it is `NULL`
+    pub fn as_int(&self) -> Option<i64> {
+        match self.value {
+            MockValue::Int(v) if !self.is_sql_null() => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The real result, unless it is `NULL`
+    pub fn as_real(&self) -> Option<f64> {
+        match self.value {
+            MockValue::Real(v) if !self.is_sql_null() => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The string or decimal result, unless it is `NULL`
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.value {
+            MockValue::Bytes(Some(ref v)) if !self.is_sql_null() => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The string or decimal result as UTF-8, unless it is `NULL` or invalid
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes().and_then(|b| str::from_utf8(b).ok())
+    }
+}
+
+impl<U: BasicSymbols> MockServer<U> {
+    /// Create a server for a type registered with `#[register]`
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            cfg: MockUdfCfg::new(),
+            coercions: Vec::new(),
+            deinit: None,
+            flags: CallFlags::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// The `UDF_INIT` shared by all calls. This can be changed before `init`
+    /// to set what the server would provide, or inspected afterwards.
+    #[inline]
+    pub fn cfg(&mut self) -> &mut MockUdfCfg {
+        &mut self.cfg
+    }
+
+    /// Call `xxx_init`
+    ///
+    /// # Errors
+    ///
+    /// Returns the message written by `init` if it fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `init` was already successful.
+    pub fn init(&mut self, args: &mut MockArgList) -> Result<(), String> {
+        assert!(self.deinit.is_none(), "`init` was already called");
+
+        let mut message = [0u8; MYSQL_ERRMSG_SIZE];
+        let failed = unsafe {
+            U::INIT(
+                self.cfg.as_raw_ptr(),
+                args.as_raw_ptr(),
+                message.as_mut_ptr().cast(),
+            )
+        };
+        self.sync_cfg();
+
+        if failed {
+            let len = message
+                .iter()
+                .position(|&b| b == 0)
+                .unwrap_or(message.len());
+            return Err(String::from_utf8_lossy(&message[..len]).into_owned());
+        }
+
+        self.coercions = args.built_types().to_vec();
+        self.deinit = Some(U::DEINIT);
+        Ok(())
+    }
+
+    /// Call `xxx` with `args`, after applying any coercions from `init`
+    ///
+    /// As in the server, once a basic UDF sets `error`, later calls return
+    /// `NULL` without calling the function. Aggregate UDFs receive the current
+    /// `error` instead, as described in [`AggregateUdf`](crate::AggregateUdf).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `init` has not succeeded, or if the number of arguments is
+    /// different from that given to `init`. With the `strict-contracts`
+    /// feature, also panics if the result breaks the `maybe_null`, `max_len`
+    /// or `decimals` settings.
+    pub fn process(&mut self, args: &MockArgList) -> MockOutput {
+        let mut args = self.coerce_args(args);
+
+        if !self.flags.start_process() {
+            return MockOutput {
+                value: null_value(U::PROCESS),
+                is_null: true,
+                error: self.flags.error(),
+            };
+        }
+
+        #[cfg(feature = "strict-contracts")]
+        crate::wrapper::contracts::take_violation();
+
+        let initid = self.cfg.as_raw_ptr();
+        let args_ptr = args.as_raw_ptr();
+        let (is_null, error) = self.flags.as_mut_ptrs();
+
+        let value = match U::PROCESS {
+            ProcessFn::Int(f) => MockValue::Int(unsafe { f(initid, args_ptr, is_null, error) }),
+            ProcessFn::Real(f) => MockValue::Real(unsafe { f(initid, args_ptr, is_null, error) }),
+            ProcessFn::Buf(f) => {
+                let mut result = [0u8; MYSQL_RESULT_BUFFER_SIZE];
+                let mut length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+                let res_ptr: *mut c_char = result.as_mut_ptr().cast();
+                let ret = unsafe { f(initid, args_ptr, res_ptr, &mut length, is_null, error) };
+
+                // The result may point into our buffer or to memory owned by
+                // the UDF, which stays valid until the next call
+                let bytes = (!ret.is_null())
+                    .then(|| unsafe { slice::from_raw_parts(ret.cast::<u8>(), length as usize) });
+                MockValue::Bytes(bytes.map(<[u8]>::to_vec))
+            }
+        };
+        self.sync_cfg();
+
+        #[cfg(feature = "strict-contracts")]
+        if let Some(msg) = crate::wrapper::contracts::take_violation() {
+            panic!("`process` broke its contract: {msg}");
+        }
+
+        MockOutput {
+            value,
+            is_null: self.flags.is_null(),
+            error: self.flags.error(),
+        }
+    }
+
+    /// Call `xxx_deinit` if `init` succeeded. This also happens on drop.
+    #[inline]
+    pub fn deinit(self) {}
+
+    /// Copy the arguments with coercions from `init` applied
+    fn coerce_args(&self, args: &MockArgList) -> MockArgList {
+        assert!(
+            self.deinit.is_some(),
+            "`init` must succeed before other calls"
+        );
+        assert_eq!(
+            args.len(),
+            self.coercions.len(),
+            "argument count must be the same as for `init`"
+        );
+        args.coerced(&self.coercions)
+    }
+
+    /// Our `MockUdfCfg` caches `max_len`, so refresh it after the UDF may have
+    /// changed it
+    fn sync_cfg(&mut self) {
+        self.cfg.max_len();
+    }
+}
+
+impl<U: AggregateSymbols> MockServer<U> {
+    /// Call `xxx_clear` to start a new group. `is_null` is reset first, but
+    /// `error` is kept from the previous group; the UDF may clear it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value of `error` if it is set after the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `init` has not succeeded.
+    pub fn clear(&mut self) -> Result<(), NonZeroU8> {
+        assert!(
+            self.deinit.is_some(),
+            "`init` must succeed before other calls"
+        );
+        self.flags.start_clear();
+
+        let (is_null, error) = self.flags.as_mut_ptrs();
+        unsafe { U::CLEAR(self.cfg.as_raw_ptr(), is_null, error) };
+        self.sync_cfg();
+        self.flags.error_result()
+    }
+
+    /// Call `xxx_add` with a row of arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns the value of `error` if it is set after the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `init` has not succeeded, or if the number of arguments is
+    /// different from that given to `init`.
+    pub fn add(&mut self, args: &MockArgList) -> Result<(), NonZeroU8> {
+        let f = U::ADD;
+        self.call_add_fn(f, args)
+    }
+
+    /// Call `xxx_remove` with a row of arguments, as the server does for window
+    /// functions
+    ///
+    /// # Errors
+    ///
+    /// Returns the value of `error` if it is set after the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the UDF does not implement `remove`, in addition to the
+    /// conditions for [`add`](Self::add).
+    pub fn remove(&mut self, args: &MockArgList) -> Result<(), NonZeroU8> {
+        let f = U::REMOVE.expect("this UDF does not implement `remove`");
+        self.call_add_fn(f, args)
+    }
+
+    fn call_add_fn(&mut self, f: AddFn, args: &MockArgList) -> Result<(), NonZeroU8> {
+        let mut args = self.coerce_args(args);
+        self.flags.start_add();
+
+        let (is_null, error) = self.flags.as_mut_ptrs();
+        unsafe { f(self.cfg.as_raw_ptr(), args.as_raw_ptr(), is_null, error) };
+        self.sync_cfg();
+        self.flags.error_result()
+    }
+}
+
+impl<U> Drop for MockServer<U> {
+    fn drop(&mut self) {
+        if let Some(deinit) = self.deinit.take() {
+            // SAFETY: `init` succeeded, so `deinit` must be called exactly once
+            unsafe { deinit(self.cfg.as_raw_ptr()) };
+        }
+    }
+}
+
+/// The value for a result that was not computed
+const fn null_value(process: ProcessFn) -> MockValue {
+    match process {
+        ProcessFn::Int(_) => MockValue::Int(0),
+        ProcessFn::Real(_) => MockValue::Real(0.0),
+        ProcessFn::Buf(_) => MockValue::Bytes(None),
+    }
+}
@@ -6,7 +6,7 @@
 use core::fmt::Debug;
 use std::num::NonZeroU8;
 
-use crate::types::{ArgList, UdfCfg};
+use crate::types::{ArgList, ToSqlReturn, UdfCfg};
 use crate::ProcessError;
 
 /// This trait specifies the functions needed for a standard (non-aggregate) UDF
@@ -34,7 +34,9 @@ pub trait BasicUdf: Sized {
     /// details.
     ///
     /// - `f64` (real), `i64` (integer), and `[u8]` (string/blob) are the three
-    ///   fundamental types
+    ///   fundamental types. Anything implementing [`ToSqlReturn`] can be used,
+    ///   including smaller integers, `bool`, `f32`, `Box<str>`, `Cow<str>`,
+    ///   and your own types
     /// - Any `Return` can be an `Option<something>` if the result is
     ///   potentially nullable
     /// - There is no meaningful difference between `String`, `Vec<u8>`, `str`,
@@ -51,6 +53,8 @@ pub trait BasicUdf: Sized {
     ///   or `&'a [u8]` that references them. This is useful for something like
     ///   a `concat` function that updates its result string with each call
     ///   (GATs allow this to work).
+    /// - For exact `decimal` results, [`Decimal<P, S>`](crate::Decimal) sets
+    ///   the result's precision and scale automatically
     ///
     /// Choosing a type may seem tricky at first but anything that successfully
     /// compiles will likely work. The flow chart below helps clarify some of
@@ -66,6 +70,9 @@ pub trait BasicUdf: Sized {
     /// ╭─────────────╮
     /// │    float    ├─> f64 / Option<f64>
     /// ╰─────────────╯
+    /// ╭─────────────╮
+    /// │   decimal   ├─> Decimal<P, S> / Option<Decimal<P, S>>
+    /// ╰─────────────╯
     ///                   ╭───────────╮
     /// ╭─────────────╮   │  static   ├─> &'static str / Option<&'static str>
     /// │ utf8 string ├─> │           │
@@ -83,7 +90,7 @@ pub trait BasicUdf: Sized {
     ///                                   │ self-updating ├─> &'a [u8] / Option<&'a [u8]>
     ///                                   ╰───────────────╯
     /// ```
-    type Returns<'a>
+    type Returns<'a>: ToSqlReturn<'a>
     where
         Self: 'a;
 
@@ -143,7 +150,13 @@ pub trait BasicUdf: Sized {
     /// this point other than that, so try to catch all possible errors in
     /// [`BasicUdf::init`].
     ///
-    /// [`ProcessError`] is just an empty type.
+    /// The error's message and source (if any) are logged along with the
+    /// UDF's name, so use [`ProcessError::msg`] or `?` on another error to
+    /// explain what went wrong.
+    ///
+    /// If this function panics, the panic is caught and logged, and the result
+    /// is the same as returning a [`ProcessError`]. Don't rely on this for
+    /// error handling; it exists so one bad row does not crash the server.
     fn process<'a>(
         &'a mut self,
         cfg: &UdfCfg<Process>,
@@ -176,8 +189,11 @@ pub trait BasicUdf: Sized {
 /// - These errors do not stop the remaining `add()`/`remove()` functions from
 ///   being called, but these functions do receive the error (and so may choose
 ///   to do nothing if there is an error set)
-/// - Errors are not reset on `clear()`; you must do this manually (Hence
-///   `error` being mutable in this function signature)
+/// - The server does not reset errors before `clear()`. Returning `Ok(())`
+///   from `clear()` resets it, and returning an error keeps it set for the next
+///   group
+/// - Returning `Ok(())` from `add()` or `remove()` leaves the error as it is,
+///   so an error from one row lasts until the next `clear()`
 ///
 /// In order to enforce some of these constraints, we use `NonZeroU8` to
 /// represent error types (which has the nice side effect of being optimizable).
@@ -209,12 +225,11 @@ pub trait AggregateUdf: BasicUdf {
     /// Hit a problem? Return an integer, which may or may not be meaningful to
     /// you. This can be done with `return Err(NonZeroU8::new(1).unwrap());`.
     ///
-    /// The `error` argument tells you if there has been an error at some point,
-    /// and the return value also detemines whether to propegate/modify the
-    /// error (probably what you want) or clear it (I can't think of any good
-    /// reason to do this in `add()`). If you would like to propegate the error
-    /// without action, just add the following as the first line of the
-    /// function:
+    /// The `error` argument tells you if there has been an error at some point
+    /// in this group. Returning an error sets or replaces it; returning
+    /// `Ok(())` leaves it unchanged, so it cannot be cleared here. If you would
+    /// like to skip rows once there is an error, just add the following as the
+    /// first line of the function:
     ///
     /// ```
     /// # use std::num::NonZeroU8;
@@ -223,8 +238,6 @@ pub trait AggregateUdf: BasicUdf {
     /// # Ok(())
     /// # }
     /// ```
-    ///
-    /// If you do this,
     fn add(
         &mut self,
         cfg: &UdfCfg<Process>,
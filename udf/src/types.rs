@@ -1,11 +1,19 @@
 //! Types that represent SQL interfaces
 
-use std::fmt;
-
 mod arg;
 mod arg_list;
 mod config;
+mod datetime;
+mod decimal;
+#[cfg(feature = "json")]
+mod json;
+mod prepared;
+mod process_error;
+mod result_buf;
+mod sql_decimal;
+mod sql_return;
 mod sql_types;
+mod typed_args;
 
 // Document everything inline
 #[doc(inline)]
@@ -15,26 +23,29 @@ pub use arg_list::*;
 #[doc(inline)]
 pub use config::*;
 #[doc(inline)]
+pub use datetime::*;
+#[doc(inline)]
+pub use decimal::*;
+#[cfg(feature = "json")]
+#[doc(inline)]
+pub use json::*;
+#[doc(inline)]
+pub use prepared::*;
+#[doc(inline)]
+pub use process_error::*;
+#[doc(inline)]
+pub use result_buf::*;
+#[doc(inline)]
+pub use sql_decimal::*;
+#[doc(inline)]
+pub use sql_return::*;
+#[doc(inline)]
 pub use sql_types::*;
+#[doc(inline)]
+pub use typed_args::*;
 
 /// Max error message size, 0x200 = 512 bytes
 pub const MYSQL_ERRMSG_SIZE: usize = 0x200;
 
 /// Minimum size of a buffer for string results
 pub const MYSQL_RESULT_BUFFER_SIZE: usize = 255;
-
-/// A zero-sized struct indicating that something went wrong
-///
-/// If you return an instance of this, it is likely a good idea to log to stderr
-/// what went wrong.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
-pub struct ProcessError;
-
-impl fmt::Display for ProcessError {
-    #[inline]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "udf processing error")
-    }
-}
-
-impl std::error::Error for ProcessError {}
@@ -1,15 +1,19 @@
 //! Rust representation of SQL arguments
 
 use core::fmt::Debug;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::ffi::{c_char, c_int, c_ulong};
 use std::marker::PhantomData;
 use std::{slice, str};
 
 use coerce::{get_coercion, get_current_type, get_desired_or_current, set_coercion};
 use udf_sys::Item_result;
 
+use crate::charset;
 use crate::types::{SqlResult, SqlType};
 use crate::wrapper::UDF_ARGSx;
-use crate::{ArgList, Init, UdfState};
+use crate::{ArgList, Init, Process, UdfState};
 
 /// A single SQL argument, including its attributes
 ///
@@ -21,25 +25,53 @@ use crate::{ArgList, Init, UdfState};
 pub struct SqlArg<'a, S: UdfState> {
     pub(super) base: &'a ArgList<'a, S>,
     pub(super) index: usize,
+    /// Whether the value is valid UTF-8, once checked by `as_str` or
+    /// `as_str_lossy`
+    pub(super) utf8: Cell<Option<bool>>,
     pub(super) marker: PhantomData<S>,
 }
 
+/// Read the value of argument `index`
+///
+/// # Safety
+///
+/// `base` must be valid and `index` must be less than its `arg_count`. The
+/// result must not outlive the argument data.
+#[inline]
+unsafe fn value_at<'a>(base: &UDF_ARGSx, index: usize) -> SqlResult<'a> {
+    value_from_parts(
+        *base.args.add(index),
+        *base.arg_types.add(index),
+        *base.lengths.add(index),
+    )
+}
+
+/// Read a value from its entries in `args`, `arg_types` and `lengths`
+///
+/// # Safety
+///
+/// The entries must come from a valid `UDF_ARGS`, and the result must not
+/// outlive the argument data.
+#[inline]
+pub(super) unsafe fn value_from_parts<'a>(
+    ptr: *const c_char,
+    arg_type: c_int,
+    len: c_ulong,
+) -> SqlResult<'a> {
+    // Ignore any coercion that has been set but not yet applied
+    let arg_type = get_current_type(arg_type);
+
+    // We can unwrap because the tag will be valid
+    SqlResult::from_ptr(ptr.cast(), arg_type.try_into().unwrap(), len as usize).unwrap()
+}
+
 impl<'a, T: UdfState> SqlArg<'a, T> {
     /// The actual argument type and value
     #[inline]
     #[allow(clippy::missing_panics_doc)]
     pub fn value(&self) -> SqlResult<'a> {
         // SAFETY: Initializing API guarantees the inner struct to be valid
-        unsafe {
-            let base = self.get_base();
-            let arg_buf_ptr: *const u8 = (*base.args.add(self.index)).cast();
-            let arg_type = *base.arg_types.add(self.index);
-            let arg_len = *base.lengths.add(self.index);
-
-            // We can unwrap because the tag will be valid
-            SqlResult::from_ptr(arg_buf_ptr, arg_type.try_into().unwrap(), arg_len as usize)
-                .unwrap()
-        }
+        unsafe { value_at(self.get_base(), self.index) }
     }
 
     /// A string representation of this argument's identifier
@@ -59,6 +91,24 @@ impl<'a, T: UdfState> SqlArg<'a, T> {
             .unwrap()
     }
 
+    /// The name of this argument's charset, such as `utf8mb4`, `latin1` or
+    /// `binary`
+    ///
+    /// This is only available on `MySQL` 8.0.19 and later; `None` is returned on
+    /// `MariaDB`, on older versions, and in tests. See [`crate::charset`].
+    #[inline]
+    pub fn charset(&self) -> Option<&'a str> {
+        charset::argument_get(self.base.0.get().cast(), charset::CHARSET, self.index)
+    }
+
+    /// The name of this argument's collation, such as `utf8mb4_0900_ai_ci`
+    ///
+    /// This has the same availability as [`SqlArg::charset`].
+    #[inline]
+    pub fn collation(&self) -> Option<&'a str> {
+        charset::argument_get(self.base.0.get().cast(), charset::COLLATION, self.index)
+    }
+
     /// Simple helper method to get the internal base
     unsafe fn get_base(&'a self) -> &'a UDF_ARGSx {
         &(*self.base.0.get())
@@ -71,7 +121,7 @@ impl<'a, T: UdfState> SqlArg<'a, T> {
 }
 
 /// This includes functions that are only applicable during initialization
-impl<'a> SqlArg<'a, Init> {
+impl SqlArg<'_, Init> {
     /// Determine whether an argument **may** be constant
     ///
     /// During initialization, a value is const if it is not `None`. This
@@ -89,6 +139,17 @@ impl<'a> SqlArg<'a, Init> {
         }
     }
 
+    /// The longest value this argument can have, as reported by the server
+    ///
+    /// For strings this is the maximum length in bytes (e.g. 255 for a
+    /// `VARCHAR(255)` column with a single byte charset), and for numbers it
+    /// is the maximum length of their string form.
+    #[inline]
+    #[allow(clippy::unnecessary_cast)] // `c_ulong` is 32 bits on Windows
+    pub fn max_len(&self) -> u64 {
+        unsafe { *self.get_base().lengths.add(self.index) as u64 }
+    }
+
     /// Whether or not this argument may be `NULL`
     #[inline]
     pub fn maybe_null(&self) -> bool {
@@ -125,6 +186,28 @@ impl<'a> SqlArg<'a, Init> {
         }
     }
 
+    /// Ask the server to convert this argument to a charset, such as
+    /// `utf8mb4`, before each call to `process`
+    ///
+    /// Setting `utf8mb4` means string arguments can be used as `&str` no matter
+    /// the charset of the column or connection. It has no effect on numeric
+    /// arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server does not support charset information
+    /// (see [`crate::charset`]) or does not accept the charset. This is usually
+    /// ignored for servers that do not support it.
+    #[inline]
+    pub fn set_charset(&mut self, charset: &str) -> Result<(), String> {
+        charset::argument_set(
+            self.base.0.get().cast(),
+            charset::CHARSET,
+            self.index,
+            charset,
+        )
+    }
+
     /// Assign the currently desired coercion
     #[inline]
     pub(crate) fn flush_coercion(&mut self) {
@@ -137,6 +220,121 @@ impl<'a> SqlArg<'a, Init> {
     }
 }
 
+/// Accessors that read the argument directly, without building a [`SqlResult`]
+///
+/// [`SqlArg::value`] is the most convenient way to work with arguments, but it
+/// matches on the type each time it is called and [`SqlResult::as_string`]
+/// validates UTF-8 each time. These methods are cheaper for hot paths, such as
+/// functions over large binary columns.
+impl<'a> SqlArg<'a, Process> {
+    /// Whether this argument is `NULL`
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        // SAFETY: Initializing API guarantees the inner struct to be valid
+        unsafe { (*self.get_base().args.add(self.index)).is_null() }
+    }
+
+    /// The type of this argument, after any coercion set in `init`
+    #[inline]
+    #[allow(clippy::missing_panics_doc)] // We will have a valid type
+    pub fn sql_type(&self) -> SqlType {
+        // SAFETY: Initializing API guarantees the inner struct to be valid
+        let arg_type = unsafe { get_current_type(*self.arg_type_ptr()) };
+        SqlType::try_from(arg_type as i8).expect("critical: invalid sql type")
+    }
+
+    /// The length in bytes of a string or decimal value, or 0 if this is
+    /// `NULL` or a number
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.as_bytes().map_or(0, <[u8]>::len)
+    }
+
+    /// Whether [`SqlArg::len`] is 0
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The bytes of a string or decimal value, or `None` if this is `NULL` or
+    /// a number
+    #[inline]
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self.sql_type() {
+            SqlType::String | SqlType::Decimal if !self.is_null() => {
+                // SAFETY: we just checked the type and that the value is not null
+                Some(unsafe { self.as_bytes_unchecked() })
+            }
+            _ => None,
+        }
+    }
+
+    /// The bytes of the value, without checking its type
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a non-`NULL` string or decimal, e.g. because it was
+    /// coerced to a string in `init` and [`SqlArg::is_null`] is false.
+    #[inline]
+    pub unsafe fn as_bytes_unchecked(&self) -> &'a [u8] {
+        let base = self.get_base();
+        let ptr: *const u8 = (*base.args.add(self.index)).cast();
+        let len = *base.lengths.add(self.index) as usize;
+        slice::from_raw_parts(ptr, len)
+    }
+
+    /// The value as a string, if it is a string or decimal and valid UTF-8
+    ///
+    /// The result of UTF-8 validation is kept in this `SqlArg`, so calling
+    /// this or [`SqlArg::as_str_lossy`] again does not check the data again.
+    /// [`ArgList::get`] and [`ArgList::iter`] return a new `SqlArg` each time,
+    /// so keep the one for the current row to reuse the check.
+    #[inline]
+    pub fn as_str(&self) -> Option<&'a str> {
+        let bytes = self.as_bytes()?;
+        match self.utf8.get() {
+            // SAFETY: these bytes were validated by an earlier call
+            Some(true) => Some(unsafe { str::from_utf8_unchecked(bytes) }),
+            Some(false) => None,
+            None => {
+                let res = str::from_utf8(bytes).ok();
+                self.utf8.set(Some(res.is_some()));
+                res
+            }
+        }
+    }
+
+    /// The value as a string, replacing invalid UTF-8 with `U+FFFD`. This only
+    /// allocates if the data is not valid UTF-8.
+    ///
+    /// Returns `None` if this is `NULL` or a number.
+    #[inline]
+    pub fn as_str_lossy(&self) -> Option<Cow<'a, str>> {
+        self.as_str().map_or_else(
+            || self.as_bytes().map(String::from_utf8_lossy),
+            |s| Some(Cow::Borrowed(s)),
+        )
+    }
+
+    /// The value as a string, decoded from its charset
+    ///
+    /// This uses [`SqlArg::charset`] and [`charset::decode`], replacing
+    /// malformed sequences with `U+FFFD`. If the charset is not known or
+    /// supported, the value is assumed to be UTF-8 as with
+    /// [`SqlArg::as_str_lossy`]. This only allocates if the value needs
+    /// converting.
+    ///
+    /// Returns `None` if this is `NULL` or a number.
+    #[cfg(feature = "encoding_rs")]
+    #[inline]
+    pub fn decode(&self) -> Option<Cow<'a, str>> {
+        let bytes = self.as_bytes()?;
+        self.charset()
+            .and_then(|cs| charset::decode(bytes, cs))
+            .or_else(|| self.as_str_lossy())
+    }
+}
+
 mod coerce {
     //! Represent a current type and a future type within a single `.arg_type` value
     //!
@@ -223,6 +421,7 @@ mod tests {
     use std::mem;
 
     use super::*;
+    use crate::mock_args;
 
     // Ensure our fake transmutes are sound
     #[test]
@@ -230,4 +429,56 @@ mod tests {
         assert_eq!(mem::size_of::<Item_result>(), mem::size_of::<i32>());
         assert_eq!(mem::align_of::<Item_result>(), mem::align_of::<i32>());
     }
+
+    #[test]
+    fn test_process_accessors() {
+        let mut args = mock_args![
+            ("abc", "a", false),
+            (&b"a\xffb"[..], "b", false),
+            (String None, "c", true),
+            (Int 5, "d", false),
+            (Decimal "1.50", "e", false),
+        ];
+        let args = args.as_process();
+        let arg = |i| args.get(i).unwrap();
+
+        assert_eq!(arg(0).len(), 3);
+        let valid = arg(0);
+        assert_eq!(valid.as_str(), Some("abc"));
+        assert_eq!(valid.utf8.get(), Some(true));
+        assert!(matches!(valid.as_str_lossy(), Some(Cow::Borrowed("abc"))));
+        assert!(matches!(arg(0).as_str_lossy(), Some(Cow::Borrowed("abc"))));
+
+        let invalid = arg(1);
+        assert_eq!(invalid.as_bytes(), Some(&b"a\xffb"[..]));
+        assert_eq!(invalid.utf8.get(), None);
+        assert_eq!(invalid.as_str(), None);
+        assert_eq!(invalid.utf8.get(), Some(false));
+        assert!(matches!(invalid.as_str_lossy(), Some(Cow::Owned(s)) if s == "a\u{fffd}b"));
+        assert_eq!(unsafe { invalid.as_bytes_unchecked() }, b"a\xffb");
+
+        assert!(arg(2).is_null());
+        assert!(arg(2).is_empty());
+        assert_eq!(arg(2).as_str_lossy(), None);
+
+        assert!(!arg(3).is_null());
+        assert_eq!(arg(3).sql_type(), SqlType::Int);
+        assert_eq!((arg(3).len(), arg(3).as_bytes()), (0, None));
+
+        assert_eq!(arg(4).as_str(), Some("1.50"));
+        assert_eq!(arg(4).sql_type(), SqlType::Decimal);
+    }
+
+    #[test]
+    fn test_values() {
+        let mut args = mock_args![(String None, "a", true), (Real 1.5, "b", false)];
+        let args = args.as_process();
+        assert_eq!(args.values().len(), 2);
+
+        let expected: Vec<_> = args.iter().map(|a| a.value()).collect();
+        assert_eq!(args.values().collect::<Vec<_>>(), expected);
+
+        let mut empty = mock_args![];
+        assert_eq!(empty.as_process().values().next(), None);
+    }
 }
@@ -2,15 +2,18 @@
 
 #![allow(dead_code)]
 
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
+use std::ffi::{c_char, c_int, c_ulong};
 use std::fmt;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::slice;
 
 use udf_sys::UDF_ARGS;
 
+use super::arg::value_from_parts;
 use crate::wrapper::UDF_ARGSx;
-use crate::{Init, SqlArg, UdfState};
+use crate::{Init, Process, SqlArg, SqlResult, UdfState};
 
 /// A collection of SQL arguments
 ///
@@ -27,7 +30,7 @@ pub struct ArgList<'a, S: UdfState>(
 
 /// Derived formatting is a bit ugly, so we clean it up by using the `Vec`
 /// format.
-impl<'a, S: UdfState> Debug for ArgList<'a, S> {
+impl<S: UdfState> Debug for ArgList<'_, S> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ArgList")
@@ -80,12 +83,35 @@ impl<'a, S: UdfState> ArgList<'a, S> {
         Some(SqlArg {
             base: self,
             index,
+            utf8: Cell::new(None),
             marker: PhantomData,
         })
     }
 }
 
-impl<'a> ArgList<'a, Init> {
+impl ArgList<'_, Init> {
+    /// The sum of [`SqlArg::max_len`] for all arguments, saturating at
+    /// `u64::MAX`
+    ///
+    /// This bounds the result of functions like concatenation, and can be
+    /// scaled for other functions that produce output proportional to their
+    /// input:
+    ///
+    /// ```
+    /// use udf::prelude::*;
+    ///
+    /// fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) {
+    ///     // Base64 output is at most 4 bytes for every 3 of input
+    ///     let bound = args.total_max_len().saturating_add(2) / 3 * 4;
+    ///     cfg.set_max_len(bound);
+    /// }
+    /// ```
+    #[inline]
+    pub fn total_max_len(&self) -> u64 {
+        self.iter()
+            .fold(0u64, |acc, arg| acc.saturating_add(arg.max_len()))
+    }
+
     /// Apply the pending coercion for all arguments. Meant to be run just
     /// before exiting the `init` function within proc macro calls.
     #[inline]
@@ -95,6 +121,51 @@ impl<'a> ArgList<'a, Init> {
     }
 }
 
+impl<'a> ArgList<'a, Process> {
+    /// Iterate over the values of all arguments
+    ///
+    /// This gives the same values as `args.iter().map(|arg| arg.value())`, but
+    /// finds the argument buffers once rather than for each [`SqlArg`].
+    ///
+    /// ```
+    /// use udf::mock::*;
+    /// use udf::prelude::*;
+    ///
+    /// let mut args = mock_args![(Int 1, "a", false), (String "x", "b", false)];
+    /// let values: Vec<_> = args.as_process().values().collect();
+    /// assert_eq!(values, [SqlResult::Int(Some(1)), SqlResult::String(Some(b"x"))]);
+    /// ```
+    #[inline]
+    pub fn values(&'a self) -> Values<'a> {
+        let base = unsafe { &*self.0.get() };
+        let len = self.len();
+        // SAFETY: the server provides `arg_count` entries in each buffer, and
+        // they are not changed during `process`
+        unsafe {
+            Values {
+                args: raw_slice(base.args, len),
+                types: raw_slice(base.arg_types, len),
+                lengths: raw_slice(base.lengths, len),
+                n: 0,
+            }
+        }
+    }
+}
+
+/// `slice::from_raw_parts` that allows a null pointer when `len` is 0
+///
+/// # Safety
+///
+/// If `len` is not 0, `ptr` must point to `len` valid items that live for
+/// `'a`.
+unsafe fn raw_slice<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr, len)
+    }
+}
+
 /// Trait for being able to iterate arguments
 impl<'a, S: UdfState> IntoIterator for &'a ArgList<'a, S> {
     type Item = SqlArg<'a, S>;
@@ -151,6 +222,39 @@ impl<'a, S: UdfState> Iterator for Iter<'a, S> {
     }
 }
 
+/// Iterator over argument values in an [`ArgList`]
+///
+/// This struct is produced by [`ArgList::values`]
+#[derive(Debug)]
+pub struct Values<'a> {
+    args: &'a [*const c_char],
+    types: &'a [c_int],
+    lengths: &'a [c_ulong],
+    n: usize,
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = SqlResult<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.n;
+        let &ty = self.types.get(i)?;
+        self.n += 1;
+
+        // SAFETY: the entries come from the list, and values live as long as it
+        Some(unsafe { value_from_parts(self.args[i], ty, self.lengths[i]) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.types.len() - self.n;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Values<'_> {}
+
 #[cfg(test)]
 mod tests {
     use std::mem::{align_of, size_of};
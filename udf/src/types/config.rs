@@ -6,13 +6,13 @@ use std::cell::UnsafeCell;
 use std::ffi::c_ulong;
 use std::fmt::Debug;
 use std::marker::PhantomData;
-#[cfg(feature = "logging-debug")]
 use std::{any::type_name, mem::size_of};
 
 use udf_sys::UDF_INIT;
 
-#[cfg(feature = "logging-debug")]
-use crate::udf_log;
+use crate::charset;
+use crate::log_filter::{self, Level};
+use crate::wrapper::RegisteredBasicUdf;
 use crate::{Init, UdfState};
 
 /// Helpful constants related to the `max_length` parameter
@@ -34,6 +34,34 @@ pub enum MaxLenOptions {
 
     /// A `mediumblob` can be up to 16 MiB.
     MediumBlob = 1 << 24,
+
+    /// A `longblob` can be up to 4 GiB.
+    LongBlob = u32::MAX,
+}
+
+impl MaxLenOptions {
+    /// The smallest `BLOB` size that can hold `len` bytes, saturating at
+    /// `LongBlob`
+    ///
+    /// This is useful for picking `max_len` from a bound on the result, such as
+    /// [`ArgList::total_max_len`](crate::ArgList::total_max_len):
+    ///
+    /// ```
+    /// use udf::MaxLenOptions;
+    ///
+    /// assert_eq!(MaxLenOptions::blob_for(1000), MaxLenOptions::Blob);
+    /// assert_eq!(MaxLenOptions::blob_for(1 << 20), MaxLenOptions::MediumBlob);
+    /// assert_eq!(MaxLenOptions::blob_for(u64::MAX), MaxLenOptions::LongBlob);
+    /// ```
+    pub fn blob_for(len: u64) -> Self {
+        if len <= Self::Blob as u64 {
+            Self::Blob
+        } else if len <= Self::MediumBlob as u64 {
+            Self::MediumBlob
+        } else {
+            Self::LongBlob
+        }
+    }
 }
 
 /// A collection of SQL arguments
@@ -59,16 +87,17 @@ impl<S: UdfState> UdfCfg<S> {
     ///
     /// This takes a boxed object, turns it into a pointer, and stores that
     /// pointer in this struct. After calling this function, [`retrieve_box`]
-    /// _must_ be called to free the memory!
-    pub(crate) fn store_box<T>(&self, b: Box<T>) {
+    /// _must_ be called to free the memory! `U` is the UDF, for logging.
+    pub(crate) fn store_box<U: RegisteredBasicUdf + ?Sized, T>(&self, b: Box<T>) {
         let box_ptr = Box::into_raw(b);
 
         // Note: if T is zero-sized, this will print `0x1` for the address
-        #[cfg(feature = "logging-debug")]
-        udf_log!(
-            Debug: "{box_ptr:p} {} bytes udf->server control transfer ({})",
-            size_of::<T>(),type_name::<T>()
-        );
+        if log_filter::enabled_for::<U>(Level::Debug) {
+            udf_log!(
+                Debug: "{box_ptr:p} {} bytes udf->server control transfer ({})",
+                size_of::<T>(),type_name::<T>()
+            );
+        }
 
         // SAFETY: unsafe when called from different threads, but we are `!Sync`
         // here
@@ -82,14 +111,15 @@ impl<S: UdfState> UdfCfg<S> {
     ///
     /// T _must_ be the type of this struct's pointer, likely created with
     /// [`store_box`]
-    pub(crate) unsafe fn retrieve_box<T>(&self) -> Box<T> {
+    pub(crate) unsafe fn retrieve_box<U: RegisteredBasicUdf + ?Sized, T>(&self) -> Box<T> {
         let box_ptr = (*self.0.get()).ptr.cast::<T>();
 
-        #[cfg(feature = "logging-debug")]
-        udf_log!(
-            Debug: "{box_ptr:p} {} bytes server->udf control transfer ({})",
-            size_of::<T>(),type_name::<T>()
-        );
+        if log_filter::enabled_for::<U>(Level::Debug) {
+            udf_log!(
+                Debug: "{box_ptr:p} {} bytes server->udf control transfer ({})",
+                size_of::<T>(),type_name::<T>()
+            );
+        }
 
         Box::from_raw(box_ptr)
     }
@@ -151,6 +181,9 @@ impl UdfCfg<Init> {
     ///
     /// This is mostly relevant for String and Decimal return types. See
     /// [`MaxLenOptions`] for possible defaults, including `BLOB` sizes.
+    ///
+    /// With `#[register(enforce_max_len)]`, this is also a limit: string
+    /// results longer than `max_len` are logged and returned as `NULL`.
     #[inline]
     pub fn set_max_len(&self, v: u64) {
         // Need to try_into because ulong is 64 bits in GNU but 32 bits MSVC
@@ -159,6 +192,20 @@ impl UdfCfg<Init> {
         unsafe { (*self.0.get()).max_length = set };
     }
 
+    /// Set the charset of string results, such as `utf8mb4`
+    ///
+    /// The server otherwise assumes results use the charset of the arguments
+    /// or connection. See [`crate::charset`] for availability.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server does not support charset information or
+    /// does not accept the charset.
+    #[inline]
+    pub fn set_result_charset(&self, charset: &str) -> Result<(), String> {
+        charset::result_set(self.0.get(), charset::CHARSET, charset)
+    }
+
     /// Set a new `const_item` value
     ///
     /// Set this to true if your function always returns the same values with
@@ -232,6 +279,12 @@ mod tests {
             map: HashMap<i64, f64>,
         }
 
+        impl RegisteredBasicUdf for X {
+            const NAME: &'static str = "x";
+            const ALIASES: &'static [&'static str] = &[];
+            const DEFAULT_NAME_USED: bool = false;
+        }
+
         let mut map = HashMap::new();
         map.insert(930_984_098, 4_525_435_435.900_981);
         map.insert(12_341_234, -234.090_909_092);
@@ -244,9 +297,9 @@ mod tests {
 
         let mut m = MockUdfCfg::new();
         let cfg = m.as_init();
-        cfg.store_box(Box::new(stored.clone()));
+        cfg.store_box::<X, _>(Box::new(stored.clone()));
 
-        let loaded: X = unsafe { *cfg.retrieve_box() };
+        let loaded: X = unsafe { *cfg.retrieve_box::<X, _>() };
         assert_eq!(stored, loaded);
     }
 
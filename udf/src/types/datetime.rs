@@ -0,0 +1,585 @@
+//! Date and time arguments and results, using `chrono`
+//!
+//! SQL `DATETIME`, `DATE` and `TIME` values are passed to UDFs as strings, and
+//! must be returned as strings. The parsers here accept the literal formats
+//! that `MySQL` and `MariaDB` accept; see [`SqlResult::as_datetime`],
+//! [`SqlResult::as_date`] and [`SqlResult::as_time`].
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::{Init, SqlResult, SqlReturn, SqlType, ToSqlReturn, UdfCfg};
+
+/// Largest `TIME` value, `838:59:59`, in seconds
+const TIME_MAX_SECS: i64 = 838 * 3600 + 59 * 60 + 59;
+
+/// A date or datetime that may be the "zero" value `0000-00-00`
+///
+/// The server accepts zero dates unless `NO_ZERO_DATE` is set, but they have
+/// no `chrono` equivalent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::exhaustive_enums)]
+pub enum MaybeZero<T> {
+    /// `0000-00-00` or `0000-00-00 00:00:00`
+    Zero,
+    /// Any other date
+    Value(T),
+}
+
+impl<T> MaybeZero<T> {
+    /// Get the value, or `None` for a zero date
+    #[inline]
+    pub fn value(self) -> Option<T> {
+        match self {
+            Self::Zero => None,
+            Self::Value(v) => Some(v),
+        }
+    }
+
+    /// Check if this is the zero date
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        matches!(*self, Self::Zero)
+    }
+}
+
+impl SqlResult<'_> {
+    /// Return this type as a date and time if possible
+    ///
+    /// Accepted formats are `YYYY-MM-DD hh:mm:ss[.fraction]` (any punctuation
+    /// may be used as a delimiter, and `T` may separate the date and time),
+    /// `YYYYMMDDhhmmss[.fraction]`, and the same with two-digit years
+    /// (`70`-`99` are 1970-1999, `00`-`69` are 2000-2069). A date without a
+    /// time is midnight. Integers such as `20240131120000` are also accepted.
+    ///
+    /// See [`SqlResult::as_int()`] for further details on `as_*` methods
+    ///
+    /// ```
+    /// use udf::chrono::NaiveDate;
+    /// use udf::{MaybeZero, SqlResult};
+    ///
+    /// let dt = NaiveDate::from_ymd_opt(2024, 1, 31)
+    ///     .unwrap()
+    ///     .and_hms_milli_opt(12, 30, 0, 500)
+    ///     .unwrap();
+    /// let arg = SqlResult::String(Some(b"2024-01-31 12:30:00.5"));
+    /// assert_eq!(arg.as_datetime(), Some(MaybeZero::Value(dt)));
+    ///
+    /// let arg = SqlResult::String(Some(b"0000-00-00 00:00:00"));
+    /// assert_eq!(arg.as_datetime(), Some(MaybeZero::Zero));
+    /// ```
+    #[inline]
+    pub fn as_datetime(&self) -> Option<MaybeZero<NaiveDateTime>> {
+        self.with_text(parse_datetime)
+    }
+
+    /// Return this type as a date if possible
+    ///
+    /// Accepts `YYYY-MM-DD`, `YYYYMMDD`, their two-digit year equivalents, and
+    /// integers such as `20240131`. See [`SqlResult::as_datetime()`] for
+    /// details.
+    #[inline]
+    pub fn as_date(&self) -> Option<MaybeZero<NaiveDate>> {
+        self.with_text(parse_date)
+    }
+
+    /// Return this type as a time if possible
+    ///
+    /// SQL `TIME` values are intervals in the range `-838:59:59` to
+    /// `838:59:59`, so this returns a [`Duration`]. Accepted formats are
+    /// `[-][D ]hh:mm:ss[.fraction]`, `hh:mm`, `D hh`, and `[-]hhmmss[.fraction]`
+    /// (where `mmss` and `ss` are also allowed). Integers such as `123000`
+    /// are also accepted.
+    ///
+    /// ```
+    /// use udf::chrono::Duration;
+    /// use udf::SqlResult;
+    ///
+    /// let arg = SqlResult::String(Some(b"-1 02:30:00"));
+    /// assert_eq!(arg.as_time(), Some(-Duration::minutes(26 * 60 + 30)));
+    /// ```
+    #[inline]
+    pub fn as_time(&self) -> Option<Duration> {
+        self.with_text(parse_time)
+    }
+
+    /// Run a parser on this value's text, if it is a string, decimal or integer
+    fn with_text<T>(&self, f: fn(&str) -> Option<T>) -> Option<T> {
+        match *self {
+            Self::String(Some(v)) => f(std::str::from_utf8(v).ok()?),
+            Self::Decimal(Some(v)) => f(v),
+            Self::Int(Some(v)) => f(&v.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// A simple cursor for the parsers
+struct Cursor<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s: s.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos == self.s.len()
+    }
+
+    /// Consume `b` if it is next
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        self.pos += usize::from(found);
+        found
+    }
+
+    /// Consume a single delimiter
+    fn punct(&mut self) -> bool {
+        let found = self.peek().map_or(false, |b| b.is_ascii_punctuation());
+        self.pos += usize::from(found);
+        found
+    }
+
+    /// Consume any amount of whitespace, returning whether there was any
+    fn whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().map_or(false, |b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    /// Consume `min..=max` digits, returning their value and count
+    fn digits(&mut self, min: usize, max: usize) -> Option<(u32, usize)> {
+        let mut val = 0u32;
+        let mut count = 0;
+        while count < max {
+            let Some(b @ b'0'..=b'9') = self.peek() else {
+                break;
+            };
+            val = val * 10 + u32::from(b - b'0');
+            count += 1;
+            self.pos += 1;
+        }
+        (count >= min).then_some((val, count))
+    }
+
+    /// Consume an optional `.fraction`, returning nanoseconds. Digits past
+    /// nanosecond precision are ignored.
+    fn fraction(&mut self) -> Option<u32> {
+        if !self.eat(b'.') {
+            return Some(0);
+        }
+        let (val, count) = self.digits(1, 9)?;
+        while self.peek().map_or(false, |b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        Some(val * 10u32.pow(9 - u32::try_from(count).ok()?))
+    }
+}
+
+/// Two-digit years are 1970-2069
+fn full_year(year: u32, digits: usize) -> Option<u32> {
+    match digits {
+        2 if year < 70 => Some(year + 2000),
+        2 => Some(year + 1900),
+        4 => Some(year),
+        _ => None,
+    }
+}
+
+fn make_date(year: u32, month: u32, day: u32) -> Option<MaybeZero<NaiveDate>> {
+    if (year, month, day) == (0, 0, 0) {
+        return Some(MaybeZero::Zero);
+    }
+    NaiveDate::from_ymd_opt(year.try_into().ok()?, month, day).map(MaybeZero::Value)
+}
+
+/// Parse a delimited date, `YYYY-MM-DD` or `YY-MM-DD`
+fn delimited_date(cur: &mut Cursor) -> Option<MaybeZero<NaiveDate>> {
+    let (year, year_digits) = cur.digits(2, 4)?;
+    let year = full_year(year, year_digits)?;
+    if !cur.punct() {
+        return None;
+    }
+    let (month, _) = cur.digits(1, 2)?;
+    if !cur.punct() {
+        return None;
+    }
+    let (day, _) = cur.digits(1, 2)?;
+    make_date(year, month, day)
+}
+
+/// Parse a delimited time of day, `hh:mm[:ss][.fraction]`
+fn delimited_time(cur: &mut Cursor) -> Option<NaiveTime> {
+    let (hour, _) = cur.digits(1, 2)?;
+    if !cur.punct() {
+        return None;
+    }
+    let (min, _) = cur.digits(1, 2)?;
+    let sec = if cur.peek() == Some(b'.') || !cur.punct() {
+        0
+    } else {
+        cur.digits(1, 2)?.0
+    };
+    NaiveTime::from_hms_nano_opt(hour, min, sec, cur.fraction()?)
+}
+
+/// Split an all-digit string into fixed-width fields. Returns `None` if it
+/// contains anything else.
+fn numeric_fields(s: &str, widths: &[usize]) -> Option<Vec<u32>> {
+    if !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut fields = Vec::with_capacity(widths.len());
+    let mut pos = 0;
+    for &w in widths {
+        fields.push(s.get(pos..pos + w)?.parse().ok()?);
+        pos += w;
+    }
+    Some(fields)
+}
+
+/// Parse `YYYYMMDD` or `YYMMDD`
+fn numeric_date(s: &str) -> Option<MaybeZero<NaiveDate>> {
+    let year_digits = match s.len() {
+        8 => 4,
+        6 => 2,
+        _ => return None,
+    };
+    let f = numeric_fields(s, &[year_digits, 2, 2])?;
+    make_date(full_year(f[0], year_digits)?, f[1], f[2])
+}
+
+fn parse_date(s: &str) -> Option<MaybeZero<NaiveDate>> {
+    let s = s.trim();
+    if let Some(date) = numeric_date(s) {
+        return Some(date);
+    }
+
+    let mut cur = Cursor::new(s);
+    let date = delimited_date(&mut cur)?;
+    cur.is_done().then_some(date)
+}
+
+fn parse_datetime(s: &str) -> Option<MaybeZero<NaiveDateTime>> {
+    let s = s.trim();
+    let (date, time) = numeric_datetime(s).or_else(|| delimited_datetime(s))?;
+
+    match date {
+        MaybeZero::Value(d) => Some(MaybeZero::Value(d.and_time(time))),
+        MaybeZero::Zero if time == NaiveTime::MIN => Some(MaybeZero::Zero),
+        MaybeZero::Zero => None,
+    }
+}
+
+/// Parse `YYYYMMDDhhmmss[.fraction]` and its two-digit year and date-only
+/// variants
+fn numeric_datetime(s: &str) -> Option<(MaybeZero<NaiveDate>, NaiveTime)> {
+    let (int_part, frac) = s.split_once('.').unwrap_or((s, ""));
+    if int_part.len() <= 8 {
+        return frac
+            .is_empty()
+            .then(|| numeric_date(int_part))
+            .flatten()
+            .map(|d| (d, NaiveTime::MIN));
+    }
+
+    let (date_part, time_part) = int_part.split_at(int_part.len().checked_sub(6)?);
+    let date = numeric_date(date_part)?;
+    let t = numeric_fields(time_part, &[2, 2, 2])?;
+    let nanos = if frac.is_empty() {
+        0
+    } else {
+        let mut cur = Cursor::new(&s[int_part.len()..]);
+        let nanos = cur.fraction()?;
+        cur.is_done().then_some(nanos)?
+    };
+    Some((date, NaiveTime::from_hms_nano_opt(t[0], t[1], t[2], nanos)?))
+}
+
+/// Parse `YYYY-MM-DD[( |T)hh:mm[:ss][.fraction]]`
+fn delimited_datetime(s: &str) -> Option<(MaybeZero<NaiveDate>, NaiveTime)> {
+    let mut cur = Cursor::new(s);
+    let date = delimited_date(&mut cur)?;
+    if cur.is_done() {
+        return Some((date, NaiveTime::MIN));
+    }
+    if !cur.eat(b'T') && !cur.whitespace() {
+        return None;
+    }
+    let time = delimited_time(&mut cur)?;
+    cur.is_done().then_some((date, time))
+}
+
+fn parse_time(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (negative, s) = s.strip_prefix('-').map_or((false, s), |rest| (true, rest));
+    let mut cur = Cursor::new(s);
+
+    let (hours, mins, secs) = if s.bytes().any(|b| b == b':' || b.is_ascii_whitespace()) {
+        // `[D ]hh[:mm[:ss]]`, where `hh` alone needs a day
+        let (first, _) = cur.digits(1, 3)?;
+        let (days, hours) = if cur.whitespace() {
+            (first, cur.digits(1, 2)?.0)
+        } else {
+            (0, first)
+        };
+        let mins = if cur.eat(b':') {
+            cur.digits(1, 2)?.0
+        } else {
+            0
+        };
+        let secs = if cur.eat(b':') {
+            cur.digits(1, 2)?.0
+        } else {
+            0
+        };
+        (days * 24 + hours, mins, secs)
+    } else {
+        // `[H]HMMSS`, `MMSS` or `SS`
+        let (val, count) = cur.digits(1, 7)?;
+        if count > 2 && count < 5 {
+            (0, val / 100, val % 100)
+        } else {
+            (val / 10_000, val / 100 % 100, val % 100)
+        }
+    };
+
+    let nanos = cur.fraction()?;
+    if !cur.is_done() || mins > 59 || secs > 59 {
+        return None;
+    }
+
+    let total = (i64::from(hours) * 60 + i64::from(mins)) * 60 + i64::from(secs);
+    if total > TIME_MAX_SECS || (total == TIME_MAX_SECS && nanos > 0) {
+        return None;
+    }
+
+    let dur = Duration::seconds(total) + Duration::nanoseconds(nanos.into());
+    Some(if negative { -dur } else { dur })
+}
+
+/// Append `.ffffff` if there are fractional seconds
+fn write_micros(out: &mut String, time: &impl Timelike) {
+    // Leap seconds are represented with `nanosecond() >= 1_000_000_000`
+    let micros = time.nanosecond() % 1_000_000_000 / 1000;
+    if micros > 0 {
+        // Writing to a `String` cannot fail
+        let _ = write!(out, ".{micros:06}");
+    }
+}
+
+/// Length of `hh:mm:ss.ffffff`
+const TIME_MAX_LEN: usize = 15;
+
+/// Length of `YYYY-MM-DD`
+const DATE_MAX_LEN: usize = 10;
+
+/// Length of `YYYY-MM-DD hh:mm:ss.ffffff`
+const DATETIME_MAX_LEN: usize = DATE_MAX_LEN + 1 + TIME_MAX_LEN;
+
+/// Returned as a `DATETIME` string, `YYYY-MM-DD hh:mm:ss[.ffffff]`
+impl<'a> ToSqlReturn<'a> for NaiveDateTime {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        let mut out = String::with_capacity(DATETIME_MAX_LEN);
+        let _ = write!(
+            out,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second()
+        );
+        write_micros(&mut out, &self);
+        SqlReturn::Bytes(Cow::Owned(out.into_bytes()))
+    }
+
+    #[inline]
+    fn set_init_defaults(cfg: &UdfCfg<Init>) {
+        cfg.set_max_len(DATETIME_MAX_LEN as u64);
+    }
+}
+
+/// Returned as a `DATE` string, `YYYY-MM-DD`
+impl<'a> ToSqlReturn<'a> for NaiveDate {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        let s = format!("{:04}-{:02}-{:02}", self.year(), self.month(), self.day());
+        SqlReturn::Bytes(Cow::Owned(s.into_bytes()))
+    }
+
+    #[inline]
+    fn set_init_defaults(cfg: &UdfCfg<Init>) {
+        cfg.set_max_len(DATE_MAX_LEN as u64);
+    }
+}
+
+/// Returned as a `TIME` string, `hh:mm:ss[.ffffff]`
+impl<'a> ToSqlReturn<'a> for NaiveTime {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        let mut out = format!(
+            "{:02}:{:02}:{:02}",
+            self.hour(),
+            self.minute(),
+            self.second()
+        );
+        write_micros(&mut out, &self);
+        SqlReturn::Bytes(Cow::Owned(out.into_bytes()))
+    }
+
+    #[inline]
+    fn set_init_defaults(cfg: &UdfCfg<Init>) {
+        cfg.set_max_len(TIME_MAX_LEN as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32, us: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, mo, d)
+            .unwrap()
+            .and_hms_micro_opt(h, mi, s, us)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let expected = MaybeZero::Value(dt(2024, 1, 31, 12, 30, 5, 0));
+        for s in [
+            "2024-01-31 12:30:05",
+            "2024-1-31 12:30:5",
+            "2024/01/31T12+30+05",
+            "  2024-01-31   12:30:05 ",
+            "24-01-31 12:30:05",
+            "20240131123005",
+            "240131123005",
+        ] {
+            assert_eq!(parse_datetime(s), Some(expected), "{s}");
+        }
+
+        assert_eq!(
+            parse_datetime("2024-01-31 12:30:05.123456"),
+            Some(MaybeZero::Value(dt(2024, 1, 31, 12, 30, 5, 123_456)))
+        );
+        assert_eq!(
+            parse_datetime("20240131123005.5"),
+            Some(MaybeZero::Value(dt(2024, 1, 31, 12, 30, 5, 500_000)))
+        );
+        assert_eq!(
+            parse_datetime("2024-01-31"),
+            Some(MaybeZero::Value(dt(2024, 1, 31, 0, 0, 0, 0)))
+        );
+        assert_eq!(
+            parse_datetime("2024-01-31 12:30"),
+            Some(MaybeZero::Value(dt(2024, 1, 31, 12, 30, 0, 0)))
+        );
+        assert_eq!(
+            parse_datetime("99-12-31 00:00:00"),
+            Some(MaybeZero::Value(dt(1999, 12, 31, 0, 0, 0, 0)))
+        );
+        assert_eq!(parse_datetime("0000-00-00 00:00:00"), Some(MaybeZero::Zero));
+        assert_eq!(parse_datetime("00000000000000"), Some(MaybeZero::Zero));
+
+        for s in [
+            "",
+            "2024-02-30 00:00:00",
+            "2024-01-31 24:00:00",
+            "2024-01-31 12:30:05.",
+            "2024-01-31 12:30:05 x",
+            "0000-00-00 12:00:00",
+            "202401311230",
+            "2024-01",
+        ] {
+            assert_eq!(parse_datetime(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let expected = NaiveDate::from_ymd_opt(2024, 1, 31).map(MaybeZero::Value);
+        for s in ["2024-01-31", "24.1.31", "20240131", "240131"] {
+            assert_eq!(parse_date(s), expected, "{s}");
+        }
+        assert_eq!(parse_date("0000-00-00"), Some(MaybeZero::Zero));
+        assert_eq!(parse_date("2024-01-31 00:00:00"), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let hms = |h: i64, m: i64, s: i64| Duration::seconds((h * 60 + m) * 60 + s);
+
+        assert_eq!(parse_time("12:30:05"), Some(hms(12, 30, 5)));
+        assert_eq!(parse_time("12:30"), Some(hms(12, 30, 0)));
+        assert_eq!(parse_time("-838:59:59"), Some(-hms(838, 59, 59)));
+        assert_eq!(parse_time("2 01:00:00"), Some(hms(49, 0, 0)));
+        assert_eq!(parse_time("2 01"), Some(hms(49, 0, 0)));
+        assert_eq!(parse_time("123005"), Some(hms(12, 30, 5)));
+        assert_eq!(parse_time("3005"), Some(hms(0, 30, 5)));
+        assert_eq!(parse_time("5"), Some(hms(0, 0, 5)));
+        assert_eq!(
+            parse_time("00:00:01.25"),
+            Some(Duration::milliseconds(1250))
+        );
+
+        for s in ["839:00:00", "838:59:59.1", "12:60:00", "1:2:3:4", "abc", ""] {
+            assert_eq!(parse_time(s), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_sql_result() {
+        let expected = Some(MaybeZero::Value(dt(2024, 1, 31, 12, 30, 5, 0)));
+        assert_eq!(
+            SqlResult::Int(Some(20_240_131_123_005)).as_datetime(),
+            expected
+        );
+        assert_eq!(
+            SqlResult::Decimal(Some("20240131123005.000")).as_datetime(),
+            expected
+        );
+        assert_eq!(SqlResult::Real(Some(1.0)).as_datetime(), None);
+        assert_eq!(SqlResult::String(None).as_date(), None);
+    }
+
+    #[test]
+    fn test_return() {
+        let bytes = |r: SqlReturn| match r {
+            SqlReturn::Bytes(b) => String::from_utf8(b.into_owned()).unwrap(),
+            other => panic!("{other:?}"),
+        };
+
+        let val = dt(2024, 1, 31, 12, 30, 5, 0);
+        assert_eq!(bytes(val.into_sql_return()), "2024-01-31 12:30:05");
+
+        let val = dt(999, 1, 2, 3, 4, 5, 60);
+        assert_eq!(bytes(val.into_sql_return()), "0999-01-02 03:04:05.000060");
+        assert_eq!(bytes(val.date().into_sql_return()), "0999-01-02");
+        assert_eq!(bytes(val.time().into_sql_return()), "03:04:05.000060");
+    }
+}
@@ -0,0 +1,202 @@
+//! An exact decimal type that can be returned from a UDF
+
+use std::fmt;
+
+/// Largest precision supported by [`Decimal`], limited by its `i128` storage
+pub const DECIMAL_MAX_PRECISION: u8 = 38;
+
+/// An exact fixed-point number, equivalent to SQL's `DECIMAL(P, S)`
+///
+/// `P` is the total number of significant digits and `S` is the number of
+/// those digits that come after the decimal point. The value is stored as an
+/// unscaled integer, so `Decimal::<5, 2>::from_unscaled(12345)` represents
+/// `123.45`. `P` may be at most [`DECIMAL_MAX_PRECISION`].
+///
+/// When a UDF's `Returns` type is `Decimal<P, S>` or `Option<Decimal<P, S>>`,
+/// `max_len` and `decimals` are set from `P` and `S` before the user's
+/// `init` is called.
+///
+/// ```
+/// use udf::Decimal;
+///
+/// let price = Decimal::<10, 2>::from_unscaled(1999).unwrap();
+/// let qty = Decimal::<10, 2>::from_int(3).unwrap();
+/// assert_eq!(price.to_string(), "19.99");
+/// assert_eq!(price.checked_add(qty).unwrap().to_string(), "22.99");
+///
+/// // Values that need more than `P` digits are rejected
+/// assert!(Decimal::<3, 1>::from_unscaled(1000).is_none());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal<const P: u8, const S: u8>(i128);
+
+impl<const P: u8, const S: u8> Decimal<P, S> {
+    /// Fails to compile if the parameters are not valid
+    const VALID: () = assert!(
+        P > 0 && P <= DECIMAL_MAX_PRECISION && S <= P,
+        "`Decimal<P, S>` requires 0 < P <= 38 and S <= P"
+    );
+
+    /// Total number of digits
+    pub const PRECISION: u8 = P;
+
+    /// Number of digits after the decimal point
+    pub const SCALE: u8 = S;
+
+    /// Maximum length of the text representation, including a sign, decimal
+    /// point, and leading zero (if `S == P`)
+    pub const MAX_LEN: usize = P as usize + 1 + (S > 0) as usize + (S == P) as usize;
+
+    /// Largest unscaled value, `10^P - 1`
+    const MAX_UNSCALED: i128 = 10i128.pow(P as u32) - 1;
+
+    /// Zero
+    pub const ZERO: Self = Self(0);
+
+    /// The largest representable value, e.g. `999.99` for `Decimal<5, 2>`
+    pub const MAX: Self = Self(Self::MAX_UNSCALED);
+
+    /// The smallest representable value, e.g. `-999.99` for `Decimal<5, 2>`
+    pub const MIN: Self = Self(-Self::MAX_UNSCALED);
+
+    /// Create a decimal from its unscaled value, i.e. `value * 10^-S`. Returns
+    /// `None` if this would need more than `P` digits.
+    #[inline]
+    pub fn from_unscaled(value: i128) -> Option<Self> {
+        let () = Self::VALID;
+        (value.unsigned_abs() <= Self::MAX_UNSCALED.unsigned_abs()).then_some(Self(value))
+    }
+
+    /// Create a decimal from an integer, returning `None` if it does not fit
+    #[inline]
+    pub fn from_int(value: i64) -> Option<Self> {
+        i128::from(value)
+            .checked_mul(10i128.pow(u32::from(S)))
+            .and_then(Self::from_unscaled)
+    }
+
+    /// The unscaled value, such that this decimal is `unscaled * 10^-S`
+    #[inline]
+    pub fn unscaled(self) -> i128 {
+        self.0
+    }
+
+    /// Add two decimals, returning `None` if the result does not fit
+    #[inline]
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).and_then(Self::from_unscaled)
+    }
+
+    /// Subtract two decimals, returning `None` if the result does not fit
+    #[inline]
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).and_then(Self::from_unscaled)
+    }
+
+    /// Multiply by an integer, returning `None` if the result does not fit
+    #[inline]
+    pub fn checked_mul_int(self, rhs: i64) -> Option<Self> {
+        self.0.checked_mul(rhs.into()).and_then(Self::from_unscaled)
+    }
+}
+
+impl<const P: u8, const S: u8> fmt::Display for Decimal<P, S> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        FmtUnscaled(self.0, S).fmt(f)
+    }
+}
+
+/// Display `.0 * 10^-.1`. Fails if the scale is larger than
+/// [`DECIMAL_MAX_PRECISION`].
+// `pub(crate)` keeps this out of the glob re-export in `types`
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) struct FmtUnscaled(pub i128, pub u8);
+
+impl fmt::Display for FmtUnscaled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(value, scale) = *self;
+        let divisor = 10u128.checked_pow(u32::from(scale)).ok_or(fmt::Error)?;
+        let abs = value.unsigned_abs();
+        let sign = if value < 0 { "-" } else { "" };
+        let int_part = abs / divisor;
+
+        if scale == 0 {
+            write!(f, "{sign}{int_part}")
+        } else {
+            let frac_part = abs % divisor;
+            let width = usize::from(scale);
+            write!(f, "{sign}{int_part}.{frac_part:0width$}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let cases: &[(i128, &str)] = &[
+            (0, "0.00"),
+            (5, "0.05"),
+            (-5, "-0.05"),
+            (12345, "123.45"),
+            (-12345, "-123.45"),
+            (99999, "999.99"),
+        ];
+        for &(unscaled, expected) in cases {
+            let dec = Decimal::<5, 2>::from_unscaled(unscaled).unwrap();
+            assert_eq!(dec.to_string(), expected);
+        }
+
+        assert_eq!(Decimal::<3, 0>::from_int(-123).unwrap().to_string(), "-123");
+        assert_eq!(Decimal::<2, 2>::MIN.to_string(), "-0.99");
+        assert_eq!(
+            Decimal::<38, 10>::MAX.to_string(),
+            "9999999999999999999999999999.9999999999"
+        );
+    }
+
+    #[test]
+    fn test_max_len() {
+        assert_eq!(
+            Decimal::<5, 2>::MAX_LEN,
+            Decimal::<5, 2>::MIN.to_string().len()
+        );
+        assert_eq!(
+            Decimal::<3, 0>::MAX_LEN,
+            Decimal::<3, 0>::MIN.to_string().len()
+        );
+        assert_eq!(
+            Decimal::<2, 2>::MAX_LEN,
+            Decimal::<2, 2>::MIN.to_string().len()
+        );
+        assert_eq!(
+            Decimal::<38, 10>::MAX_LEN,
+            Decimal::<38, 10>::MIN.to_string().len()
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert!(Decimal::<5, 2>::from_unscaled(100_000).is_none());
+        assert!(Decimal::<5, 2>::from_unscaled(-100_000).is_none());
+        assert!(Decimal::<5, 2>::from_int(999).is_some());
+        assert!(Decimal::<5, 2>::from_int(1000).is_none());
+        assert!(Decimal::<38, 0>::from_int(i64::MIN).is_some());
+
+        let max = Decimal::<5, 2>::MAX;
+        assert_eq!(max.checked_add(Decimal::ZERO), Some(max));
+        assert_eq!(max.checked_add(Decimal::from_unscaled(1).unwrap()), None);
+        assert_eq!(Decimal::<5, 2>::MIN.checked_sub(max), None);
+        assert_eq!(
+            Decimal::<5, 2>::from_unscaled(250)
+                .unwrap()
+                .checked_mul_int(4)
+                .unwrap()
+                .to_string(),
+            "10.00"
+        );
+    }
+}
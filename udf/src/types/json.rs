@@ -0,0 +1,144 @@
+//! JSON arguments and results, using `serde_json`
+//!
+//! SQL `JSON` columns are passed to UDFs as strings, so these are thin
+//! wrappers around `serde_json`'s parsing and serialization.
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FromSqlArg, SqlResult, SqlReturn, SqlType, ToSqlReturn, MYSQL_RESULT_BUFFER_SIZE};
+
+/// A value that is passed or returned as JSON text
+///
+/// As a result, the value is serialized with `serde_json`. Output that fits is
+/// copied to the server's buffer; longer output is kept by the wrapper until
+/// the next call, so there is no size limit. If serialization fails, the
+/// reason is logged and the UDF returns an error.
+///
+/// As an argument, the string is deserialized to `T`, which may borrow from
+/// the argument. Invalid JSON fails argument extraction.
+///
+/// ```
+/// use udf::{Json, SqlResult, SqlReturn, ToSqlReturn};
+///
+/// let ret = Json(vec![1, 2, 3]).into_sql_return();
+/// assert_eq!(ret, SqlReturn::Bytes(b"[1,2,3]".to_vec().into()));
+///
+/// let arg = SqlResult::String(Some(br#"{"a": 1}"#));
+/// let val: serde_json::Value = arg.as_json().unwrap();
+/// assert_eq!(val["a"], 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    /// Get the wrapped value
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Json<T> {
+    #[inline]
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<'a> SqlResult<'a> {
+    /// Deserialize this value from JSON if possible
+    ///
+    /// Strings and decimals are parsed; any other type, `NULL`, or invalid
+    /// JSON gives `None`. The result may borrow from the argument, e.g. as a
+    /// `&str` field.
+    ///
+    /// See [`SqlResult::as_int()`] for further details on `as_*` methods
+    #[inline]
+    pub fn as_json<T: Deserialize<'a>>(&self) -> Option<T> {
+        match *self {
+            Self::String(Some(v)) => serde_json::from_slice(v).ok(),
+            Self::Decimal(Some(v)) => serde_json::from_str(v).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, T: Deserialize<'a>> FromSqlArg<'a> for Json<T> {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_json().map(Self)
+    }
+}
+
+impl<'a, T: Serialize> ToSqlReturn<'a> for Json<T> {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        let mut buf = Vec::with_capacity(MYSQL_RESULT_BUFFER_SIZE);
+        match serde_json::to_writer(&mut buf, &self.0) {
+            Ok(()) => SqlReturn::Bytes(Cow::Owned(buf)),
+            Err(e) => {
+                udf_log!(Error: "failed to serialize JSON result: {e}");
+                SqlReturn::Error
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{mock_args, ProcessErrorKind};
+
+    #[test]
+    fn test_as_json() {
+        let res = SqlResult::String(Some(br#"{"name": "apple", "qty": 3}"#));
+        let map: BTreeMap<&str, serde_json::Value> = res.as_json().unwrap();
+        assert_eq!(map["qty"], 3);
+        // Strings without escapes can be borrowed
+        let res = SqlResult::String(Some(br#"["a", "b"]"#));
+        assert_eq!(res.as_json::<Vec<&str>>(), Some(vec!["a", "b"]));
+        assert_eq!(SqlResult::Decimal(Some("1.5")).as_json::<f64>(), Some(1.5));
+        assert_eq!(SqlResult::String(Some(b"{")).as_json::<Vec<&str>>(), None);
+        assert_eq!(SqlResult::String(None).as_json::<u32>(), None);
+        assert_eq!(SqlResult::Int(Some(1)).as_json::<u32>(), None);
+    }
+
+    #[test]
+    fn test_extract_json() {
+        let mut args = mock_args![(String "[1, 2, 3]", "a", false), (String None, "b", true)];
+        let (a, b): (Json<Vec<i64>>, Option<Json<Vec<i64>>>) = args.as_process().extract().unwrap();
+        assert_eq!(a, Json(vec![1, 2, 3]));
+        assert_eq!(b, None);
+
+        // Invalid JSON is an error
+        let mut args = mock_args![(String "nope", "a", false)];
+        assert_eq!(
+            args.as_process()
+                .extract::<(Json<Vec<i64>>,)>()
+                .unwrap_err()
+                .kind(),
+            ProcessErrorKind::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_json_return() {
+        let map = BTreeMap::from([("a", 1), ("b", 2)]);
+        assert_eq!(
+            Json(&map).into_sql_return(),
+            SqlReturn::Bytes(Cow::Borrowed(br#"{"a":1,"b":2}"#))
+        );
+        assert_eq!(<Option<Json<()>> as ToSqlReturn>::SQL_TYPE, SqlType::String);
+
+        // Keys must be strings
+        let bad = BTreeMap::from([((1, 2), 3)]);
+        assert_eq!(Json(bad).into_sql_return(), SqlReturn::Error);
+    }
+}
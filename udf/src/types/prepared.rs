@@ -0,0 +1,196 @@
+//! Values derived from arguments that may be computed once in `init`
+
+use std::fmt;
+
+use crate::{ArgList, Init, Process, ProcessError, SqlResult};
+
+/// A value derived from one argument, computed in `init` if the argument is
+/// constant and on every row otherwise
+///
+/// Many UDFs take an argument that is usually a literal, such as a pattern or a
+/// format string, and turn it into something more expensive to build. Storing a
+/// `Prepared` in the UDF struct runs the conversion once when the server
+/// provides a const value during `init`, and again for each row only when it
+/// does not.
+///
+/// ```
+/// use udf::prelude::*;
+/// use udf::Prepared;
+///
+/// struct Split {
+///     sep: Prepared<char>,
+/// }
+///
+/// fn parse_sep(value: &SqlResult<'_>) -> Result<char, ProcessError> {
+///     let s = value.as_string().unwrap_or_default();
+///     let mut chars = s.chars();
+///     match (chars.next(), chars.next()) {
+///         (Some(c), None) => Ok(c),
+///         _ => Err(ProcessError::msg("separator must be one character")),
+///     }
+/// }
+///
+/// #[register]
+/// impl BasicUdf for Split {
+///     type Returns<'a> = i64;
+///
+///     fn init(_cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+///         if args.len() != 2 {
+///             return Err("expected 2 arguments".to_owned());
+///         }
+///         // `SELECT split(x, ',')` fails here rather than on the first row
+///         Ok(Self {
+///             sep: Prepared::new(args, 1, parse_sep)?,
+///         })
+///     }
+///
+///     fn process<'a>(
+///         &'a mut self,
+///         _cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         _error: Option<NonZeroU8>,
+///     ) -> Result<Self::Returns<'a>, ProcessError> {
+///         let sep = *self.sep.get(args)?;
+///         let value = args.get(0).unwrap().value();
+///         let s = value.as_string().unwrap_or_default();
+///         Ok(s.split(sep).count().try_into()?)
+///     }
+/// }
+/// ```
+pub struct Prepared<T> {
+    index: usize,
+    prepare: fn(&SqlResult<'_>) -> Result<T, ProcessError>,
+    /// Set in `init` if the argument was const
+    cached: Option<T>,
+    /// The value for the current row if the argument is not const
+    row: Option<T>,
+}
+
+impl<T> Prepared<T> {
+    /// Run `prepare` on argument `index` if it is const
+    ///
+    /// `prepare` is kept and used for each row if it is not. `NULL` constants
+    /// cannot be told apart from non-constant arguments during `init`, so these
+    /// are also converted per row.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `prepare`, prefixed with the argument's
+    /// attribute, so that it can be returned directly from `init`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no argument at `index`. Check the argument count
+    /// first.
+    pub fn new(
+        args: &ArgList<Init>,
+        index: usize,
+        prepare: fn(&SqlResult<'_>) -> Result<T, ProcessError>,
+    ) -> Result<Self, String> {
+        let arg = args
+            .get(index)
+            .unwrap_or_else(|| panic!("no argument at index {index}"));
+
+        let cached = if arg.is_const() {
+            let value = prepare(&arg.value())
+                .map_err(|e| format!("Argument `{}`: {e}", arg.attribute()))?;
+            Some(value)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            index,
+            prepare,
+            cached,
+            row: None,
+        })
+    }
+
+    /// Whether the value was computed in `init`
+    #[inline]
+    pub fn is_const(&self) -> bool {
+        self.cached.is_some()
+    }
+
+    /// Get the prepared value, or compute it from this row's argument if it
+    /// was not const
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `prepare` if it is run and fails. An argument
+    /// missing in `process` is reported as an error rather than a panic.
+    pub fn get(&mut self, args: &ArgList<Process>) -> Result<&T, ProcessError> {
+        if let Some(ref value) = self.cached {
+            return Ok(value);
+        }
+        let arg = args
+            .get(self.index)
+            .ok_or_else(|| ProcessError::msg(format!("missing argument {}", self.index)))?;
+        let value = (self.prepare)(&arg.value())?;
+        Ok(self.row.insert(value))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Prepared<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prepared")
+            .field("index", &self.index)
+            .field("cached", &self.cached)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::mock_args;
+
+    thread_local! {
+        static CALLS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn parse_int(value: &SqlResult<'_>) -> Result<i64, ProcessError> {
+        CALLS.with(|c| c.set(c.get() + 1));
+        value
+            .as_string()
+            .ok_or_else(|| ProcessError::msg("expected a string"))?
+            .parse()
+            .map_err(Into::into)
+    }
+
+    #[test]
+    fn test_const() {
+        let mut args = mock_args![(String "12", "n", false)];
+        let mut prep = Prepared::new(args.as_init(), 0, parse_int).unwrap();
+        assert!(prep.is_const());
+        CALLS.with(|c| c.set(0));
+
+        let mut row = mock_args![(String "99", "n", false)];
+        assert_eq!(prep.get(row.as_process()).unwrap(), &12);
+        assert_eq!(prep.get(row.as_process()).unwrap(), &12);
+        assert_eq!(CALLS.with(Cell::get), 0);
+    }
+
+    #[test]
+    fn test_per_row() {
+        let mut args = mock_args![(String None, "n", true)];
+        let mut prep = Prepared::new(args.as_init(), 0, parse_int).unwrap();
+        assert!(!prep.is_const());
+
+        let mut row = mock_args![(String "5", "n", false)];
+        assert_eq!(prep.get(row.as_process()).unwrap(), &5);
+        let mut row = mock_args![(String "x", "n", false)];
+        assert!(prep.get(row.as_process()).is_err());
+    }
+
+    #[test]
+    fn test_init_error() {
+        let mut args = mock_args![(String "abc", "sep", false)];
+        let err = Prepared::new(args.as_init(), 0, parse_int).unwrap_err();
+        assert_eq!(err, "Argument `sep`: invalid digit found in string");
+    }
+}
@@ -0,0 +1,176 @@
+//! The error type returned from `process`
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+/// The category of a [`ProcessError`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProcessErrorKind {
+    /// No more specific kind applies
+    #[default]
+    Other,
+    /// An argument was missing, `NULL`, or could not be converted
+    InvalidArgument,
+    /// A computed value did not fit in the result type
+    OutOfRange,
+}
+
+impl fmt::Display for ProcessErrorKind {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match *self {
+            Self::Other => "udf processing error",
+            Self::InvalidArgument => "invalid argument",
+            Self::OutOfRange => "value out of range",
+        };
+        f.write_str(msg)
+    }
+}
+
+/// An error returned from [`BasicUdf::process`](crate::BasicUdf::process)
+///
+/// The server only receives `NULL` and an error flag, so the reason is logged
+/// with the UDF's name instead. Logging is rate-limited so that a failing
+/// column does not flood the error log.
+///
+/// Any [`Error`] converts to a `ProcessError` with `?`, and becomes its
+/// source. For this reason, `ProcessError` does not implement `Error` itself.
+///
+/// ```
+/// use udf::{ProcessError, ProcessErrorKind};
+///
+/// fn parse(s: &str) -> Result<i64, ProcessError> {
+///     Ok(s.parse::<i64>()?)
+/// }
+///
+/// assert!(parse("12").is_ok());
+/// assert_eq!(
+///     parse("x").unwrap_err().to_string(),
+///     "invalid digit found in string"
+/// );
+///
+/// let err = ProcessError::msg("no such host").with_kind(ProcessErrorKind::InvalidArgument);
+/// assert_eq!(err.kind(), ProcessErrorKind::InvalidArgument);
+/// assert_eq!(err.to_string(), "no such host");
+/// ```
+#[derive(Debug, Default)]
+pub struct ProcessError {
+    kind: ProcessErrorKind,
+    message: Option<Cow<'static, str>>,
+    source: Option<Box<dyn Error + Send + Sync + 'static>>,
+}
+
+impl ProcessError {
+    /// Create an error with no message
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            kind: ProcessErrorKind::Other,
+            message: None,
+            source: None,
+        }
+    }
+
+    /// Create an error with a message
+    #[inline]
+    pub fn msg(message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..Self::new()
+        }
+    }
+
+    /// Set the kind of this error
+    #[inline]
+    #[must_use]
+    pub fn with_kind(mut self, kind: ProcessErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set the underlying cause of this error
+    #[inline]
+    #[must_use]
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The kind of this error
+    #[inline]
+    pub fn kind(&self) -> ProcessErrorKind {
+        self.kind
+    }
+
+    /// The message of this error, if any
+    #[inline]
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The underlying cause of this error, if any
+    #[inline]
+    pub fn source(&self) -> Option<&(dyn Error + Send + Sync + 'static)> {
+        self.source.as_deref()
+    }
+}
+
+impl fmt::Display for ProcessError {
+    /// Print the message and the source, or the kind if there are neither
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.message.as_deref(), self.source.as_deref()) {
+            (Some(msg), Some(src)) => write!(f, "{msg}: {src}"),
+            (Some(msg), None) => f.write_str(msg),
+            (None, Some(src)) => write!(f, "{src}"),
+            (None, None) => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl<E: Error + Send + Sync + 'static> From<E> for ProcessError {
+    #[inline]
+    fn from(value: E) -> Self {
+        Self::new().with_source(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecimalError;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ProcessError::new().to_string(), "udf processing error");
+        assert_eq!(
+            ProcessError::new()
+                .with_kind(ProcessErrorKind::OutOfRange)
+                .to_string(),
+            "value out of range"
+        );
+        assert_eq!(
+            ProcessError::msg("bad seed")
+                .with_source(DecimalError::Overflow)
+                .to_string(),
+            "bad seed: decimal too large for target type"
+        );
+    }
+
+    #[test]
+    fn test_from_error() {
+        fn overflow() -> Result<(), ProcessError> {
+            Err(DecimalError::Overflow)?
+        }
+
+        let err = overflow().unwrap_err();
+        assert_eq!(err.kind(), ProcessErrorKind::Other);
+        assert_eq!(err.message(), None);
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            "decimal too large for target type"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! A reusable buffer for string and blob results
+
+use std::borrow::Cow;
+use std::{fmt, io};
+
+use crate::{SqlReturn, SqlType, ToSqlReturn};
+
+/// A buffer for building string results that keeps its allocation between
+/// rows
+///
+/// Returning a `String` or `Vec<u8>` from `process` allocates for every row.
+/// Instead, a `ResultBuf` can be stored in the UDF struct, cleared and written
+/// to on each call (with [`fmt::Write`] or [`io::Write`]), and returned by
+/// reference. Results that fit in the server's buffer are copied there, and
+/// larger results are returned as a pointer into the `ResultBuf` without a
+/// copy, so after the first few rows no allocations are needed.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// use udf::prelude::*;
+/// use udf::ResultBuf;
+///
+/// struct Repeat {
+///     out: ResultBuf,
+/// }
+///
+/// #[register]
+/// impl BasicUdf for Repeat {
+///     type Returns<'a> = Option<&'a ResultBuf>;
+///
+///     fn init(cfg: &UdfCfg<Init>, args: &ArgList<Init>) -> Result<Self, String> {
+///         if args.len() != 2 {
+///             return Err("expected 2 arguments".to_owned());
+///         }
+///         cfg.set_max_len(u32::MAX.into());
+///         Ok(Self {
+///             out: ResultBuf::new(),
+///         })
+///     }
+///
+///     fn process<'a>(
+///         &'a mut self,
+///         _cfg: &UdfCfg<Process>,
+///         args: &ArgList<Process>,
+///         _error: Option<NonZeroU8>,
+///     ) -> Result<Self::Returns<'a>, ProcessError> {
+///         let value = args.get(0).unwrap().value();
+///         let (Some(s), Some(n)) = (value.as_string(), args.get(1).unwrap().value().as_int())
+///         else {
+///             return Ok(None);
+///         };
+///
+///         self.out.clear();
+///         for i in 0..n {
+///             write!(self.out, "{i}:{s};")?;
+///         }
+///         Ok(Some(&self.out))
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResultBuf {
+    buf: Vec<u8>,
+}
+
+impl ResultBuf {
+    /// Create an empty buffer. This does not allocate.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    /// Create an empty buffer with space for at least `capacity` bytes
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Remove the previous contents but keep the allocation. Call this at the
+    /// start of `process`.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Append a string
+    #[inline]
+    pub fn push_str(&mut self, s: &str) {
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    /// Append bytes, which do not need to be valid UTF-8
+    #[inline]
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The current contents
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// The current contents as a string, if they are valid UTF-8
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.buf).ok()
+    }
+
+    /// Length of the current contents in bytes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Whether the buffer is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Number of bytes that can be written without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Release memory above `max` bytes, e.g. after an unusually large row
+    #[inline]
+    pub fn shrink_to(&mut self, max: usize) {
+        self.buf.shrink_to(max);
+    }
+
+    /// Access the underlying `Vec` for other ways of writing
+    #[inline]
+    pub fn as_mut_vec(&mut self) -> &mut Vec<u8> {
+        &mut self.buf
+    }
+}
+
+impl fmt::Write for ResultBuf {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl io::Write for ResultBuf {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.buf.extend_from_slice(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsRef<[u8]> for ResultBuf {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl From<Vec<u8>> for ResultBuf {
+    #[inline]
+    fn from(buf: Vec<u8>) -> Self {
+        Self { buf }
+    }
+}
+
+impl From<String> for ResultBuf {
+    #[inline]
+    fn from(s: String) -> Self {
+        Self {
+            buf: s.into_bytes(),
+        }
+    }
+}
+
+impl From<ResultBuf> for Vec<u8> {
+    #[inline]
+    fn from(value: ResultBuf) -> Self {
+        value.buf
+    }
+}
+
+/// Returned by reference, so the data stays in the UDF struct
+impl<'a, 'b: 'a> ToSqlReturn<'a> for &'b ResultBuf {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        SqlReturn::Bytes(Cow::Borrowed(&self.buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write as _;
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let mut buf = ResultBuf::new();
+        write!(buf, "{}-a", 1).unwrap();
+        io::Write::write_all(&mut buf, b"\xff").unwrap();
+        buf.push_str("z");
+        assert_eq!(buf.as_bytes(), b"1-a\xffz");
+        assert_eq!(buf.as_str(), None);
+        assert_eq!(
+            (&buf).into_sql_return(),
+            SqlReturn::Bytes(Cow::Borrowed(b"1-a\xffz"))
+        );
+    }
+
+    #[test]
+    fn test_reuse() {
+        let mut buf = ResultBuf::with_capacity(1000);
+        let ptr = buf.as_bytes().as_ptr();
+        for i in 0..10 {
+            buf.clear();
+            for _ in 0..100 {
+                write!(buf, "{i}").unwrap();
+            }
+            assert_eq!(buf.len(), 100);
+            assert_eq!(buf.as_bytes().as_ptr(), ptr);
+        }
+        assert_eq!(buf.as_str(), Some("9".repeat(100).as_str()));
+        assert_eq!(<Option<&ResultBuf>>::SQL_TYPE, SqlType::String);
+    }
+}
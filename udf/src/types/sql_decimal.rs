@@ -0,0 +1,511 @@
+//! Parsing of decimal arguments
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Decimal;
+
+/// The most digits a SQL `DECIMAL` may have
+pub const SQL_DECIMAL_MAX_DIGITS: usize = 65;
+
+/// Largest accepted exponent magnitude, e.g. the `10` in `1.5e10`
+const MAX_EXPONENT: i32 = 1000;
+
+/// An error parsing or converting a decimal value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecimalError {
+    /// The string did not contain any digits
+    Empty,
+    /// The string contained an unexpected character
+    InvalidChar,
+    /// There were more than [`SQL_DECIMAL_MAX_DIGITS`] digits
+    TooManyDigits,
+    /// The exponent was too large to be meaningful
+    ExponentRange,
+    /// The value is too large for the target type
+    Overflow,
+    /// The target type cannot represent the value exactly
+    PrecisionLoss,
+}
+
+impl fmt::Display for DecimalError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match *self {
+            Self::Empty => "no digits in decimal",
+            Self::InvalidChar => "invalid character in decimal",
+            Self::TooManyDigits => "decimal has more than 65 digits",
+            Self::ExponentRange => "decimal exponent out of range",
+            Self::Overflow => "decimal too large for target type",
+            Self::PrecisionLoss => "decimal cannot be represented exactly",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for DecimalError {}
+
+/// A parsed SQL decimal value
+///
+/// This is the exact value of a decimal argument, stored as up to 65 digits
+/// and a scale (the number of those digits after the decimal point). It can be
+/// created from a [`SqlResult::Decimal`](crate::SqlResult::Decimal) with
+/// [`SqlResult::as_decimal()`](crate::SqlResult::as_decimal), or parsed from
+/// any string in the format the server uses: an optional sign, digits with an
+/// optional decimal point, and an optional exponent (`-12.5`, `+.5`, `1.2e-3`).
+///
+/// Conversions to other numeric types are checked and return
+/// [`DecimalError::PrecisionLoss`] rather than silently rounding.
+///
+/// ```
+/// use udf::{Decimal, DecimalError, SqlDecimal};
+///
+/// let dec: SqlDecimal = "-12.50".parse().unwrap();
+/// assert_eq!(dec.to_string(), "-12.50");
+/// assert_eq!(dec.to_f64(), Ok(-12.5));
+/// assert_eq!(dec.to_i128(), Err(DecimalError::PrecisionLoss));
+/// assert_eq!(dec.to_decimal::<5, 3>().unwrap().to_string(), "-12.500");
+///
+/// let big: SqlDecimal = "1.5e3".parse().unwrap();
+/// assert_eq!(big.to_i128(), Ok(1500));
+/// ```
+#[derive(Clone, Copy)]
+pub struct SqlDecimal {
+    negative: bool,
+    /// ASCII digits without leading zeros. Only the first `len` are used.
+    digits: [u8; SQL_DECIMAL_MAX_DIGITS],
+    len: u8,
+    /// Digits after the decimal point. Negative values mean trailing zeros.
+    scale: i32,
+}
+
+impl SqlDecimal {
+    /// Parse a decimal string. This is the same as using [`str::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a valid decimal, has more than
+    /// [`SQL_DECIMAL_MAX_DIGITS`] significant digits, or has an exponent that
+    /// is out of range.
+    #[inline]
+    pub fn parse(s: &str) -> Result<Self, DecimalError> {
+        let s = s.trim();
+        let (negative, s) = match s.as_bytes().first().copied() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(idx) => (&s[..idx], parse_exponent(&s[idx + 1..])?),
+            None => (s, 0),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(DecimalError::Empty);
+        }
+
+        let mut ret = Self {
+            negative,
+            digits: [0; SQL_DECIMAL_MAX_DIGITS],
+            len: 0,
+            scale: 0,
+        };
+        for &ch in int_part.as_bytes().iter().chain(frac_part.as_bytes()) {
+            if !ch.is_ascii_digit() {
+                return Err(DecimalError::InvalidChar);
+            }
+            if ret.len == 0 && ch == b'0' {
+                continue;
+            }
+            let idx = usize::from(ret.len);
+            *ret.digits.get_mut(idx).ok_or(DecimalError::TooManyDigits)? = ch;
+            ret.len += 1;
+        }
+
+        ret.scale = i32::try_from(frac_part.len())
+            .ok()
+            .and_then(|len| len.checked_sub(exponent))
+            .ok_or(DecimalError::ExponentRange)?;
+        if ret.len == 0 {
+            ret.negative = false;
+        }
+
+        Ok(ret)
+    }
+
+    /// Whether this value is less than zero
+    #[inline]
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Whether this value is zero
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The significant digits of this value, without a sign or leading zeros
+    ///
+    /// This is empty if the value is zero.
+    #[inline]
+    pub fn digits(&self) -> &str {
+        // SAFETY: we only ever store ASCII digits
+        unsafe { std::str::from_utf8_unchecked(&self.digits[..usize::from(self.len)]) }
+    }
+
+    /// The number of digits after the decimal point, as given in the source.
+    /// This may be negative if an exponent was used, e.g. `12e3` has a scale
+    /// of -3.
+    #[inline]
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// Convert to an integer
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecimalError::PrecisionLoss`] if there is a nonzero fractional
+    /// part, or [`DecimalError::Overflow`] if the value does not fit.
+    #[inline]
+    pub fn to_i128(&self) -> Result<i128, DecimalError> {
+        let (coeff, scale) = self.normalized()?;
+        if scale > 0 {
+            return Err(DecimalError::PrecisionLoss);
+        }
+        let value = pow10(scale.unsigned_abs())
+            .and_then(|mul| coeff.checked_mul(mul))
+            .and_then(|v| i128::try_from(v).ok())
+            .ok_or(DecimalError::Overflow)?;
+
+        Ok(if self.negative { -value } else { value })
+    }
+
+    /// Convert to a float
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecimalError::PrecisionLoss`] if the value cannot be
+    /// represented exactly, or [`DecimalError::Overflow`] if it is out of
+    /// range. Values with more than 38 significant digits are always
+    /// considered lossy. Use [`SqlDecimal::to_f64_lossy`] to get the nearest
+    /// float instead.
+    #[inline]
+    pub fn to_f64(&self) -> Result<f64, DecimalError> {
+        let value = self.to_f64_lossy();
+        if value.is_infinite() {
+            return Err(DecimalError::Overflow);
+        }
+        let (coeff, scale) = self.normalized().map_err(|_| DecimalError::PrecisionLoss)?;
+        if coeff == 0 {
+            return Ok(value);
+        }
+
+        // The value is `coeff * 2^-scale * 5^-scale`. It is exact in binary
+        // only if dividing by `5^scale` leaves an integer, and the odd part of
+        // what remains fits in the 53-bit mantissa.
+        let odd_part = if scale > 0 {
+            pow5(scale.unsigned_abs())
+                .filter(|div| coeff % div == 0)
+                .map(|div| coeff / div)
+        } else {
+            pow5(scale.unsigned_abs()).and_then(|mul| strip_twos(coeff).checked_mul(mul))
+        };
+
+        match odd_part.map(strip_twos) {
+            Some(odd) if odd < (1 << f64::MANTISSA_DIGITS) && value != 0.0 => Ok(value),
+            _ => Err(DecimalError::PrecisionLoss),
+        }
+    }
+
+    /// Get the nearest float to this value
+    #[inline]
+    pub fn to_f64_lossy(&self) -> f64 {
+        // Our formatting is always valid float syntax
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Convert to a [`Decimal`] with a fixed precision and scale
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecimalError::PrecisionLoss`] if there are more than `S`
+    /// nonzero digits after the decimal point, or [`DecimalError::Overflow`]
+    /// if there are more than `P - S` digits before it.
+    #[inline]
+    pub fn to_decimal<const P: u8, const S: u8>(&self) -> Result<Decimal<P, S>, DecimalError> {
+        let (coeff, scale) = self.normalized()?;
+        let target = i32::from(S);
+        if scale > target {
+            return Err(DecimalError::PrecisionLoss);
+        }
+        let unscaled = pow10((target - scale).unsigned_abs())
+            .and_then(|mul| coeff.checked_mul(mul))
+            .and_then(|v| i128::try_from(v).ok())
+            .ok_or(DecimalError::Overflow)?;
+
+        Decimal::from_unscaled(if self.negative { -unscaled } else { unscaled })
+            .ok_or(DecimalError::Overflow)
+    }
+
+    /// Get the digits and scale with trailing zeros removed
+    fn trimmed(&self) -> (&str, i32) {
+        let digits = self.digits().trim_end_matches('0');
+        if digits.is_empty() {
+            return ("", 0);
+        }
+        // At most 65 digits get trimmed
+        let removed = (self.len - digits.len() as u8).into();
+        (digits, self.scale.saturating_sub(removed))
+    }
+
+    /// Get the coefficient and scale with trailing zeros removed
+    fn normalized(&self) -> Result<(u128, i32), DecimalError> {
+        let (digits, scale) = self.trimmed();
+        digits
+            .bytes()
+            .try_fold(0u128, |acc, ch| {
+                acc.checked_mul(10)?.checked_add(u128::from(ch - b'0'))
+            })
+            .map(|coeff| (coeff, scale))
+            .ok_or(DecimalError::Overflow)
+    }
+}
+
+impl FromStr for SqlDecimal {
+    type Err = DecimalError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Values are equal if they represent the same number, regardless of scale
+impl PartialEq for SqlDecimal {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.negative == other.negative && self.trimmed() == other.trimmed()
+    }
+}
+
+impl Eq for SqlDecimal {}
+
+impl fmt::Debug for SqlDecimal {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SqlDecimal({self})")
+    }
+}
+
+/// Format as a plain decimal, without an exponent
+impl fmt::Display for SqlDecimal {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+
+        let digits = self.digits();
+        if self.scale <= 0 {
+            f.write_str(if digits.is_empty() { "0" } else { digits })?;
+            if !digits.is_empty() {
+                (0..self.scale.unsigned_abs()).try_for_each(|_| f.write_str("0"))?;
+            }
+            return Ok(());
+        }
+
+        let scale = self.scale.unsigned_abs() as usize;
+        if digits.len() > scale {
+            let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+            write!(f, "{int_part}.{frac_part}")
+        } else {
+            write!(f, "0.{digits:0>scale$}")
+        }
+    }
+}
+
+fn parse_exponent(s: &str) -> Result<i32, DecimalError> {
+    let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|ch| ch.is_ascii_digit()) {
+        return Err(DecimalError::InvalidChar);
+    }
+    let exp: i32 = s.parse().map_err(|_| DecimalError::ExponentRange)?;
+    if exp.abs() > MAX_EXPONENT {
+        return Err(DecimalError::ExponentRange);
+    }
+    Ok(exp)
+}
+
+fn pow10(exp: u32) -> Option<u128> {
+    10u128.checked_pow(exp)
+}
+
+fn pow5(exp: u32) -> Option<u128> {
+    5u128.checked_pow(exp)
+}
+
+fn strip_twos(v: u128) -> u128 {
+    if v == 0 {
+        0
+    } else {
+        v >> v.trailing_zeros()
+    }
+}
+
+/// `rust_decimal` supports at most 28 decimal places
+#[cfg(feature = "rust_decimal")]
+const RUST_DECIMAL_MAX_SCALE: u32 = 28;
+
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<&SqlDecimal> for rust_decimal::Decimal {
+    type Error = DecimalError;
+
+    /// Convert to a `rust_decimal::Decimal`, which holds up to 28 decimal
+    /// places and a 96-bit coefficient
+    #[inline]
+    fn try_from(value: &SqlDecimal) -> Result<Self, Self::Error> {
+        let (coeff, scale) = value.normalized()?;
+        let (coeff, scale) = if scale < 0 {
+            let mul = pow10(scale.unsigned_abs()).ok_or(DecimalError::Overflow)?;
+            (coeff.checked_mul(mul).ok_or(DecimalError::Overflow)?, 0)
+        } else {
+            (coeff, scale.unsigned_abs())
+        };
+        if scale > RUST_DECIMAL_MAX_SCALE {
+            return Err(DecimalError::PrecisionLoss);
+        }
+        let coeff = i128::try_from(coeff).map_err(|_| DecimalError::Overflow)?;
+        let signed = if value.negative { -coeff } else { coeff };
+        Self::try_from_i128_with_scale(signed, scale).map_err(|_| DecimalError::Overflow)
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<&SqlDecimal> for bigdecimal::BigDecimal {
+    /// Convert to a `bigdecimal::BigDecimal`. This is always exact.
+    #[inline]
+    fn from(value: &SqlDecimal) -> Self {
+        let digits = if value.is_zero() { "0" } else { value.digits() };
+        let coeff: bigdecimal::num_bigint::BigInt = digits.parse().unwrap_or_default();
+        let signed = if value.negative { -coeff } else { coeff };
+        Self::new(signed, value.scale.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        let cases = [
+            ("0", "0", 0),
+            ("-0.00", "0.00", 2),
+            ("123.45", "123.45", 2),
+            ("-123.45", "-123.45", 2),
+            ("+.5", "0.5", 1),
+            ("5.", "5", 0),
+            ("0.001", "0.001", 3),
+            ("1.5e3", "1500", -2),
+            ("1.5E-3", "0.0015", 4),
+            ("  42  ", "42", 0),
+        ];
+        for (input, expected, scale) in cases {
+            let dec = SqlDecimal::parse(input).unwrap();
+            assert_eq!(dec.to_string(), expected, "{input}");
+            assert_eq!(dec.scale(), scale, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(SqlDecimal::parse(""), Err(DecimalError::Empty));
+        assert_eq!(SqlDecimal::parse("-."), Err(DecimalError::Empty));
+        assert_eq!(SqlDecimal::parse("1.2.3"), Err(DecimalError::InvalidChar));
+        assert_eq!(SqlDecimal::parse("12a"), Err(DecimalError::InvalidChar));
+        assert_eq!(SqlDecimal::parse("1e"), Err(DecimalError::InvalidChar));
+        assert_eq!(
+            SqlDecimal::parse("1e5000"),
+            Err(DecimalError::ExponentRange)
+        );
+
+        // 65 digits is the limit, not counting leading zeros
+        let max = "9".repeat(65);
+        assert!(SqlDecimal::parse(&max).is_ok());
+        assert!(SqlDecimal::parse(&format!("000{max}")).is_ok());
+        assert!(SqlDecimal::parse(&format!("0.{max}")).is_ok());
+        assert_eq!(
+            SqlDecimal::parse(&format!("{max}9")),
+            Err(DecimalError::TooManyDigits)
+        );
+    }
+
+    #[test]
+    fn test_eq() {
+        let parse = |s: &str| SqlDecimal::parse(s).unwrap();
+        assert_eq!(parse("1.50"), parse("1.5"));
+        assert_eq!(parse("15e-1"), parse("1.5"));
+        assert_eq!(parse("-0"), parse("0.000"));
+        assert_ne!(parse("-1.5"), parse("1.5"));
+        assert_ne!(parse("1.05"), parse("1.5"));
+    }
+
+    #[test]
+    fn test_to_i128() {
+        let conv = |s: &str| SqlDecimal::parse(s).unwrap().to_i128();
+        assert_eq!(conv("-1234"), Ok(-1234));
+        assert_eq!(conv("12.000"), Ok(12));
+        assert_eq!(conv("12e2"), Ok(1200));
+        assert_eq!(conv("12.5"), Err(DecimalError::PrecisionLoss));
+        assert_eq!(conv(&i128::MAX.to_string()), Ok(i128::MAX));
+        assert_eq!(conv(&"9".repeat(40)), Err(DecimalError::Overflow));
+    }
+
+    #[test]
+    fn test_to_f64() {
+        let conv = |s: &str| SqlDecimal::parse(s).unwrap().to_f64();
+        assert_eq!(conv("0.5"), Ok(0.5));
+        assert_eq!(conv("-12.25"), Ok(-12.25));
+        assert_eq!(conv("0"), Ok(0.0));
+        assert_eq!(conv("1e20"), Ok(1e20));
+        assert_eq!(conv("9007199254740992"), Ok(9_007_199_254_740_992.0));
+        assert_eq!(conv("9007199254740993"), Err(DecimalError::PrecisionLoss));
+        assert_eq!(conv("0.1"), Err(DecimalError::PrecisionLoss));
+        assert_eq!(conv("1e400"), Err(DecimalError::Overflow));
+
+        let lossy = SqlDecimal::parse("0.1").unwrap().to_f64_lossy();
+        assert!((lossy - 0.1).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    #[cfg(feature = "rust_decimal")]
+    fn test_rust_decimal() {
+        let conv = |s: &str| rust_decimal::Decimal::try_from(&SqlDecimal::parse(s).unwrap());
+        assert_eq!(conv("-12.50").unwrap().to_string(), "-12.5");
+        assert_eq!(conv("12e3").unwrap().to_string(), "12000");
+        assert_eq!(
+            conv(&format!("0.{}1", "0".repeat(30))),
+            Err(DecimalError::PrecisionLoss)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bigdecimal")]
+    fn test_bigdecimal() {
+        let dec = SqlDecimal::parse(&format!("-{}.5", "9".repeat(60))).unwrap();
+        let big = bigdecimal::BigDecimal::from(&dec);
+        assert_eq!(big.to_string(), dec.to_string());
+    }
+
+    #[test]
+    fn test_to_decimal() {
+        let dec = SqlDecimal::parse("-12.5").unwrap();
+        assert_eq!(dec.to_decimal::<4, 2>().unwrap().unscaled(), -1250);
+        assert_eq!(dec.to_decimal::<3, 0>(), Err(DecimalError::PrecisionLoss));
+        assert_eq!(dec.to_decimal::<3, 2>(), Err(DecimalError::Overflow));
+    }
+}
@@ -0,0 +1,287 @@
+//! Conversions from Rust types to UDF results
+
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::{Decimal, Init, SqlType, UdfCfg};
+
+/// A result in one of the forms the SQL server accepts
+///
+/// This is produced by [`ToSqlReturn::into_sql_return`]. The variant must
+/// agree with [`ToSqlReturn::SQL_TYPE`]; `Null` and `Error` are always
+/// allowed.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SqlReturn<'a> {
+    /// A `NULL` result
+    Null,
+    /// An integer, for `SqlType::Int`
+    Int(i64),
+    /// A float, for `SqlType::Real`
+    Real(f64),
+    /// A string or blob, for `SqlType::String`
+    ///
+    /// Borrowed data must remain valid until the next call to `process`, which
+    /// is always the case for data borrowed from the UDF struct or a `static`.
+    /// Owned data is copied into the server's buffer if it fits, or otherwise
+    /// kept alive by the wrapper until the next call.
+    Bytes(Cow<'a, [u8]>),
+    /// An exact decimal `unscaled * 10^-scale`, for `SqlType::Decimal`
+    Decimal {
+        /// The value without a decimal point
+        unscaled: i128,
+        /// Number of digits after the decimal point
+        scale: u8,
+    },
+    /// The value could not be converted. This sets the error flag, so the
+    /// result is `NULL`; log the reason before returning this.
+    Error,
+}
+
+/// A Rust type that can be returned from a UDF
+///
+/// Every [`BasicUdf::Returns`](crate::BasicUdf::Returns) type must implement
+/// this. It is implemented for:
+///
+/// - Integers (`i8` through `i64`, `u8` through `u32`) and `bool`, returned as
+///   `INTEGER`
+/// - `f32` and `f64`, returned as `REAL`
+/// - `&str`, `&[u8]`, `&String`, `&Vec<u8>`, `String`, `Vec<u8>`, `Box<str>`,
+///   `Box<[u8]>`, `Cow<str>`, `Cow<[u8]>`, the `Arc`/`Rc` string and byte
+///   types, and [`&ResultBuf`](crate::ResultBuf), returned as `STRING`
+/// - [`Decimal<P, S>`], returned as `DECIMAL`
+/// - `Option<T>` for any of the above, where `None` is `NULL`
+///
+/// Implement this on your own types to return them directly:
+///
+/// ```
+/// use udf::{SqlReturn, SqlType, ToSqlReturn};
+///
+/// struct Celsius(f64);
+///
+/// impl<'a> ToSqlReturn<'a> for Celsius {
+///     const SQL_TYPE: SqlType = SqlType::Real;
+///
+///     fn into_sql_return(self) -> SqlReturn<'a> {
+///         SqlReturn::Real(self.0)
+///     }
+/// }
+///
+/// assert_eq!(Celsius(21.5).into_sql_return(), SqlReturn::Real(21.5));
+/// ```
+///
+/// `#[register]` picks the C signature of the UDF from the `Returns` type when
+/// it is a primitive, and otherwise assumes a string result. If that guess
+/// does not match `SQL_TYPE` (e.g. for a type alias of `i64`), compilation
+/// fails and `#[register(returns = "int")]` (or `"real"`, `"string"`,
+/// `"decimal"`) must be given.
+pub trait ToSqlReturn<'a> {
+    /// The SQL type of this result: `Int`, `Real`, `String`, or `Decimal`
+    const SQL_TYPE: SqlType;
+
+    /// Whether this type can produce `NULL` (not counting errors). This is
+    /// only `true` for `Option`.
+    const MAYBE_NULL: bool = false;
+
+    /// Convert this value to a result
+    fn into_sql_return(self) -> SqlReturn<'a>;
+
+    /// Set defaults such as `max_len` or `decimals` before the user's `init`
+    /// is called. Does nothing by default.
+    #[inline]
+    fn set_init_defaults(cfg: &UdfCfg<Init>) {
+        let _ = cfg;
+    }
+}
+
+/// Implement `ToSqlReturn` for integers that losslessly convert to `i64`
+macro_rules! impl_int_return {
+    ($($ty:ty),*) => {$(
+        impl<'a> ToSqlReturn<'a> for $ty {
+            const SQL_TYPE: SqlType = SqlType::Int;
+
+            #[inline]
+            fn into_sql_return(self) -> SqlReturn<'a> {
+                SqlReturn::Int(self.into())
+            }
+        }
+    )*};
+}
+
+impl_int_return!(i8, i16, i32, i64, u8, u16, u32, bool);
+
+impl<'a> ToSqlReturn<'a> for f32 {
+    const SQL_TYPE: SqlType = SqlType::Real;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        SqlReturn::Real(self.into())
+    }
+}
+
+impl<'a> ToSqlReturn<'a> for f64 {
+    const SQL_TYPE: SqlType = SqlType::Real;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        SqlReturn::Real(self)
+    }
+}
+
+/// Implement `ToSqlReturn` for references to byte-like types
+macro_rules! impl_ref_return {
+    ($($ty:ty),*) => {$(
+        impl<'a, 'b: 'a> ToSqlReturn<'a> for &'b $ty {
+            const SQL_TYPE: SqlType = SqlType::String;
+
+            #[inline]
+            fn into_sql_return(self) -> SqlReturn<'a> {
+                let bytes: &[u8] = self.as_ref();
+                SqlReturn::Bytes(Cow::Borrowed(bytes))
+            }
+        }
+    )*};
+}
+
+impl_ref_return!(str, [u8], String, Vec<u8>);
+
+/// Implement `ToSqlReturn` for owned byte-like types, given a conversion to
+/// `Vec<u8>`
+macro_rules! impl_owned_return {
+    ($($ty:ty => $to_vec:expr),* $(,)?) => {$(
+        impl<'a> ToSqlReturn<'a> for $ty {
+            const SQL_TYPE: SqlType = SqlType::String;
+
+            #[inline]
+            fn into_sql_return(self) -> SqlReturn<'a> {
+                let to_vec: fn($ty) -> Vec<u8> = $to_vec;
+                SqlReturn::Bytes(Cow::Owned(to_vec(self)))
+            }
+        }
+    )*};
+}
+
+impl_owned_return!(
+    Vec<u8> => |v| v,
+    String => String::into_bytes,
+    Box<[u8]> => Vec::from,
+    Box<str> => |s| s.into_boxed_bytes().into_vec(),
+    Arc<[u8]> => |v| v.to_vec(),
+    Arc<str> => |s| s.as_bytes().to_vec(),
+    Rc<[u8]> => |v| v.to_vec(),
+    Rc<str> => |s| s.as_bytes().to_vec(),
+);
+
+impl<'a, 'b: 'a> ToSqlReturn<'a> for Cow<'b, [u8]> {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        SqlReturn::Bytes(self)
+    }
+}
+
+impl<'a, 'b: 'a> ToSqlReturn<'a> for Cow<'b, str> {
+    const SQL_TYPE: SqlType = SqlType::String;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        let bytes = match self {
+            Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+            Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+        };
+        SqlReturn::Bytes(bytes)
+    }
+}
+
+impl<'a, const P: u8, const S: u8> ToSqlReturn<'a> for Decimal<P, S> {
+    const SQL_TYPE: SqlType = SqlType::Decimal;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        SqlReturn::Decimal {
+            unscaled: self.unscaled(),
+            scale: S,
+        }
+    }
+
+    /// Decimals know their length and scale, so set them as defaults
+    #[inline]
+    fn set_init_defaults(cfg: &UdfCfg<Init>) {
+        cfg.set_max_len(Self::MAX_LEN as u64);
+        cfg.set_decimals(S.into());
+    }
+}
+
+impl<'a, T: ToSqlReturn<'a>> ToSqlReturn<'a> for Option<T> {
+    const SQL_TYPE: SqlType = T::SQL_TYPE;
+    const MAYBE_NULL: bool = true;
+
+    #[inline]
+    fn into_sql_return(self) -> SqlReturn<'a> {
+        self.map_or(SqlReturn::Null, T::into_sql_return)
+    }
+
+    /// `Option`s may be `NULL`, so also set `maybe_null`
+    #[inline]
+    fn set_init_defaults(cfg: &UdfCfg<Init>) {
+        T::set_init_defaults(cfg);
+        cfg.set_maybe_null(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(b: &[u8]) -> SqlReturn<'_> {
+        SqlReturn::Bytes(Cow::Borrowed(b))
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(10i32.into_sql_return(), SqlReturn::Int(10));
+        assert_eq!(u32::MAX.into_sql_return(), SqlReturn::Int(4_294_967_295));
+        assert_eq!(true.into_sql_return(), SqlReturn::Int(1));
+        assert_eq!(1.5f32.into_sql_return(), SqlReturn::Real(1.5));
+        assert_eq!(Some(-2i64).into_sql_return(), SqlReturn::Int(-2));
+        assert_eq!(None::<f64>.into_sql_return(), SqlReturn::Null);
+        assert_eq!(<Option<u8>>::SQL_TYPE, SqlType::Int);
+        assert_eq!((<Option<u8>>::MAYBE_NULL, u8::MAYBE_NULL), (true, false));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let s = String::from("abc");
+        assert_eq!((&s).into_sql_return(), bytes(b"abc"));
+        assert!(matches!(
+            "abc".into_sql_return(),
+            SqlReturn::Bytes(Cow::Borrowed(b"abc"))
+        ));
+        assert!(matches!(
+            s.clone().into_sql_return(),
+            SqlReturn::Bytes(Cow::Owned(_))
+        ));
+        assert_eq!(Box::<str>::from("abc").into_sql_return(), bytes(b"abc"));
+        assert_eq!(
+            Arc::<[u8]>::from(&b"abc"[..]).into_sql_return(),
+            bytes(b"abc")
+        );
+        assert_eq!(Cow::<str>::Owned(s).into_sql_return(), bytes(b"abc"));
+        assert_eq!(Some(&b"abc"[..]).into_sql_return(), bytes(b"abc"));
+    }
+
+    #[test]
+    fn test_decimal() {
+        let dec = Decimal::<5, 2>::from_unscaled(-12345).unwrap();
+        assert_eq!(
+            Some(dec).into_sql_return(),
+            SqlReturn::Decimal {
+                unscaled: -12345,
+                scale: 2
+            }
+        );
+        assert_eq!(<Option<Decimal<5, 2>>>::SQL_TYPE, SqlType::Decimal);
+    }
+}
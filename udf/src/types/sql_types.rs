@@ -4,6 +4,8 @@ use std::{slice, str};
 
 use udf_sys::Item_result;
 
+use crate::SqlDecimal;
+
 /// Enum representing possible SQL result types
 ///
 /// This simply represents the possible types, but does not contain any values.
@@ -37,7 +39,7 @@ impl SqlType {
 
     /// Small helper function to get a displayable type name.
     #[inline]
-    pub fn display_name(&self) -> &'static str {
+    pub const fn display_name(&self) -> &'static str {
         match *self {
             Self::String => "string",
             Self::Real => "real",
@@ -208,6 +210,16 @@ impl<'a> SqlResult<'a> {
     pub fn is_decimal(&self) -> bool {
         matches!(*self, Self::Decimal(_))
     }
+    /// Check if this argument is `NULL`, of any type
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        match *self {
+            Self::String(v) => v.is_none(),
+            Self::Real(v) => v.is_none(),
+            Self::Int(v) => v.is_none(),
+            Self::Decimal(v) => v.is_none(),
+        }
+    }
 
     /// Return this type as an integer if possible
     ///
@@ -254,6 +266,19 @@ impl<'a> SqlResult<'a> {
         }
     }
 
+    /// Return this type as a parsed decimal if possible
+    ///
+    /// This will exist if the variant is [`SqlResult::Decimal`], it contains a
+    /// value, and that value can be parsed. See [`SqlResult::as_int()`] for
+    /// further details on `as_*` methods, and [`SqlDecimal`] for conversions.
+    #[inline]
+    pub fn as_decimal(&self) -> Option<SqlDecimal> {
+        match *self {
+            Self::Decimal(Some(v)) => SqlDecimal::parse(v).ok(),
+            _ => None,
+        }
+    }
+
     /// Return this type as a byte slice if possible
     ///
     /// This will exist if the variant is [`SqlResult::String`], or
@@ -0,0 +1,459 @@
+//! Typed access to a UDF's arguments
+//!
+//! [`FromSqlArg`] describes how a single Rust type is read from a SQL argument,
+//! and [`UdfArgs`] combines these into a full signature (a tuple). Signatures
+//! are usually declared with `#[register(args(...))]`, which implements
+//! [`TypedArgs`] for the UDF struct.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::wrapper::{check_arg_count, extract_one, set_coercion};
+use crate::{
+    ArgList, Decimal, Init, MaybeZero, Process, ProcessError, SqlDecimal, SqlResult, SqlType,
+};
+
+/// A Rust type that can be read from a single SQL argument
+///
+/// This is implemented for `i64`, `f64`, `&str`, `String`, `&[u8]`,
+/// `Vec<u8>`, [`SqlDecimal`], [`Decimal`], the `chrono` date and time types
+/// (see [`SqlResult::as_datetime`]), and [`SqlResult`] (any type, no
+/// coercion). Wrap any of these in an `Option` to accept `NULL`.
+pub trait FromSqlArg<'a>: Sized {
+    /// The type the server should coerce this argument to, if any
+    const SQL_TYPE: Option<SqlType>;
+
+    /// Convert the (already coerced) value. Return `None` if this is not
+    /// possible, e.g. for `NULL` or invalid UTF-8.
+    fn from_sql(value: SqlResult<'a>) -> Option<Self>;
+}
+
+impl<'a> FromSqlArg<'a> for i64 {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::Int);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_int()
+    }
+}
+
+impl<'a> FromSqlArg<'a> for f64 {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::Real);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        match value {
+            SqlResult::Real(v) => v,
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromSqlArg<'a> for &'a [u8] {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        match value {
+            SqlResult::String(v) => v,
+            SqlResult::Decimal(v) => v.map(str::as_bytes),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromSqlArg<'a> for &'a str {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        match value {
+            SqlResult::String(v) => std::str::from_utf8(v?).ok(),
+            SqlResult::Decimal(v) => v,
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromSqlArg<'a> for Vec<u8> {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        <&[u8]>::from_sql(value).map(ToOwned::to_owned)
+    }
+}
+
+impl<'a> FromSqlArg<'a> for String {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        <&str>::from_sql(value).map(ToOwned::to_owned)
+    }
+}
+
+impl<'a> FromSqlArg<'a> for SqlDecimal {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::Decimal);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_decimal()
+    }
+}
+
+/// Values that would lose precision or overflow are rejected
+impl<'a, const P: u8, const S: u8> FromSqlArg<'a> for Decimal<P, S> {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::Decimal);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_decimal()?.to_decimal().ok()
+    }
+}
+
+/// Zero dates are rejected; use `MaybeZero<NaiveDateTime>` to accept them
+impl<'a> FromSqlArg<'a> for NaiveDateTime {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_datetime()?.value()
+    }
+}
+
+impl<'a> FromSqlArg<'a> for MaybeZero<NaiveDateTime> {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_datetime()
+    }
+}
+
+/// Zero dates are rejected; use `MaybeZero<NaiveDate>` to accept them
+impl<'a> FromSqlArg<'a> for NaiveDate {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_date()?.value()
+    }
+}
+
+impl<'a> FromSqlArg<'a> for MaybeZero<NaiveDate> {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_date()
+    }
+}
+
+/// A SQL `TIME`
+impl<'a> FromSqlArg<'a> for Duration {
+    const SQL_TYPE: Option<SqlType> = Some(SqlType::String);
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        value.as_time()
+    }
+}
+
+/// Accept any argument type without coercion
+impl<'a> FromSqlArg<'a> for SqlResult<'a> {
+    const SQL_TYPE: Option<SqlType> = None;
+
+    #[inline]
+    fn from_sql(value: Self) -> Option<Self> {
+        Some(value)
+    }
+}
+
+/// `NULL` is accepted and becomes `None`
+impl<'a, T: FromSqlArg<'a>> FromSqlArg<'a> for Option<T> {
+    const SQL_TYPE: Option<SqlType> = T::SQL_TYPE;
+
+    #[inline]
+    fn from_sql(value: SqlResult<'a>) -> Option<Self> {
+        if value.is_null() {
+            Some(None)
+        } else {
+            T::from_sql(value).map(Some)
+        }
+    }
+}
+
+/// A full argument signature, implemented for tuples of [`FromSqlArg`] types
+/// (up to 12 elements)
+///
+/// This can also be derived for a struct with `#[derive(UdfArgs)]`; see the
+/// [macro documentation](derive@crate::UdfArgs) for details.
+pub trait UdfArgs<'a>: Sized {
+    /// Number of positional arguments in this signature, not including any
+    /// variadic tail
+    const COUNT: usize;
+
+    /// Check the number of arguments and set type coercions. Meant to be called
+    /// during `init`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the standard "Expected N arguments; got M" message if the
+    /// argument count does not match.
+    fn validate(args: &ArgList<Init>) -> Result<(), String>;
+
+    /// Read all arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an argument is missing, `NULL` for a non-`Option`
+    /// type, or cannot be converted.
+    fn extract(args: &'a ArgList<'a, Process>) -> Result<Self, ProcessError>;
+}
+
+/// Implemented by `#[register(args(...))]` to give a UDF a typed signature
+///
+/// When registered this way, the generated `init` checks the argument count
+/// and sets coercions before calling [`BasicUdf::init`](crate::BasicUdf::init),
+/// so the user's `init` only needs to handle anything specific to the UDF.
+/// Within `process`, `Self::typed_args(args)?` returns the typed tuple.
+pub trait TypedArgs {
+    /// The argument tuple, e.g. `(&'a str, Option<i64>)`. A variadic tail is
+    /// collected into a `Vec<T>`.
+    type Args<'a>;
+
+    /// Set coercions and check that arguments are constant where required.
+    /// The argument count is checked by the wrapper before this is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error message for the server if an argument is not valid.
+    fn validate_args(args: &ArgList<Init>) -> Result<(), String>;
+
+    /// Read the arguments as their declared types
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an argument is `NULL` for a non-`Option` type or
+    /// cannot be converted. The message names the argument by its attribute.
+    fn typed_args<'a>(args: &'a ArgList<'a, Process>) -> Result<Self::Args<'a>, ProcessError>;
+}
+
+impl<'a> ArgList<'a, Process> {
+    /// Read all arguments as a tuple of typed values
+    ///
+    /// The argument list should have been validated with [`UdfArgs::validate`]
+    /// during `init`, so that the server coerces arguments to the correct
+    /// types.
+    ///
+    /// ```
+    /// # use udf::prelude::*;
+    /// # use udf::mock::*;
+    /// let mut mock = mock_args![(String "abc", "", false), (Int None, "", true)];
+    /// let (s, n): (&str, Option<i64>) = mock.as_process().extract().unwrap();
+    /// assert_eq!(s, "abc");
+    /// assert_eq!(n, None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`UdfArgs::extract`]
+    #[inline]
+    pub fn extract<T: UdfArgs<'a>>(&'a self) -> Result<T, ProcessError> {
+        T::extract(self)
+    }
+}
+
+macro_rules! impl_udf_args {
+    ($count:literal; $($idx:tt $ty:ident),*) => {
+        impl<'a, $($ty: FromSqlArg<'a>),*> UdfArgs<'a> for ($($ty,)*) {
+            const COUNT: usize = $count;
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn validate(args: &ArgList<Init>) -> Result<(), String> {
+                check_arg_count(Self::COUNT, args.len())?;
+                $( set_coercion::<$ty>(args, $idx); )*
+                Ok(())
+            }
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn extract(args: &'a ArgList<'a, Process>) -> Result<Self, ProcessError> {
+                Ok(($( extract_one::<$ty>(args, $idx)?, )*))
+            }
+        }
+    };
+}
+
+impl_udf_args!(0;);
+impl_udf_args!(1; 0 A);
+impl_udf_args!(2; 0 A, 1 B);
+impl_udf_args!(3; 0 A, 1 B, 2 C);
+impl_udf_args!(4; 0 A, 1 B, 2 C, 3 D);
+impl_udf_args!(5; 0 A, 1 B, 2 C, 3 D, 4 E);
+impl_udf_args!(6; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_udf_args!(7; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_udf_args!(8; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+impl_udf_args!(9; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I);
+impl_udf_args!(10; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J);
+impl_udf_args!(11; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K);
+impl_udf_args!(12; 0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockArg, MockArgData, MockArgList};
+    use crate::wrapper::check_arg_range;
+    use crate::{mock_args, ProcessErrorKind};
+
+    #[test]
+    fn test_validate_count() {
+        let mut args = mock_args![(Int None, "a", false)];
+        assert_eq!(
+            <(i64, f64)>::validate(args.as_init()),
+            Err("Expected 2 arguments; got 1".to_owned())
+        );
+
+        let mut args = MockArgList::new();
+        assert_eq!(
+            <(&str,)>::validate(args.as_init()),
+            Err("Expected 1 argument; got 0".to_owned())
+        );
+        assert_eq!(<()>::validate(args.as_init()), Ok(()));
+    }
+
+    #[test]
+    fn test_arg_range() {
+        assert_eq!(check_arg_range(1, Some(1), 1), Ok(()));
+        assert_eq!(check_arg_range(0, Some(2), 2), Ok(()));
+        assert_eq!(check_arg_range(2, None, 5), Ok(()));
+        assert_eq!(
+            check_arg_range(1, Some(1), 2),
+            Err("Expected 1 argument; got 2".to_owned())
+        );
+        assert_eq!(
+            check_arg_range(0, Some(1), 2),
+            Err("Expected at most 1 argument; got 2".to_owned())
+        );
+        assert_eq!(
+            check_arg_range(1, Some(3), 0),
+            Err("Expected 1 to 3 arguments; got 0".to_owned())
+        );
+        assert_eq!(
+            check_arg_range(2, None, 1),
+            Err("Expected at least 2 arguments; got 1".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_validate_coercion() {
+        let mut args = mock_args![
+            (Int None, "a", false),
+            (Int None, "b", false),
+            (Real None, "c", false),
+        ];
+        let init = args.as_init();
+        <(String, Option<f64>, SqlResult)>::validate(init).unwrap();
+
+        let types: Vec<_> = init.iter().map(|a| a.get_type_coercion()).collect();
+        assert_eq!(types, [SqlType::String, SqlType::Real, SqlType::Real]);
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut args = mock_args![
+            (String "foo", "a", false),
+            (Int 10, "b", false),
+            (Real None, "c", true),
+            (Decimal "1.23", "d", false),
+        ];
+        let (a, b, c, d): (&str, i64, Option<f64>, String) = args.as_process().extract().unwrap();
+        assert_eq!((a, b, c, d.as_str()), ("foo", 10, None, "1.23"));
+    }
+
+    #[test]
+    fn test_extract_decimal() {
+        let mut args = mock_args![
+            (Decimal "-1.50", "a", false),
+            (Decimal "2.125", "b", false),
+        ];
+        let (a, b): (Decimal<4, 2>, SqlDecimal) = args.as_process().extract().unwrap();
+        assert_eq!(a.unscaled(), -150);
+        assert_eq!(b.to_string(), "2.125");
+
+        // Too many decimal places
+        assert_eq!(
+            args.as_process()
+                .extract::<(Decimal<4, 2>, Decimal<4, 2>)>()
+                .unwrap_err()
+                .kind(),
+            ProcessErrorKind::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_extract_datetime() {
+        let mut args = mock_args![
+            (String "2024-01-31 12:30:00", "a", false),
+            (String "0000-00-00", "b", false),
+            (String "12:30:00", "c", false),
+        ];
+        let (a, b, c): (NaiveDateTime, MaybeZero<NaiveDate>, Duration) =
+            args.as_process().extract().unwrap();
+        assert_eq!(a.to_string(), "2024-01-31 12:30:00");
+        assert_eq!(b, MaybeZero::Zero);
+        assert_eq!(c, Duration::minutes(12 * 60 + 30));
+
+        // Zero dates need `MaybeZero`
+        assert_eq!(
+            args.as_process()
+                .extract::<(NaiveDateTime, NaiveDate, Duration)>()
+                .unwrap_err()
+                .kind(),
+            ProcessErrorKind::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        // `NULL` for a non-optional type
+        let mut args = mock_args![(Int None, "a", true)];
+        assert_eq!(
+            args.as_process()
+                .extract::<(i64,)>()
+                .unwrap_err()
+                .to_string(),
+            "Argument `a` must not be NULL"
+        );
+        assert_eq!(
+            args.as_process().extract::<(i64,)>().unwrap_err().kind(),
+            ProcessErrorKind::InvalidArgument
+        );
+
+        // Not coerced to the declared type
+        let mut args = mock_args![(String "abc", "price", false)];
+        assert_eq!(
+            args.as_process()
+                .extract::<(f64,)>()
+                .unwrap_err()
+                .to_string(),
+            "Argument `price` must be real"
+        );
+
+        // Invalid UTF-8
+        let mut args = MockArgList::from([MockArg::new(
+            MockArgData::Bytes(Some(vec![0xff])),
+            "a",
+            false,
+        )]);
+        assert_eq!(
+            args.as_process().extract::<(&str,)>().unwrap_err().kind(),
+            ProcessErrorKind::InvalidArgument
+        );
+        assert_eq!(
+            args.as_process().extract::<(&[u8],)>().unwrap(),
+            (&[0xffu8][..],)
+        );
+    }
+}
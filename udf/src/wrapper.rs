@@ -3,24 +3,61 @@
 //! Warning: This module should be considered unstable and generally not for
 //! public use
 
+mod arg_helpers;
 #[macro_use]
 mod const_helpers;
+#[cfg(feature = "strict-contracts")]
+pub(crate) mod contracts;
 mod functions;
 mod helpers;
+mod meta;
 mod modded_types;
 mod process;
+mod symbols;
 
 use std::str;
 
+pub use arg_helpers::{
+    check_arg_count, check_arg_range, check_const, check_min_arg_count, extract_one,
+    extract_optional, extract_rest, set_coercion, set_coercion_to, set_rest_coercion,
+};
 use const_helpers::{const_slice_eq, const_slice_to_str, const_str_eq};
-pub use functions::{wrap_add, wrap_clear, wrap_deinit, wrap_init, wrap_remove, BufConverter};
+pub use functions::{
+    wrap_add, wrap_clear, wrap_deinit, wrap_init, wrap_init_typed, wrap_remove, BufConverter,
+};
 pub(crate) use helpers::*;
+pub use meta::{aggregate_meta, aggregate_meta_len, basic_meta, basic_meta_len, META_TAG};
 pub use modded_types::UDF_ARGSx;
-pub use process::{
-    wrap_process_basic, wrap_process_basic_option, wrap_process_buf, wrap_process_buf_option,
-    wrap_process_buf_option_ref,
+pub use process::{wrap_process_buf, wrap_process_int, wrap_process_real};
+pub use symbols::{
+    AddFn, AggregateSymbols, BasicSymbols, ClearFn, DeinitFn, InitFn, ProcessBufFn, ProcessFn,
+    ProcessIntFn, ProcessRealFn,
 };
 
+#[cfg(feature = "metrics")]
+use crate::metrics::counters_by_name;
+#[cfg(feature = "metrics")]
+pub use crate::metrics::Counters;
+use crate::{BasicUdf, FromSqlArg, SqlType, ToSqlReturn};
+
+/// Stands in for the `metrics` counters without that feature, so
+/// `#[register]` output is the same either way
+#[cfg(not(feature = "metrics"))]
+#[derive(Debug, Default)]
+pub struct Counters;
+
+#[cfg(not(feature = "metrics"))]
+impl Counters {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+fn counters_by_name(_name: &'static str) -> &'static Counters {
+    &Counters
+}
+
 /// A trait implemented by the proc macro
 // FIXME: on unimplemented
 pub trait RegisteredBasicUdf {
@@ -30,6 +67,17 @@ pub trait RegisteredBasicUdf {
     const ALIASES: &'static [&'static str];
     /// True if `NAME` comes from the default value for the struct
     const DEFAULT_NAME_USED: bool;
+    /// Argument count limits, from `min_args`, `max_args` or `args(...)`
+    const ARG_RANGE: (usize, Option<usize>) = (0, None);
+    /// From `enforce_max_len`: string results longer than `max_len` are logged
+    /// and returned as `NULL`
+    const ENFORCE_MAX_LEN: bool = false;
+
+    /// Counters for the `metrics` feature. `#[register]` returns a static made
+    /// for this function; the default looks them up by name, which locks.
+    fn counters() -> &'static Counters {
+        counters_by_name(Self::NAME)
+    }
 }
 
 /// Implemented by the proc macro. This is used to enforce that the basic UDF and aggregate
@@ -123,5 +171,71 @@ const fn verify_aggregate_attribute_aliases<T: RegisteredAggregateUdf>() {
     panic!("{}", msg);
 }
 
+/// Enforce that the `SQL_TYPE` of a UDF's `Returns` matches the signature that
+/// `#[register]` chose. A `String` signature also accepts `Decimal`, since
+/// both use the same C function.
+pub const fn verify_return_type<U: BasicUdf + 'static>(sig: SqlType) {
+    let actual = <U::Returns<'static> as ToSqlReturn<'static>>::SQL_TYPE;
+
+    if actual as i8 == sig as i8
+        || (sig as i8 == SqlType::String as i8 && actual as i8 == SqlType::Decimal as i8)
+    {
+        return;
+    }
+
+    let mut msg_buf = [0u8; 512];
+    let mut curs = 0;
+    curs += const_write_all!(
+        msg_buf,
+        [
+            "`Returns` has SQL type `",
+            actual.display_name(),
+            "` but `#[register]` created a `",
+            sig.display_name(),
+            "` function; specify `#[register(returns = \"",
+            actual.display_name(),
+            "\")]`",
+        ],
+        curs
+    );
+
+    let msg = const_slice_to_str(msg_buf.as_slice(), curs);
+    panic!("{}", msg);
+}
+
+/// Enforce that an argument's `coerce` type can be read by its Rust type.
+/// Types without a `SQL_TYPE` accept any value, and string types also accept
+/// `decimal`, which is passed as text.
+pub const fn verify_coercion<T: FromSqlArg<'static>>(coerce: SqlType, arg: &str) {
+    let Some(expected) = T::SQL_TYPE else {
+        return;
+    };
+
+    if expected as i8 == coerce as i8
+        || (expected as i8 == SqlType::String as i8 && coerce as i8 == SqlType::Decimal as i8)
+    {
+        return;
+    }
+
+    let mut msg_buf = [0u8; 512];
+    let mut curs = 0;
+    curs += const_write_all!(
+        msg_buf,
+        [
+            "argument `",
+            arg,
+            "` is coerced to `",
+            coerce.display_name(),
+            "` but its type reads `",
+            expected.display_name(),
+            "` values; change the type or remove `coerce`",
+        ],
+        curs
+    );
+
+    let msg = const_slice_to_str(msg_buf.as_slice(), curs);
+    panic!("{}", msg);
+}
+
 #[cfg(test)]
 mod tests;
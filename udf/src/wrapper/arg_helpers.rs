@@ -0,0 +1,140 @@
+//! Helpers used by typed argument signatures, both within this crate and from
+//! code generated by `#[register(args(...))]` and `#[derive(UdfArgs)]`
+
+use std::any::type_name;
+
+use crate::{
+    ArgList, FromSqlArg, Init, Process, ProcessError, ProcessErrorKind, SqlResult, SqlType,
+};
+
+/// Create the standard error message for a wrong argument count
+#[inline]
+pub fn check_arg_count(expected: usize, got: usize) -> Result<(), String> {
+    if expected == got {
+        return Ok(());
+    }
+    let plural = if expected == 1 { "" } else { "s" };
+    Err(format!("Expected {expected} argument{plural}; got {got}"))
+}
+
+/// Create the standard error message for too few arguments to a variadic
+/// signature
+#[inline]
+pub fn check_min_arg_count(min: usize, got: usize) -> Result<(), String> {
+    if got >= min {
+        return Ok(());
+    }
+    let plural = if min == 1 { "" } else { "s" };
+    Err(format!(
+        "Expected at least {min} argument{plural}; got {got}"
+    ))
+}
+
+/// Create the standard error message for an argument count outside of
+/// `min..=max` (`max` is unbounded if `None`)
+#[inline]
+pub fn check_arg_range(min: usize, max: Option<usize>, got: usize) -> Result<(), String> {
+    match max {
+        None => check_min_arg_count(min, got),
+        Some(max) if max == min => check_arg_count(min, got),
+        Some(max) if (min..=max).contains(&got) => Ok(()),
+        Some(max) if min == 0 => {
+            let plural = if max == 1 { "" } else { "s" };
+            Err(format!(
+                "Expected at most {max} argument{plural}; got {got}"
+            ))
+        }
+        Some(max) => Err(format!("Expected {min} to {max} arguments; got {got}")),
+    }
+}
+
+/// Verify that the argument at `index` is a constant. The error names the
+/// argument by its attribute, like errors from [`extract_one`].
+#[inline]
+pub fn check_const(args: &ArgList<Init>, index: usize) -> Result<(), String> {
+    match args.get(index) {
+        Some(arg) if arg.is_const() => Ok(()),
+        Some(arg) => Err(format!("Argument `{}` must be a constant", arg.attribute())),
+        None => Err(format!("Argument {index} is missing")),
+    }
+}
+
+/// Set the coercion for a single argument to the type's preferred type
+#[inline]
+pub fn set_coercion<'a, T: FromSqlArg<'a>>(args: &ArgList<Init>, index: usize) {
+    if let Some(ty) = T::SQL_TYPE {
+        set_coercion_to(args, index, ty);
+    }
+}
+
+/// Set the coercion for a single argument to a specific type
+#[inline]
+pub fn set_coercion_to(args: &ArgList<Init>, index: usize, ty: SqlType) {
+    if let Some(mut arg) = args.get(index) {
+        arg.set_type_coercion(ty);
+    }
+}
+
+/// Set the coercion for all arguments starting at `start`
+#[inline]
+pub fn set_rest_coercion<'a, T: FromSqlArg<'a>>(args: &ArgList<Init>, start: usize) {
+    (start..args.len()).for_each(|i| set_coercion::<T>(args, i));
+}
+
+/// Read a single argument
+///
+/// Errors name the argument by its attribute (the column name or expression
+/// text), e.g. "Argument `price` must be real".
+#[inline]
+pub fn extract_one<'a, T: FromSqlArg<'a>>(
+    args: &'a ArgList<'a, Process>,
+    index: usize,
+) -> Result<T, ProcessError> {
+    let Some(arg) = args.get(index) else {
+        return Err(ProcessError::msg(format!("Argument {index} is missing"))
+            .with_kind(ProcessErrorKind::InvalidArgument));
+    };
+    let value = arg.value();
+    let is_null = value.is_null();
+    let actual = SqlType::try_from(&value).ok();
+
+    T::from_sql(value).ok_or_else(|| {
+        let attr = arg.attribute();
+        let msg = match T::SQL_TYPE {
+            _ if is_null => format!("Argument `{attr}` must not be NULL"),
+            Some(ty) if Some(ty) != actual => {
+                format!("Argument `{attr}` must be {}", ty.display_name())
+            }
+            _ => format!(
+                "Argument `{attr}` could not be converted to `{}`",
+                type_name::<T>()
+            ),
+        };
+        ProcessError::msg(msg).with_kind(ProcessErrorKind::InvalidArgument)
+    })
+}
+
+/// Read an argument that may be left out, which is treated like `NULL`. Only
+/// used for `Option<T>` types, after `min_args`.
+#[inline]
+pub fn extract_optional<'a, T: FromSqlArg<'a>>(
+    args: &'a ArgList<'a, Process>,
+    index: usize,
+) -> Result<T, ProcessError> {
+    if index < args.len() {
+        return extract_one(args, index);
+    }
+    T::from_sql(SqlResult::Int(None)).ok_or_else(|| {
+        ProcessError::msg(format!("Argument {index} is missing"))
+            .with_kind(ProcessErrorKind::InvalidArgument)
+    })
+}
+
+/// Read all arguments starting at `start`
+#[inline]
+pub fn extract_rest<'a, T: FromSqlArg<'a>>(
+    args: &'a ArgList<'a, Process>,
+    start: usize,
+) -> Result<Vec<T>, ProcessError> {
+    (start..args.len()).map(|i| extract_one(args, i)).collect()
+}
@@ -6,10 +6,10 @@ use std::str;
 macro_rules! const_arr_copy {
     ($dst:expr, $src:expr, $start:expr) => {{
         let max_idx = $dst.len() - $start;
-        let (to_write, add_ellipsis) = if $src.len() <= (max_idx.saturating_sub($start)) {
+        let (to_write, add_ellipsis) = if $src.len() <= max_idx {
             ($src.len(), false)
         } else {
-            ($src.len().saturating_sub(4), true)
+            (max_idx.saturating_sub(3), true)
         };
 
         let mut i = 0;
@@ -115,6 +115,13 @@ mod tests {
         let written = const_arr_copy!(x, b"foobar", 0);
         let s = const_slice_to_str(x.as_slice(), written);
         assert_eq!(s, "fo...");
+
+        // Exactly filling the buffer at an offset
+        let mut x = [0u8; 9];
+        let w1 = const_arr_copy!(x, b"foo", 0);
+        let w2 = const_arr_copy!(x, b"barbaz", w1);
+        let s = const_slice_to_str(x.as_slice(), w1 + w2);
+        assert_eq!(s, "foobarbaz");
     }
 
     #[test]
@@ -0,0 +1,113 @@
+//! Checks that `process` results keep the promises made in `init`, enabled
+//! with the `strict-contracts` feature
+//!
+//! The server sizes its result column from `max_length` and `decimals`, and
+//! may skip `NULL` handling if `maybe_null` is false. A result that breaks
+//! these is truncated or misread without any error, so with this feature the
+//! wrapper turns it into an error instead.
+
+use std::cell::RefCell;
+
+use super::helpers::LogLimiter;
+use super::RegisteredBasicUdf;
+use crate::types::FmtUnscaled;
+use crate::{Process, SqlReturn, UdfCfg};
+
+/// Violations are logged at most 10 times per minute, shared by all UDFs in
+/// the library
+static CONTRACT_LOG: LogLimiter = LogLimiter::new(10, 60);
+
+thread_local! {
+    /// The most recent violation on this thread, for `mock` to report
+    static LAST_VIOLATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Check a result against `maybe_null`, `max_len` and `decimals`, returning a
+/// description of the first problem
+pub fn check_result(ret: &SqlReturn, cfg: &UdfCfg<Process>) -> Result<(), String> {
+    let max_len = cfg.get_max_len();
+    match *ret {
+        SqlReturn::Null if !cfg.get_maybe_null() => {
+            Err("returned NULL, but `maybe_null` is false".to_owned())
+        }
+        SqlReturn::Bytes(ref b) if b.len() as u64 > max_len => Err(format!(
+            "returned {} bytes, but `max_len` is {max_len}",
+            b.len()
+        )),
+        SqlReturn::Decimal { unscaled, scale } => {
+            let decimals = cfg.get_decimals();
+            if u32::from(scale) > decimals {
+                return Err(format!(
+                    "returned a decimal with {scale} digits after the point, but `decimals` is {decimals}"
+                ));
+            }
+            let len = FmtUnscaled(unscaled, scale).to_string().len();
+            if len as u64 > max_len {
+                return Err(format!(
+                    "returned a decimal of {len} characters, but `max_len` is {max_len}"
+                ));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Log a violation, subject to rate limiting, and record it for `mock`
+pub fn report_violation<U: RegisteredBasicUdf>(msg: String) {
+    if let Some(suppressed) = CONTRACT_LOG.check() {
+        if suppressed > 0 {
+            udf_log!(Warning: "{suppressed} contract violations were not logged due to rate limiting");
+        }
+        udf_log!(Error: "'{}' broke its contract: {msg}", U::NAME);
+    }
+    LAST_VIOLATION.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+/// Take the most recent violation on this thread, if any
+pub fn take_violation() -> Option<String> {
+    LAST_VIOLATION.with(|last| last.borrow_mut().take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockUdfCfg;
+
+    #[test]
+    fn test_check_null() {
+        let mut m = MockUdfCfg::new();
+        *m.maybe_null() = false;
+        assert!(check_result(&SqlReturn::Null, m.as_process()).is_err());
+        assert!(check_result(&SqlReturn::Int(1), m.as_process()).is_ok());
+
+        *m.maybe_null() = true;
+        assert!(check_result(&SqlReturn::Null, m.as_process()).is_ok());
+    }
+
+    #[test]
+    fn test_check_len() {
+        let mut m = MockUdfCfg::new();
+        *m.max_len() = 3;
+        let ok = SqlReturn::Bytes(b"abc".as_slice().into());
+        let long = SqlReturn::Bytes(b"abcd".as_slice().into());
+        assert!(check_result(&ok, m.as_process()).is_ok());
+        assert_eq!(
+            check_result(&long, m.as_process()).unwrap_err(),
+            "returned 4 bytes, but `max_len` is 3"
+        );
+    }
+
+    #[test]
+    fn test_check_decimal() {
+        let mut m = MockUdfCfg::new();
+        *m.max_len() = 7;
+        *m.decimals() = 2;
+        let dec = |unscaled, scale| SqlReturn::Decimal { unscaled, scale };
+
+        assert!(check_result(&dec(-12345, 2), m.as_process()).is_ok());
+        assert!(check_result(&dec(12345, 3), m.as_process()).is_err());
+        // "-1234.56" is 8 characters
+        assert!(check_result(&dec(-123_456, 2), m.as_process()).is_err());
+    }
+}
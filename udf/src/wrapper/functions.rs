@@ -8,43 +8,42 @@ use std::num::NonZeroU8;
 
 use udf_sys::{UDF_ARGS, UDF_INIT};
 
-use crate::wrapper::write_msg_to_buf;
-use crate::{AggregateUdf, ArgList, BasicUdf, Process, UdfCfg, MYSQL_ERRMSG_SIZE};
+use crate::wrapper::{catch_user_panic, check_arg_range, write_msg_to_buf, RegisteredBasicUdf};
+use crate::{
+    AggregateUdf, ArgList, BasicUdf, Init, Process, ToSqlReturn, TypedArgs, UdfCfg,
+    MYSQL_ERRMSG_SIZE,
+};
 
-/// A wrapper that lets us handle return types when the user returns an
-/// allocated buffer (rather than a reference). We wrap the user's type within
-/// this struct and need to be sure to negotiate correctly
+/// Error value set for aggregate functions if a panic is caught
+const PANIC_ERROR: NonZeroU8 = match NonZeroU8::new(1) {
+    Some(v) => v,
+    None => unreachable!(),
+};
+
+/// A wrapper that lets us handle owned buffer return types
+///
+/// We wrap the user's type within this struct and keep a result that does not
+/// fit in the server's buffer here, so it stays valid until the next call.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct BufConverter<U, B>
+pub struct BufConverter<U>
 where
     U: BasicUdf,
-    B: Default,
 {
     udf: U,
-    buf: B,
-}
-
-impl<U, B> BufConverter<U, B>
-where
-    U: BasicUdf,
-    B: Default,
-{
-    #[allow(dead_code)]
-    fn set_retval(&mut self, val: B) {
-        self.buf = val;
-    }
+    buf: Vec<u8>,
 }
 
 /// Trait to allow interfacing a buffered or plain type
 pub trait UdfConverter<U> {
     fn as_mut_ref(&mut self) -> &mut U;
     fn into_storable(source: U) -> Self;
+    /// Get the UDF and the result buffer, if there is one
+    fn as_mut_parts(&mut self) -> (&mut U, Option<&mut Vec<u8>>);
 }
 
-impl<U, B> UdfConverter<U> for BufConverter<U, B>
+impl<U> UdfConverter<U> for BufConverter<U>
 where
     U: BasicUdf,
-    B: Default,
 {
     fn as_mut_ref(&mut self) -> &mut U {
         &mut self.udf
@@ -53,9 +52,13 @@ where
     fn into_storable(source: U) -> Self {
         Self {
             udf: source,
-            buf: B::default(),
+            buf: Vec::new(),
         }
     }
+
+    fn as_mut_parts(&mut self) -> (&mut U, Option<&mut Vec<u8>>) {
+        (&mut self.udf, Some(&mut self.buf))
+    }
 }
 
 impl<U: BasicUdf> UdfConverter<U> for U {
@@ -66,6 +69,10 @@ impl<U: BasicUdf> UdfConverter<U> for U {
     fn into_storable(source: U) -> Self {
         source
     }
+
+    fn as_mut_parts(&mut self) -> (&mut U, Option<&mut Vec<u8>>) {
+        (self, None)
+    }
 }
 
 /// This function provides the same signature as the C FFI expects. It is used
@@ -87,9 +94,8 @@ impl<U: BasicUdf> UdfConverter<U> for U {
 ///
 /// # Panics
 ///
-/// - Panics if the error message contains "\0", or if the message is too long (
-///   greater than 511 bytes).
-/// - Panics if the provides error message string contains null characters
+/// Panics in the user's `init` function are caught and reported as an error
+/// message, rather than unwinding into the server.
 ///
 /// # Interface
 ///
@@ -100,24 +106,71 @@ impl<U: BasicUdf> UdfConverter<U> for U {
 ///   function is called. (handled by `U::init`)
 /// - To allocate any memory required by the main function. (We box our struct
 ///   for this)
-/// - To specify the maximum length of the result
+/// - To specify the maximum length of the result (defaults may be set by
+///   [`ToSqlReturn::set_init_defaults`])
 /// - To specify (for REAL functions) the maximum number of decimal places in
 ///   the result.
 /// - To specify whether the result can be NULL. (handled by proc macro based on
 ///   `Returns`)
 #[inline]
-pub unsafe fn wrap_init<W: UdfConverter<U>, U: BasicUdf>(
+pub unsafe fn wrap_init<W: UdfConverter<U>, U: BasicUdf + RegisteredBasicUdf + 'static>(
+    initid: *mut UDF_INIT,
+    args: *mut UDF_ARGS,
+    message: *mut c_char,
+) -> bool {
+    init_with_check::<W, U>(initid, args, message, |_| Ok(()))
+}
+
+/// The same as [`wrap_init`], but first checks the argument count and sets
+/// coercions for a signature declared with `#[register(args(...))]`. The
+/// user's `init` is not called if this check fails.
+#[inline]
+pub unsafe fn wrap_init_typed<
+    W: UdfConverter<U>,
+    U: BasicUdf + RegisteredBasicUdf + TypedArgs + 'static,
+>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
     message: *mut c_char,
+) -> bool {
+    init_with_check::<W, U>(initid, args, message, U::validate_args)
+}
+
+/// Shared implementation of `init`, running `check` before the user's function
+unsafe fn init_with_check<W: UdfConverter<U>, U: BasicUdf + RegisteredBasicUdf + 'static>(
+    initid: *mut UDF_INIT,
+    args: *mut UDF_ARGS,
+    message: *mut c_char,
+    check: fn(&ArgList<Init>) -> Result<(), String>,
 ) -> bool {
     log_call!(enter: "init", U, args, message);
 
     let cfg = UdfCfg::from_raw_ptr(initid);
     let arglist = ArgList::from_raw_ptr(args);
 
-    // Call the user's init function
-    let init_res = U::init(cfg, arglist);
+    // Defaults for the return type, which the user's `init` may override
+    <U::Returns<'static> as ToSqlReturn<'static>>::set_init_defaults(cfg);
+
+    // Check the argument count and any signature, then call the user's init
+    // function, turning any panic into an error message
+    let (min_args, max_args) = U::ARG_RANGE;
+    let init_res = check_arg_range(min_args, max_args, arglist.len())
+        .and_then(|()| check(arglist))
+        .and_then(|()| {
+            catch_user_panic::<U, _, _>("init", || U::init(cfg, arglist)).unwrap_or_else(|msg| {
+                #[cfg(feature = "metrics")]
+                crate::metrics::counters::<U>().record_panic();
+                Err(format!("init panicked: {msg}"))
+            })
+        });
+
+    #[cfg(feature = "metrics")]
+    crate::metrics::counters::<U>().record_init(init_res.is_ok());
+
+    // Results over `max_len` are returned as `NULL`
+    if U::ENFORCE_MAX_LEN {
+        cfg.set_maybe_null(true);
+    }
 
     // Apply any pending coercions
     arglist.flush_all_coercions();
@@ -129,7 +182,7 @@ pub unsafe fn wrap_init<W: UdfConverter<U>, U: BasicUdf>(
             // set the `initid` struct to contain our struct
             // SAFETY: must be cleaned up in deinit function, or we will leak!
             let boxed_struct: Box<W> = Box::new(W::into_storable(v));
-            cfg.store_box(boxed_struct);
+            cfg.store_box::<U, _>(boxed_struct);
             false
         }
         Err(e) => {
@@ -147,19 +200,32 @@ pub unsafe fn wrap_init<W: UdfConverter<U>, U: BasicUdf>(
 /// value on the stack. The function ends, it goes out of scope and gets
 /// dropped.
 ///
-/// There is no specific wrapped function here
+/// There is no specific wrapped function here, but a user `Drop` implementation
+/// may still panic so we guard against that.
 #[inline]
-pub unsafe fn wrap_deinit<W: UdfConverter<U>, U: BasicUdf>(initid: *const UDF_INIT) {
+pub unsafe fn wrap_deinit<W: UdfConverter<U>, U: BasicUdf + RegisteredBasicUdf>(
+    initid: *const UDF_INIT,
+) {
     log_call!(enter: "deinit", U, &*initid);
 
     // SAFETY: we constructed this box so it is formatted correctly
     // caller ensures validity of initid
     let cfg: &UdfCfg<Process> = UdfCfg::from_raw_ptr(initid);
-    cfg.retrieve_box::<W>();
+    let b = cfg.retrieve_box::<U, W>();
+
+    // Nothing else to do on panic, it has already been logged
+    let res = catch_user_panic::<U, _, _>("deinit", || drop(b));
+
+    #[cfg(feature = "metrics")]
+    if res.is_err() {
+        crate::metrics::counters::<U>().record_panic();
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = res;
 }
 
 #[inline]
-pub unsafe fn wrap_add<W: UdfConverter<U>, U: AggregateUdf>(
+pub unsafe fn wrap_add<W: UdfConverter<U>, U: AggregateUdf + RegisteredBasicUdf>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
     _is_null: *mut c_uchar,
@@ -170,9 +236,10 @@ pub unsafe fn wrap_add<W: UdfConverter<U>, U: AggregateUdf>(
     let cfg = UdfCfg::from_raw_ptr(initid);
     let arglist = ArgList::from_raw_ptr(args);
     let err = *(error as *const Option<NonZeroU8>);
-    let mut b = cfg.retrieve_box::<W>();
-    let res = U::add(b.as_mut_ref(), cfg, arglist, err);
-    cfg.store_box(b);
+    let mut b = cfg.retrieve_box::<U, W>();
+    let res = catch_user_panic::<U, _, _>("add", || U::add(b.as_mut_ref(), cfg, arglist, err))
+        .unwrap_or_else(|_| panic_error::<U>());
+    cfg.store_box::<U, _>(b);
 
     if let Err(e) = res {
         *error = e.into();
@@ -180,7 +247,7 @@ pub unsafe fn wrap_add<W: UdfConverter<U>, U: AggregateUdf>(
 }
 
 #[inline]
-pub unsafe fn wrap_clear<W: UdfConverter<U>, U: AggregateUdf>(
+pub unsafe fn wrap_clear<W: UdfConverter<U>, U: AggregateUdf + RegisteredBasicUdf>(
     initid: *mut UDF_INIT,
     _is_null: *mut c_uchar,
     error: *mut c_uchar,
@@ -189,17 +256,18 @@ pub unsafe fn wrap_clear<W: UdfConverter<U>, U: AggregateUdf>(
 
     let cfg = UdfCfg::from_raw_ptr(initid);
     let err = *(error as *const Option<NonZeroU8>);
-    let mut b = cfg.retrieve_box::<W>();
-    let res = U::clear(b.as_mut_ref(), cfg, err);
-    cfg.store_box(b);
+    let mut b = cfg.retrieve_box::<U, W>();
+    let res = catch_user_panic::<U, _, _>("clear", || U::clear(b.as_mut_ref(), cfg, err))
+        .unwrap_or_else(|_| panic_error::<U>());
+    cfg.store_box::<U, _>(b);
 
-    if let Err(e) = res {
-        *error = e.into();
-    }
+    // The server keeps the flag across groups, so this is the only place it
+    // can be reset. `add` and `remove` only ever set it.
+    *error = res.err().map_or(0, NonZeroU8::get);
 }
 
 #[inline]
-pub unsafe fn wrap_remove<W: UdfConverter<U>, U: AggregateUdf>(
+pub unsafe fn wrap_remove<W: UdfConverter<U>, U: AggregateUdf + RegisteredBasicUdf>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
     _is_null: *mut c_uchar,
@@ -210,11 +278,22 @@ pub unsafe fn wrap_remove<W: UdfConverter<U>, U: AggregateUdf>(
     let cfg = UdfCfg::from_raw_ptr(initid);
     let arglist = ArgList::from_raw_ptr(args);
     let err = *(error as *const Option<NonZeroU8>);
-    let mut b = cfg.retrieve_box::<W>();
-    let res = U::remove(b.as_mut_ref(), cfg, arglist, err);
-    cfg.store_box(b);
+    let mut b = cfg.retrieve_box::<U, W>();
+    let res =
+        catch_user_panic::<U, _, _>("remove", || U::remove(b.as_mut_ref(), cfg, arglist, err))
+            .unwrap_or_else(|_| panic_error::<U>());
+    cfg.store_box::<U, _>(b);
 
     if let Err(e) = res {
         *error = e.into();
     }
 }
+
+/// The error for a panic caught in an aggregate function, which is also counted
+/// with the `metrics` feature
+#[cfg_attr(not(feature = "metrics"), allow(clippy::extra_unused_type_parameters))]
+fn panic_error<U: RegisteredBasicUdf>() -> Result<(), NonZeroU8> {
+    #[cfg(feature = "metrics")]
+    crate::metrics::counters::<U>().record_panic();
+    Err(PANIC_ERROR)
+}
@@ -1,10 +1,15 @@
 //! Private module that handles the implementation of the wrapper module
 
+use std::any::Any;
+use std::cell::Cell;
 use std::cmp::min;
 use std::ffi::{c_char, c_ulong};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::udf_log;
+use super::RegisteredBasicUdf;
 
 /// Write a string message to a buffer. Accepts a const generic size `N` that
 /// length of the message will check against (N must be the size of the buffer)
@@ -25,6 +30,103 @@ pub unsafe fn write_msg_to_buf<const N: usize>(msg: &[u8], buf: *mut c_char) {
     }
 }
 
+thread_local! {
+    /// Number of panics caught on this thread, so tests can tell that one
+    /// happened even though the server only sees an error
+    static CAUGHT_PANICS: Cell<u32> = const { Cell::new(0) };
+}
+
+/// The number of panics caught by [`catch_user_panic`] on this thread
+#[cfg(feature = "arbitrary")]
+pub fn caught_panics() -> u32 {
+    CAUGHT_PANICS.with(Cell::get)
+}
+
+/// Run a user-provided function, catching any unwinding panics so they do not
+/// cross the FFI boundary into the server.
+///
+/// If the function panics, the payload is logged and returned as a string in
+/// the `Err` variant. `U`'s registered name and `phase` are used for output
+/// formatting, and with the `log` feature they are the call context for the
+/// logger.
+///
+/// Note that this cannot do anything if the library is built with `panic =
+/// "abort"`.
+pub fn catch_user_panic<U, R, F>(phase: &'static str, f: F) -> Result<R, String>
+where
+    U: RegisteredBasicUdf + ?Sized,
+    F: FnOnce() -> R,
+{
+    let udf_name = U::NAME;
+    #[cfg(feature = "log")]
+    let _scope = crate::logger::CallScope::enter(udf_name, U::ALIASES, phase);
+
+    // `AssertUnwindSafe` is OK here: callers either discard or restore any
+    // state that may have been observed mid-panic
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        CAUGHT_PANICS.with(|count| count.set(count.get().wrapping_add(1)));
+        let msg = panic_message(payload.as_ref()).to_owned();
+        udf_log!(Critical: "caught panic in {phase} for '{udf_name}': {msg}");
+        msg
+    })
+}
+
+/// Get a printable message from a panic payload
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+/// Limit how often a message is logged, allowing `burst` messages per
+/// `interval_secs` seconds
+pub struct LogLimiter {
+    burst: u32,
+    interval_secs: u64,
+    window_start: AtomicU64,
+    count: AtomicU32,
+}
+
+impl LogLimiter {
+    pub const fn new(burst: u32, interval_secs: u64) -> Self {
+        Self {
+            burst,
+            interval_secs,
+            window_start: AtomicU64::new(0),
+            count: AtomicU32::new(0),
+        }
+    }
+
+    /// Check whether a message may be logged now. If so, return the number of
+    /// messages that were suppressed in the previous interval.
+    pub fn check(&self) -> Option<u32> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.check_at(now)
+    }
+
+    fn check_at(&self, now: u64) -> Option<u32> {
+        let start = self.window_start.load(Ordering::Relaxed);
+        if now.saturating_sub(start) >= self.interval_secs
+            && self
+                .window_start
+                .compare_exchange(start, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let prev = self.count.swap(1, Ordering::Relaxed);
+            return Some(prev.saturating_sub(self.burst));
+        }
+
+        let count = self.count.fetch_add(1, Ordering::Relaxed);
+        (count < self.burst).then_some(0)
+    }
+}
+
 /// Data that is only relevant to buffer return types
 pub struct BufOptions {
     res_buf: *mut c_char,
@@ -110,6 +212,31 @@ mod tests {
         };
     }
 
+    #[test]
+    fn catch_panic_payloads() {
+        struct Test;
+
+        impl RegisteredBasicUdf for Test {
+            const NAME: &'static str = "test";
+            const ALIASES: &'static [&'static str] = &[];
+            const DEFAULT_NAME_USED: bool = false;
+        }
+
+        assert_eq!(catch_user_panic::<Test, _, _>("test", || 10), Ok(10));
+        assert_eq!(
+            catch_user_panic::<Test, _, _>("test", || panic!("static message")),
+            Err("static message".to_owned())
+        );
+        assert_eq!(
+            catch_user_panic::<Test, _, _>("test", || panic!("formatted {}", 10)),
+            Err("formatted 10".to_owned())
+        );
+        assert_eq!(
+            catch_user_panic::<Test, _, _>("test", || panic::panic_any(10u32)),
+            Err("Box<dyn Any>".to_owned())
+        );
+    }
+
     #[test]
     fn argtype_from_ptr_null() {
         // Just test null pointers here
@@ -297,4 +424,17 @@ mod buffer_tests {
     //     assert_eq!(len, 0);
     //     assert_eq!(res, None);
     // }
+
+    #[test]
+    fn test_log_limiter() {
+        let limiter = LogLimiter::new(2, 60);
+        assert_eq!(limiter.check_at(1000), Some(0));
+        assert_eq!(limiter.check_at(1010), Some(0));
+        assert_eq!(limiter.check_at(1020), None);
+        assert_eq!(limiter.check_at(1059), None);
+        // A new interval reports what was skipped
+        assert_eq!(limiter.check_at(1060), Some(2));
+        assert_eq!(limiter.check_at(1061), Some(0));
+        assert_eq!(limiter.check_at(1200), Some(0));
+    }
 }
@@ -0,0 +1,138 @@
+//! Function metadata that `#[register]` places in the library, so that the
+//! [`manifest`](crate::manifest) and tools such as `cargo udf` can find out
+//! which functions it provides
+//!
+//! Each registered implementation adds one record to the metadata section
+//! (see [`manifest`](crate::manifest) for its name). A record is a single line
+//! of space-separated `key=value` fields after a version tag:
+//!
+//! ```text
+//! udf1 kind=basic name=foo returns=int maybe_null=false version=0.1.0 alias=bar\n
+//! ```
+//!
+//! `kind` is `basic`, `aggregate` or `window`, and `returns` is the
+//! `display_name` of the result's [`SqlType`](crate::SqlType). An aggregate
+//! UDF has both a `basic` and an `aggregate` or `window` record. Records may be
+//! padded with NUL bytes.
+
+use super::{RegisteredAggregateUdf, RegisteredBasicUdf};
+use crate::{BasicUdf, ToSqlReturn};
+
+/// Start of each record, which includes the format version
+pub const META_TAG: &str = "udf1";
+
+/// Length of a record for a `BasicUdf`
+pub const fn basic_meta_len<U: BasicUdf + RegisteredBasicUdf + 'static>(version: &str) -> usize {
+    let fields = fields::<U>("basic", <U as RegisteredBasicUdf>::NAME, version);
+    record_len(&fields, <U as RegisteredBasicUdf>::ALIASES)
+}
+
+/// The record for a `BasicUdf`. `N` must be `basic_meta_len::<U>(version)`.
+pub const fn basic_meta<U: BasicUdf + RegisteredBasicUdf + 'static, const N: usize>(
+    version: &str,
+) -> [u8; N] {
+    let fields = fields::<U>("basic", <U as RegisteredBasicUdf>::NAME, version);
+    record(&fields, <U as RegisteredBasicUdf>::ALIASES)
+}
+
+/// Length of a record for an `AggregateUdf`. `window` is true if it
+/// implements `remove`.
+pub const fn aggregate_meta_len<U: BasicUdf + RegisteredAggregateUdf + 'static>(
+    version: &str,
+    window: bool,
+) -> usize {
+    let name = <U as RegisteredAggregateUdf>::NAME;
+    let fields = fields::<U>(aggregate_kind(window), name, version);
+    record_len(&fields, <U as RegisteredAggregateUdf>::ALIASES)
+}
+
+/// The record for an `AggregateUdf`. `N` must be
+/// `aggregate_meta_len::<U>(version, window)`.
+pub const fn aggregate_meta<U: BasicUdf + RegisteredAggregateUdf + 'static, const N: usize>(
+    version: &str,
+    window: bool,
+) -> [u8; N] {
+    let name = <U as RegisteredAggregateUdf>::NAME;
+    let fields = fields::<U>(aggregate_kind(window), name, version);
+    record(&fields, <U as RegisteredAggregateUdf>::ALIASES)
+}
+
+/// Aggregates that implement `remove` can be used as window functions
+const fn aggregate_kind(window: bool) -> &'static str {
+    if window {
+        "window"
+    } else {
+        "aggregate"
+    }
+}
+
+/// Every part of a record before the aliases
+const fn fields<'a, U: BasicUdf + 'static>(
+    kind: &'a str,
+    name: &'a str,
+    version: &'a str,
+) -> [&'a str; 11] {
+    let sql_type = <U::Returns<'static> as ToSqlReturn<'static>>::SQL_TYPE;
+    let maybe_null = <U::Returns<'static> as ToSqlReturn<'static>>::MAYBE_NULL;
+    [
+        META_TAG,
+        " kind=",
+        kind,
+        " name=",
+        name,
+        " returns=",
+        sql_type.display_name(),
+        " maybe_null=",
+        if maybe_null { "true" } else { "false" },
+        " version=",
+        version,
+    ]
+}
+
+const fn record_len(fields: &[&str], aliases: &[&str]) -> usize {
+    let mut len = "\n".len();
+    let mut i = 0;
+    while i < fields.len() {
+        len += fields[i].len();
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < aliases.len() {
+        len += " alias=".len() + aliases[i].len();
+        i += 1;
+    }
+    len
+}
+
+const fn record<const N: usize>(fields: &[&str], aliases: &[&str]) -> [u8; N] {
+    assert!(N == record_len(fields, aliases));
+
+    let mut buf = [0u8; N];
+    let mut curs = const_write_all!(buf, fields, 0);
+    let mut i = 0;
+    while i < aliases.len() {
+        curs += const_write_all!(buf, [" alias=", aliases[i]], curs);
+        i += 1;
+    }
+    let _ = const_write_all!(buf, ["\n"], curs);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        const FIELDS: [&str; 3] = [META_TAG, " kind=window", " name=avg_cost"];
+        const ALIASES: [&str; 2] = ["ac", "cost"];
+        const LEN: usize = record_len(&FIELDS, &ALIASES);
+        const REC: [u8; LEN] = record(&FIELDS, &ALIASES);
+
+        assert_eq!(
+            &REC,
+            b"udf1 kind=window name=avg_cost alias=ac alias=cost\n"
+        );
+    }
+}
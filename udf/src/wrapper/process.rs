@@ -1,163 +1,226 @@
 //! Functions related to strictly the `process` UDF components
+//!
+//! There is one wrapper per C signature. The user's result is converted with
+//! [`ToSqlReturn`], and the wrapper writes whichever [`SqlReturn`] it gets.
 
 #![allow(clippy::module_name_repetitions)]
-#![allow(clippy::option_if_let_else)]
 
-use std::ffi::{c_char, c_uchar, c_ulong};
+use std::borrow::Cow;
+use std::cmp::min;
+use std::ffi::{c_char, c_double, c_longlong, c_uchar, c_ulong};
+use std::io::Write;
 use std::num::NonZeroU8;
-use std::ptr;
+use std::{ptr, slice};
 
 use udf_sys::{UDF_ARGS, UDF_INIT};
 
 use super::functions::UdfConverter;
-use super::helpers::{buf_result_callback, BufOptions};
-use crate::{ArgList, BasicUdf, ProcessError, UdfCfg};
+use super::helpers::{buf_result_callback, catch_user_panic, BufOptions, LogLimiter};
+use super::RegisteredBasicUdf;
+use crate::types::FmtUnscaled;
+use crate::{ArgList, BasicUdf, ProcessError, SqlReturn, ToSqlReturn, UdfCfg};
 
-/// Callback for properly unwrapping and setting values for `Option<T>`
-///
-/// Returns `None` if the value is `Err` or `None`, `Some` otherwise
-#[inline]
-unsafe fn ret_callback_option<R>(
-    res: Result<Option<R>, ProcessError>,
-    error: *mut c_uchar,
-    is_null: *mut c_uchar,
-) -> Option<R> {
-    let transposed = res.transpose();
-
-    // Perform action for if internal is `None`
-    let Some(res_some) = transposed else {
-        // We have a None result
-        *is_null = 1;
-        return None;
-    };
+/// Errors from `process` are logged at most 10 times per minute, shared by
+/// all UDFs in the library
+static PROCESS_ERROR_LOG: LogLimiter = LogLimiter::new(10, 60);
 
-    // Rest of the behavior is in `ret_callback`
-    ret_callback(res_some, error, is_null)
-}
-
-/// Callback for properly unwrapping and setting values for any `T`
+/// Run the user's `process` function and convert its result
 ///
-/// Returns `None` if the value is `Err`, `Some` otherwise
+/// `f` receives the result, or `None` if `process` failed or panicked (`error`
+/// is already set in this case), and the result buffer of `W` if it has one.
+/// A string result longer than `max_len` is logged and returned as `NULL` if
+/// the UDF was registered with `enforce_max_len`; this is not an error, since
+/// the server would return `NULL` for every later row. With
+/// `strict-contracts`, any result that does not fit the settings from `init` is
+/// an error.
 #[inline]
-unsafe fn ret_callback<R>(
-    res: Result<R, ProcessError>,
-    error: *mut c_uchar,
-    is_null: *mut c_uchar,
-) -> Option<R> {
-    // Error case: set an error, and set length to 0 if applicable
-    let Ok(val) = res else {
-        *error = 1;
-        return None;
-    };
-
-    // Ok case: just return the desired value
-    *is_null = c_uchar::from(false);
-
-    Some(val)
-}
-
-/// Apply the `process` function for any implementation returning a nonbuffer type
-/// (`f64`, `i64`)
-#[inline]
-#[allow(clippy::let_and_return)]
-pub unsafe fn wrap_process_basic<W, U, R>(
+unsafe fn process_with<W, U, T>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
-    is_null: *mut c_uchar,
     error: *mut c_uchar,
-) -> R
+    f: impl FnOnce(Option<SqlReturn<'_>>, Option<&mut Vec<u8>>) -> T,
+) -> T
 where
     W: UdfConverter<U>,
-    for<'a> U: BasicUdf<Returns<'a> = R>,
-    R: Default + std::fmt::Debug,
+    U: BasicUdf + RegisteredBasicUdf,
 {
-    log_call!(enter: "process", U, &*initid, &*args, &*is_null, &*error);
-
     let cfg = UdfCfg::from_raw_ptr(initid);
     let arglist = ArgList::from_raw_ptr(args);
-    let mut b = cfg.retrieve_box::<W>();
     let err = *(error as *const Option<NonZeroU8>);
-    let proc_res = U::process(b.as_mut_ref(), cfg, arglist, err);
-    cfg.store_box(b);
+    let mut b = cfg.retrieve_box::<U, W>();
+    let (udf, res_buf) = b.as_mut_parts();
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+    let proc_res = catch_user_panic::<U, _, _>("process", move || {
+        U::process(udf, cfg, arglist, err).map(ToSqlReturn::into_sql_return)
+    });
+    #[cfg(feature = "metrics")]
+    let elapsed = start.elapsed();
+    #[cfg(feature = "metrics")]
+    if proc_res.is_err() {
+        crate::metrics::counters::<U>().record_panic();
+    }
+
+    // Panics have already been logged
+    let ret_val = match proc_res {
+        Ok(Ok(v)) => Some(v),
+        Ok(Err(e)) => {
+            log_process_error::<U>(&e);
+            None
+        }
+        Err(_) => None,
+    };
 
-    let ret = ret_callback(proc_res, error, is_null).unwrap_or_default();
-    log_call!(exit: "process", U, &*initid, &*args, &*is_null, &*error, ret);
+    let ret_val = ret_val.map(|v| match v {
+        SqlReturn::Bytes(ref b) if U::ENFORCE_MAX_LEN && b.len() as u64 > cfg.get_max_len() => {
+            let msg = format!(
+                "result of {} bytes exceeds `max_len` of {}",
+                b.len(),
+                cfg.get_max_len()
+            );
+            log_process_error::<U>(&ProcessError::msg(msg));
+            SqlReturn::Null
+        }
+        v => v,
+    });
+
+    #[cfg(feature = "strict-contracts")]
+    let ret_val = ret_val.and_then(|v| match super::contracts::check_result(&v, cfg) {
+        Ok(()) => Some(v),
+        Err(msg) => {
+            super::contracts::report_violation::<U>(msg);
+            None
+        }
+    });
+
+    if ret_val.is_none() {
+        *error = 1;
+    }
+
+    #[cfg(feature = "metrics")]
+    crate::metrics::counters::<U>().record_process(
+        match ret_val {
+            Some(SqlReturn::Null) => crate::metrics::Outcome::Null,
+            Some(SqlReturn::Error) | None => crate::metrics::Outcome::Error,
+            Some(_) => crate::metrics::Outcome::Value,
+        },
+        elapsed,
+    );
+
+    let ret = f(ret_val, res_buf);
+    cfg.store_box::<U, _>(b);
     ret
 }
 
-/// Apply the `process` function for any implementation returning an optional
-/// nonbuffer type (`Option<f64>`, `Option<i64>`)
+/// Log the reason `process` failed, subject to rate limiting
+fn log_process_error<U: RegisteredBasicUdf>(err: &ProcessError) {
+    let Some(suppressed) = PROCESS_ERROR_LOG.check() else {
+        return;
+    };
+    if suppressed > 0 {
+        udf_log!(Warning: "{suppressed} process errors were not logged due to rate limiting");
+    }
+    udf_log!(Error: "process failed for '{}': {err}", U::NAME);
+}
+
+/// A `ToSqlReturn` implementation gave a variant that doesn't match its
+/// `SQL_TYPE`
+unsafe fn unexpected_return<U: RegisteredBasicUdf>(ret: &SqlReturn, error: *mut c_uchar) {
+    udf_log!(
+        Error: "'{}' returned {ret:?}, which does not match its SQL type",
+        U::NAME
+    );
+    *error = 1;
+}
+
+/// Apply the `process` function for an implementation returning an integer
 #[inline]
 #[allow(clippy::let_and_return)]
-pub unsafe fn wrap_process_basic_option<W, U, R>(
+pub unsafe fn wrap_process_int<W, U>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
     is_null: *mut c_uchar,
     error: *mut c_uchar,
-) -> R
+) -> c_longlong
 where
     W: UdfConverter<U>,
-    for<'a> U: BasicUdf<Returns<'a> = Option<R>>,
-    R: Default + std::fmt::Debug,
+    U: BasicUdf + RegisteredBasicUdf,
 {
     log_call!(enter: "process", U, &*initid, &*args, &*is_null, &*error);
 
-    let cfg = UdfCfg::from_raw_ptr(initid);
-    let arglist = ArgList::from_raw_ptr(args);
-    let mut b = cfg.retrieve_box::<W>();
-    let err = *(error as *const Option<NonZeroU8>);
-    let proc_res = U::process(b.as_mut_ref(), cfg, arglist, err);
-    cfg.store_box(b);
+    let ret = process_with::<W, U, _>(initid, args, error, |res, _| match res {
+        Some(SqlReturn::Int(v)) => {
+            *is_null = 0;
+            v
+        }
+        Some(SqlReturn::Null) => {
+            *is_null = 1;
+            0
+        }
+        Some(SqlReturn::Error) => {
+            *error = 1;
+            0
+        }
+        Some(other) => {
+            unexpected_return::<U>(&other, error);
+            0
+        }
+        None => 0,
+    });
 
-    let ret = ret_callback_option(proc_res, error, is_null).unwrap_or_default();
     log_call!(exit: "process", U, &*initid, &*args, &*is_null, &*error, ret);
     ret
 }
 
-/// Apply the `process` function for any implementation returning a buffer type
-/// (`String`, `Vec<u8>`, `str`, `[u8]`)
+/// Apply the `process` function for an implementation returning a float
 #[inline]
-pub unsafe fn wrap_process_buf<W, U>(
+#[allow(clippy::let_and_return)]
+pub unsafe fn wrap_process_real<W, U>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
-    result: *mut c_char,
-    length: *mut c_ulong,
     is_null: *mut c_uchar,
     error: *mut c_uchar,
-) -> *const c_char
+) -> c_double
 where
     W: UdfConverter<U>,
-    for<'b> U: BasicUdf,
-    for<'a> <U as BasicUdf>::Returns<'a>: AsRef<[u8]>,
+    U: BasicUdf + RegisteredBasicUdf,
 {
-    log_call!(exit: "process", U, &*initid, &*args, result, &*length, &*is_null, &*error);
-
-    let cfg = UdfCfg::from_raw_ptr(initid);
-    let arglist = ArgList::from_raw_ptr(args);
-    let mut b = cfg.retrieve_box::<W>();
-    let err = *(error as *const Option<NonZeroU8>);
-    let binding = b.as_mut_ref();
-    let proc_res = U::process(binding, cfg, arglist, err);
-    let buf_opts = BufOptions::new(result, length);
-
-    let post_effects_val = ret_callback(proc_res, error, is_null);
-
-    let ret = match post_effects_val {
-        Some(ref v) => buf_result_callback(v, &buf_opts),
-        None => ptr::null(),
-    };
+    log_call!(enter: "process", U, &*initid, &*args, &*is_null, &*error);
 
-    std::mem::forget(post_effects_val);
-    cfg.store_box(b);
+    let ret = process_with::<W, U, _>(initid, args, error, |res, _| match res {
+        Some(SqlReturn::Real(v)) => {
+            *is_null = 0;
+            v
+        }
+        Some(SqlReturn::Null) => {
+            *is_null = 1;
+            0.0
+        }
+        Some(SqlReturn::Error) => {
+            *error = 1;
+            0.0
+        }
+        Some(other) => {
+            unexpected_return::<U>(&other, error);
+            0.0
+        }
+        None => 0.0,
+    });
 
-    log_call!(exit: "process", U, &*initid, &*args, result, &*length, &*is_null, &*error, ret);
+    log_call!(exit: "process", U, &*initid, &*args, &*is_null, &*error, ret);
     ret
 }
 
-/// Apply the `process` function for any implementation returning a buffer type
-/// (`Option<String>`, `Option<Vec<u8>>`, `Option<str>`, `Option<[u8]>`)
+/// Apply the `process` function for an implementation returning a string or a
+/// decimal
+///
+/// Results that fit are copied to the server's buffer. Larger borrowed results
+/// are returned by reference, and larger owned results are moved into the
+/// `BufConverter` so they remain valid until the next call. Decimals are
+/// formatted directly into the result buffer, which is always large enough
+/// for the longest possible `Decimal`.
 #[inline]
-pub unsafe fn wrap_process_buf_option<W, U, B>(
+pub unsafe fn wrap_process_buf<W, U>(
     initid: *mut UDF_INIT,
     args: *mut UDF_ARGS,
     result: *mut c_char,
@@ -167,64 +230,71 @@ pub unsafe fn wrap_process_buf_option<W, U, B>(
 ) -> *const c_char
 where
     W: UdfConverter<U>,
-    for<'a> U: BasicUdf<Returns<'a> = Option<B>>,
-    B: AsRef<[u8]>,
+    U: BasicUdf + RegisteredBasicUdf,
 {
     log_call!(enter: "process", U, &*initid, &*args, result, &*length, &*is_null, &*error);
 
-    let cfg = UdfCfg::from_raw_ptr(initid);
-    let arglist = ArgList::from_raw_ptr(args);
-    let err = *(error as *const Option<NonZeroU8>);
-    let mut b = cfg.retrieve_box::<W>();
-    let proc_res = U::process(b.as_mut_ref(), cfg, arglist, err);
+    let buf_len = *length as usize;
     let buf_opts = BufOptions::new(result, length);
 
-    let post_effects_val = ret_callback_option(proc_res, error, is_null);
-
-    let ret = match post_effects_val {
-        Some(ref v) => buf_result_callback(v, &buf_opts),
+    let ret = process_with::<W, U, _>(initid, args, error, |res, res_buf| match res {
+        Some(SqlReturn::Bytes(bytes)) => {
+            *is_null = 0;
+            bytes_result::<U>(bytes, res_buf, buf_len, &buf_opts)
+        }
+        Some(SqlReturn::Decimal { unscaled, scale }) => {
+            *is_null = 0;
+            let mut cursor = slice::from_raw_parts_mut(result.cast::<u8>(), buf_len);
+            if write!(cursor, "{}", FmtUnscaled(unscaled, scale)).is_ok() {
+                *length = (buf_len - cursor.len()) as c_ulong;
+                result.cast_const()
+            } else {
+                *error = 1;
+                ptr::null()
+            }
+        }
+        Some(SqlReturn::Null) => {
+            *is_null = 1;
+            ptr::null()
+        }
+        Some(SqlReturn::Error) => {
+            *error = 1;
+            ptr::null()
+        }
+        Some(other) => {
+            unexpected_return::<U>(&other, error);
+            ptr::null()
+        }
         None => ptr::null(),
-    };
-
-    std::mem::forget(post_effects_val);
-    cfg.store_box(b);
+    });
 
     log_call!(exit: "process", U, &*initid, &*args, result, &*length, &*is_null, &*error, ret);
     ret
 }
 
-#[inline]
-pub unsafe fn wrap_process_buf_option_ref<W, U, B>(
-    initid: *mut UDF_INIT,
-    args: *mut UDF_ARGS,
-    result: *mut c_char,
-    length: *mut c_ulong,
-    is_null: *mut c_uchar,
-    error: *mut c_uchar,
-) -> *const c_char
-where
-    W: UdfConverter<U>,
-    for<'a> U: BasicUdf<Returns<'a> = Option<&'a B>>,
-    B: AsRef<[u8]> + ?Sized,
-{
-    log_call!(enter: "process", U, &*initid, &*args, result, &*length, &*is_null, &*error);
-
-    let cfg = UdfCfg::from_raw_ptr(initid);
-    let arglist = ArgList::from_raw_ptr(args);
-    let err = *(error as *const Option<NonZeroU8>);
-    let mut b = cfg.retrieve_box::<W>();
-    let proc_res = U::process(b.as_mut_ref(), cfg, arglist, err);
-    let buf_opts = BufOptions::new(result, length);
-
-    let post_effects_val = ret_callback_option(proc_res, error, is_null);
-
-    let ret = match post_effects_val {
-        Some(ref v) => buf_result_callback(v, &buf_opts),
-        None => ptr::null(),
-    };
-
-    cfg.store_box(b);
-
-    log_call!(exit: "process", U, &*initid, &*args, result, &*length, &*is_null, &*error, ret);
-    ret
+/// Return a byte result. Owned data is kept in `res_buf` in case it does not
+/// fit in the server's buffer; without `res_buf`, it is truncated.
+unsafe fn bytes_result<U: RegisteredBasicUdf>(
+    bytes: Cow<'_, [u8]>,
+    res_buf: Option<&mut Vec<u8>>,
+    buf_len: usize,
+    opts: &BufOptions,
+) -> *const c_char {
+    match (bytes, res_buf) {
+        (Cow::Borrowed(b), _) => buf_result_callback(b, opts),
+        (Cow::Owned(v), Some(res_buf)) => {
+            *res_buf = v;
+            buf_result_callback(res_buf.as_slice(), opts)
+        }
+        (Cow::Owned(v), None) => {
+            if v.len() > buf_len {
+                udf_log!(
+                    Error: "'{}' result of {} bytes exceeds buffer of {buf_len}. Truncating",
+                    U::NAME,
+                    v.len()
+                );
+            }
+            buf_result_callback(&v[..min(v.len(), buf_len)], opts)
+        }
+    }
 }
@@ -0,0 +1,62 @@
+//! Pointers to the C functions that `#[register]` generates, so that
+//! [`MockServer`](crate::mock::MockServer) can call them the way the server
+//! does
+
+use std::ffi::{c_char, c_double, c_longlong, c_uchar, c_ulong};
+
+use udf_sys::{UDF_ARGS, UDF_INIT};
+
+/// Signature of `xxx_init`
+pub type InitFn = unsafe extern "C" fn(*mut UDF_INIT, *mut UDF_ARGS, *mut c_char) -> bool;
+
+/// Signature of `xxx_deinit`
+pub type DeinitFn = unsafe extern "C" fn(*mut UDF_INIT);
+
+/// Signature of `xxx` for integer results
+pub type ProcessIntFn =
+    unsafe extern "C" fn(*mut UDF_INIT, *mut UDF_ARGS, *mut c_uchar, *mut c_uchar) -> c_longlong;
+
+/// Signature of `xxx` for real results
+pub type ProcessRealFn =
+    unsafe extern "C" fn(*mut UDF_INIT, *mut UDF_ARGS, *mut c_uchar, *mut c_uchar) -> c_double;
+
+/// Signature of `xxx` for string and decimal results
+pub type ProcessBufFn = unsafe extern "C" fn(
+    *mut UDF_INIT,
+    *mut UDF_ARGS,
+    *mut c_char,
+    *mut c_ulong,
+    *mut c_uchar,
+    *mut c_uchar,
+) -> *const c_char;
+
+/// Signature of `xxx_add` and `xxx_remove`
+pub type AddFn = unsafe extern "C" fn(*mut UDF_INIT, *mut UDF_ARGS, *mut c_uchar, *mut c_uchar);
+
+/// Signature of `xxx_clear`
+pub type ClearFn = unsafe extern "C" fn(*mut UDF_INIT, *mut c_uchar, *mut c_uchar);
+
+/// The generated `xxx` function, which has a different signature for each
+/// result type
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum ProcessFn {
+    Int(ProcessIntFn),
+    Real(ProcessRealFn),
+    Buf(ProcessBufFn),
+}
+
+/// Implemented by the proc macro, pointing to the functions for the main name
+pub trait BasicSymbols {
+    const INIT: InitFn;
+    const PROCESS: ProcessFn;
+    const DEINIT: DeinitFn;
+}
+
+/// Implemented by the proc macro for `AggregateUdf`s. `REMOVE` is `None` if
+/// `remove` is not implemented.
+pub trait AggregateSymbols: BasicSymbols {
+    const CLEAR: ClearFn;
+    const ADD: AddFn;
+    const REMOVE: Option<AddFn>;
+}
@@ -1,6 +1,30 @@
 use super::*;
 use crate::prelude::*;
 
+/// Implement `RegisteredBasicUdf` as `#[register]` would, using the type name
+macro_rules! registered {
+    ($($ty:ident),*) => {$(
+        impl RegisteredBasicUdf for $ty {
+            const NAME: &'static str = stringify!($ty);
+            const ALIASES: &'static [&'static str] = &[];
+            const DEFAULT_NAME_USED: bool = true;
+        }
+    )*};
+}
+
+registered!(
+    ExampleInt,
+    ExampleIntOpt,
+    ExampleBufRef,
+    ExampleBufOpt,
+    ExampleBufOptRef,
+    ExamplePanic,
+    ExampleDecimal,
+    ExampleLongString,
+    ExampleError,
+    ExampleAggError
+);
+
 struct ExampleInt;
 struct ExampleIntOpt;
 struct ExampleBufRef;
@@ -117,10 +141,10 @@ fn test_fn_sig() {
     // Just validate our function signatures with compile tests
 
     unsafe {
-        wrap_process_basic::<ExampleInt, _, _>(todo!(), todo!(), todo!(), todo!());
-        wrap_process_basic_option::<ExampleIntOpt, _, _>(todo!(), todo!(), todo!(), todo!());
+        wrap_process_int::<ExampleInt, _>(todo!(), todo!(), todo!(), todo!());
+        wrap_process_int::<ExampleIntOpt, _>(todo!(), todo!(), todo!(), todo!());
         wrap_process_buf::<ExampleBufRef, _>(todo!(), todo!(), todo!(), todo!(), todo!(), todo!());
-        wrap_process_buf_option::<ExampleBufOpt, _, _>(
+        wrap_process_buf::<BufConverter<ExampleBufOpt>, ExampleBufOpt>(
             todo!(),
             todo!(),
             todo!(),
@@ -128,7 +152,7 @@ fn test_fn_sig() {
             todo!(),
             todo!(),
         );
-        wrap_process_buf_option_ref::<ExampleBufOptRef, _, _>(
+        wrap_process_buf::<ExampleBufOptRef, _>(
             todo!(),
             todo!(),
             todo!(),
@@ -214,3 +238,592 @@ fn test_verify_aggregate_attributes_mismatch_aliases() {
 
     verify_aggregate_attributes::<Foo>();
 }
+
+/// A UDF that panics in every function
+struct ExamplePanic;
+
+impl BasicUdf for ExamplePanic {
+    type Returns<'a> = i64;
+
+    fn init(_cfg: &UdfCfg<crate::Init>, args: &ArgList<crate::Init>) -> Result<Self, String> {
+        if args.is_empty() {
+            Ok(Self)
+        } else {
+            panic!("init panic")
+        }
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        _args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        panic!("process panic")
+    }
+}
+
+impl AggregateUdf for ExamplePanic {
+    fn clear(
+        &mut self,
+        _cfg: &UdfCfg<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        panic!("clear panic")
+    }
+
+    fn add(
+        &mut self,
+        _cfg: &UdfCfg<Process>,
+        _args: &ArgList<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        panic!("add panic")
+    }
+}
+
+#[test]
+fn test_init_panic() {
+    use std::ffi::{c_char, CStr};
+
+    use crate::mock::MockUdfCfg;
+    use crate::{mock_args, MYSQL_ERRMSG_SIZE};
+
+    let mut cfg = MockUdfCfg::new();
+    let mut args = mock_args![(Int 1, "", false)];
+    let mut msg: [c_char; MYSQL_ERRMSG_SIZE] = [0; MYSQL_ERRMSG_SIZE];
+
+    let initid = cfg.as_init().0.get();
+    let args_ptr = (args.as_init() as *const ArgList<crate::Init>).cast_mut();
+
+    let res = unsafe {
+        wrap_init::<ExamplePanic, ExamplePanic>(initid, args_ptr.cast(), msg.as_mut_ptr())
+    };
+    let msg = unsafe { CStr::from_ptr(msg.as_ptr()) };
+
+    assert!(res);
+    assert_eq!(msg.to_str().unwrap(), "init panicked: init panic");
+}
+
+#[test]
+fn test_process_panic() {
+    use std::ffi::c_uchar;
+
+    use crate::mock::{MockArgList, MockUdfCfg};
+
+    let mut cfg = MockUdfCfg::new();
+    let mut args = MockArgList::new();
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar = 0;
+
+    let initid = cfg.as_init().0.get();
+    let args_ptr = (args.as_init() as *const ArgList<crate::Init>).cast_mut();
+
+    unsafe {
+        assert!(!wrap_init::<ExamplePanic, ExamplePanic>(
+            initid,
+            args_ptr.cast(),
+            msg.as_mut_ptr()
+        ));
+
+        let res = wrap_process_int::<ExamplePanic, ExamplePanic>(
+            initid,
+            args_ptr.cast(),
+            &mut is_null,
+            &mut error,
+        );
+        assert_eq!(res, 0);
+        assert_eq!(error, 1);
+
+        // Aggregate functions should also set the error, and the box must still
+        // be valid afterward
+        error = 0;
+        wrap_clear::<ExamplePanic, ExamplePanic>(initid, &mut is_null, &mut error);
+        assert_eq!(error, 1);
+
+        error = 0;
+        wrap_add::<ExamplePanic, ExamplePanic>(initid, args_ptr.cast(), &mut is_null, &mut error);
+        assert_eq!(error, 1);
+
+        wrap_deinit::<ExamplePanic, ExamplePanic>(initid);
+    }
+}
+
+/// `add` fails for rows with an argument, everything else succeeds
+struct ExampleAggError;
+
+impl BasicUdf for ExampleAggError {
+    type Returns<'a> = i64;
+
+    fn init(_cfg: &UdfCfg<crate::Init>, _args: &ArgList<crate::Init>) -> Result<Self, String> {
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        _args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Ok(0)
+    }
+}
+
+impl AggregateUdf for ExampleAggError {
+    fn clear(
+        &mut self,
+        _cfg: &UdfCfg<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        Ok(())
+    }
+
+    fn add(
+        &mut self,
+        _cfg: &UdfCfg<Process>,
+        args: &ArgList<Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<(), NonZeroU8> {
+        if args.is_empty() {
+            Ok(())
+        } else {
+            Err(NonZeroU8::new(2).unwrap())
+        }
+    }
+}
+
+#[test]
+fn test_aggregate_error_flag() {
+    use std::ffi::c_uchar;
+
+    use crate::mock::{MockArgList, MockUdfCfg};
+    use crate::mock_args;
+
+    let mut cfg = MockUdfCfg::new();
+    let mut no_args = MockArgList::new();
+    let mut one_arg = mock_args![(Int 1, "", false)];
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar = 0;
+
+    let initid = cfg.as_init().0.get();
+    let no_args_ptr = (no_args.as_init() as *const ArgList<crate::Init>).cast_mut();
+    let one_arg_ptr = (one_arg.as_init() as *const ArgList<crate::Init>).cast_mut();
+
+    unsafe {
+        assert!(!wrap_init::<ExampleAggError, ExampleAggError>(
+            initid,
+            no_args_ptr.cast(),
+            msg.as_mut_ptr()
+        ));
+
+        wrap_add::<ExampleAggError, ExampleAggError>(
+            initid,
+            one_arg_ptr.cast(),
+            &mut is_null,
+            &mut error,
+        );
+        assert_eq!(error, 2);
+
+        // A later row that succeeds does not clear the error for the group
+        wrap_add::<ExampleAggError, ExampleAggError>(
+            initid,
+            no_args_ptr.cast(),
+            &mut is_null,
+            &mut error,
+        );
+        assert_eq!(error, 2);
+
+        // The server keeps the flag across groups; `Ok` from `clear` resets it
+        wrap_clear::<ExampleAggError, ExampleAggError>(initid, &mut is_null, &mut error);
+        assert_eq!(error, 0);
+
+        wrap_deinit::<ExampleAggError, ExampleAggError>(initid);
+    }
+}
+
+/// Returns `1.50` for one argument, `NULL` otherwise
+struct ExampleDecimal;
+
+impl BasicUdf for ExampleDecimal {
+    type Returns<'a> = Option<Decimal<4, 2>>;
+
+    fn init(cfg: &UdfCfg<crate::Init>, _args: &ArgList<crate::Init>) -> Result<Self, String> {
+        // Defaults should already be set
+        assert_eq!(cfg.get_max_len(), 6);
+        assert_eq!(cfg.get_decimals(), 2);
+        assert!(cfg.get_maybe_null());
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Ok((args.len() == 1).then(|| Decimal::from_unscaled(150).unwrap()))
+    }
+}
+
+#[test]
+fn test_process_decimal() {
+    use std::ffi::{c_char, c_uchar, c_ulong};
+
+    use crate::mock::{MockArgList, MockUdfCfg};
+    use crate::{mock_args, MYSQL_RESULT_BUFFER_SIZE};
+
+    let mut cfg = MockUdfCfg::new();
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut result = [0 as c_char; MYSQL_RESULT_BUFFER_SIZE];
+    let mut length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar = 0;
+
+    let initid = cfg.as_init().0.get();
+    let mut args = mock_args![(Int 1, "", false)];
+    let args_ptr = (args.as_init() as *const ArgList<crate::Init>).cast_mut();
+
+    unsafe {
+        assert!(!wrap_init::<ExampleDecimal, ExampleDecimal>(
+            initid,
+            args_ptr.cast(),
+            msg.as_mut_ptr()
+        ));
+
+        let res = wrap_process_buf::<ExampleDecimal, ExampleDecimal>(
+            initid,
+            args_ptr.cast(),
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+        let out = std::slice::from_raw_parts(res.cast::<u8>(), length as usize);
+        assert_eq!(out, b"1.50");
+        assert_eq!((is_null, error), (0, 0));
+
+        // No arguments gives `NULL`
+        let mut no_args = MockArgList::new();
+        let no_args_ptr = (no_args.as_process() as *const ArgList<crate::Process>).cast_mut();
+        let res = wrap_process_buf::<ExampleDecimal, ExampleDecimal>(
+            initid,
+            no_args_ptr.cast(),
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+        assert!(res.is_null());
+        assert_eq!((is_null, error), (1, 0));
+
+        wrap_deinit::<ExampleDecimal, ExampleDecimal>(initid);
+    }
+}
+
+/// Returns a string that is too long for the result buffer
+struct ExampleLongString;
+
+impl BasicUdf for ExampleLongString {
+    type Returns<'a> = Box<str>;
+
+    fn init(_cfg: &UdfCfg<crate::Init>, _args: &ArgList<crate::Init>) -> Result<Self, String> {
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        _args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Ok("x".repeat(300).into())
+    }
+}
+
+#[test]
+fn test_process_owned_too_long() {
+    use std::ffi::{c_char, c_uchar, c_ulong};
+
+    use crate::mock::{MockArgList, MockUdfCfg};
+    use crate::MYSQL_RESULT_BUFFER_SIZE;
+
+    type Wrapper = BufConverter<ExampleLongString>;
+
+    let mut cfg = MockUdfCfg::new();
+    *cfg.max_len() = 300;
+    let mut args = MockArgList::new();
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut result = [0 as c_char; MYSQL_RESULT_BUFFER_SIZE];
+    let mut length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar = 0;
+
+    let initid = cfg.as_init().0.get();
+    let args_ptr = (args.as_init() as *const ArgList<crate::Init>).cast_mut();
+
+    unsafe {
+        assert!(!wrap_init::<Wrapper, ExampleLongString>(
+            initid,
+            args_ptr.cast(),
+            msg.as_mut_ptr()
+        ));
+
+        let res = wrap_process_buf::<Wrapper, ExampleLongString>(
+            initid,
+            args_ptr.cast(),
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+
+        // The full result is returned from the wrapper's buffer
+        assert_ne!(res, result.as_ptr());
+        let out = std::slice::from_raw_parts(res.cast::<u8>(), length as usize);
+        assert_eq!(out, "x".repeat(300).as_bytes());
+        assert_eq!((is_null, error), (0, 0));
+
+        wrap_deinit::<Wrapper, ExampleLongString>(initid);
+    }
+}
+
+/// Always fails in `process`
+struct ExampleError;
+
+impl BasicUdf for ExampleError {
+    type Returns<'a> = i64;
+
+    fn init(_cfg: &UdfCfg<crate::Init>, _args: &ArgList<crate::Init>) -> Result<Self, String> {
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        _args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Err(ProcessError::msg("always fails").with_kind(ProcessErrorKind::OutOfRange))
+    }
+}
+
+#[test]
+fn test_process_error() {
+    use std::ffi::c_uchar;
+
+    use crate::mock::{MockArgList, MockUdfCfg};
+
+    let mut cfg = MockUdfCfg::new();
+    let mut args = MockArgList::new();
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar;
+
+    let initid = cfg.as_init().0.get();
+    let args_ptr = (args.as_init() as *const ArgList<crate::Init>).cast_mut();
+
+    unsafe {
+        assert!(!wrap_init::<ExampleError, ExampleError>(
+            initid,
+            args_ptr.cast(),
+            msg.as_mut_ptr()
+        ));
+
+        // Repeated errors are rate limited but must still set the flag
+        for _ in 0..20 {
+            error = 0;
+            let res = wrap_process_int::<ExampleError, ExampleError>(
+                initid,
+                args_ptr.cast(),
+                &mut is_null,
+                &mut error,
+            );
+            assert_eq!(res, 0);
+            assert_eq!(error, 1);
+        }
+
+        wrap_deinit::<ExampleError, ExampleError>(initid);
+    }
+}
+
+/// Echoes its argument, with `max_len` set from the argument and enforced
+struct ExampleCapped;
+
+impl RegisteredBasicUdf for ExampleCapped {
+    const NAME: &'static str = "ExampleCapped";
+    const ALIASES: &'static [&'static str] = &[];
+    const DEFAULT_NAME_USED: bool = true;
+    const ENFORCE_MAX_LEN: bool = true;
+}
+
+impl BasicUdf for ExampleCapped {
+    type Returns<'a> = Vec<u8>;
+
+    fn init(cfg: &UdfCfg<crate::Init>, args: &ArgList<crate::Init>) -> Result<Self, String> {
+        cfg.set_max_len(args.total_max_len());
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Ok(args.get(0).unwrap().value().as_bytes().unwrap().to_vec())
+    }
+}
+
+#[test]
+fn test_process_enforce_max_len() {
+    use std::ffi::{c_char, c_uchar, c_ulong};
+
+    use crate::mock::{MockArgList, MockUdfCfg};
+    use crate::{mock_args, MYSQL_RESULT_BUFFER_SIZE};
+
+    type Wrapper = BufConverter<ExampleCapped>;
+
+    let mut cfg = MockUdfCfg::new();
+    let mut init_args = mock_args![(String "abcd", "s", false)];
+    let mut short = mock_args![(String "abc", "s", false)];
+    let mut long = mock_args![(String "abcdef", "s", false)];
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut result = [0 as c_char; MYSQL_RESULT_BUFFER_SIZE];
+    let mut length: c_ulong;
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar = 0;
+
+    let initid = cfg.as_init().0.get();
+    let ptr = |args: &mut MockArgList| {
+        (args.as_init() as *const ArgList<crate::Init>)
+            .cast_mut()
+            .cast()
+    };
+
+    unsafe {
+        assert!(!wrap_init::<Wrapper, ExampleCapped>(
+            initid,
+            ptr(&mut init_args),
+            msg.as_mut_ptr()
+        ));
+        assert_eq!(*cfg.max_len(), 4);
+        assert!(*cfg.maybe_null());
+
+        length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+        let res = wrap_process_buf::<Wrapper, ExampleCapped>(
+            initid,
+            ptr(&mut short),
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+        let out = std::slice::from_raw_parts(res.cast::<u8>(), length as usize);
+        assert_eq!(out, b"abc");
+        assert_eq!(error, 0);
+
+        // Longer than `max_len`, so this row is `NULL` rather than a result
+        length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+        let res = wrap_process_buf::<Wrapper, ExampleCapped>(
+            initid,
+            ptr(&mut long),
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+        assert!(res.is_null());
+        assert_eq!((is_null, error), (1, 0));
+
+        // Later rows are unaffected
+        length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+        let res = wrap_process_buf::<Wrapper, ExampleCapped>(
+            initid,
+            ptr(&mut short),
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+        let out = std::slice::from_raw_parts(res.cast::<u8>(), length as usize);
+        assert_eq!(out, b"abc");
+        assert_eq!((is_null, error), (0, 0));
+
+        wrap_deinit::<Wrapper, ExampleCapped>(initid);
+    }
+}
+
+/// Returns the name of the call that the logger sees
+#[cfg(feature = "log")]
+struct ExampleCallName;
+
+#[cfg(feature = "log")]
+impl RegisteredBasicUdf for ExampleCallName {
+    const NAME: &'static str = "call_name";
+    const ALIASES: &'static [&'static str] = &[];
+    const DEFAULT_NAME_USED: bool = false;
+}
+
+#[cfg(feature = "log")]
+impl BasicUdf for ExampleCallName {
+    type Returns<'a> = &'static str;
+
+    fn init(cfg: &UdfCfg<crate::Init>, _args: &ArgList<crate::Init>) -> Result<Self, String> {
+        cfg.set_max_len(64);
+        Ok(Self)
+    }
+
+    fn process<'a>(
+        &'a mut self,
+        _cfg: &UdfCfg<crate::Process>,
+        _args: &ArgList<crate::Process>,
+        _error: Option<NonZeroU8>,
+    ) -> Result<Self::Returns<'a>, ProcessError> {
+        Ok(crate::logger::current_call().map_or("", |(name, _)| name))
+    }
+}
+
+#[test]
+#[cfg(feature = "log")]
+fn test_call_scope_name() {
+    use std::ffi::{c_char, c_uchar, c_ulong};
+
+    use crate::mock::MockUdfCfg;
+    use crate::{mock_args, MYSQL_RESULT_BUFFER_SIZE};
+
+    let mut cfg = MockUdfCfg::new();
+    let mut args = mock_args![];
+    let mut msg = [0; crate::MYSQL_ERRMSG_SIZE];
+    let mut result = [0 as c_char; MYSQL_RESULT_BUFFER_SIZE];
+    let mut length = MYSQL_RESULT_BUFFER_SIZE as c_ulong;
+    let mut is_null: c_uchar = 0;
+    let mut error: c_uchar = 0;
+
+    let initid = cfg.as_init().0.get();
+    let args_ptr = (args.as_init() as *const ArgList<crate::Init>)
+        .cast_mut()
+        .cast();
+
+    unsafe {
+        assert!(!wrap_init::<ExampleCallName, ExampleCallName>(
+            initid,
+            args_ptr,
+            msg.as_mut_ptr()
+        ));
+        let res = wrap_process_buf::<ExampleCallName, ExampleCallName>(
+            initid,
+            args_ptr,
+            result.as_mut_ptr(),
+            &mut length,
+            &mut is_null,
+            &mut error,
+        );
+        assert_eq!(error, 0);
+        let out = std::slice::from_raw_parts(res.cast::<u8>(), length as usize);
+        // The registered name, not the Rust type path
+        assert_eq!(out, b"call_name");
+        wrap_deinit::<ExampleCallName, ExampleCallName>(initid);
+    }
+}